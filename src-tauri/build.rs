@@ -1,3 +1,59 @@
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 fn main() {
+    emit_build_metadata();
     tauri_build::build()
 }
+
+// Exposes CEREBRO_GIT_COMMIT and CEREBRO_BUILD_TIMESTAMP to the crate for `mcp_app_info`.
+fn emit_build_metadata() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
+
+    let commit = Command::new("git")
+        .args([
+            "-C",
+            manifest_dir.as_str(),
+            "rev-parse",
+            "--short=12",
+            "HEAD",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=CEREBRO_GIT_COMMIT={commit}");
+
+    // Honour SOURCE_DATE_EPOCH so reproducible builds don't embed the wall clock.
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let timestamp_ms = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|seconds| seconds * 1000)
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or(0)
+        });
+    println!("cargo:rustc-env=CEREBRO_BUILD_TIMESTAMP={timestamp_ms}");
+
+    // Re-run when HEAD moves; watching a missing path would force a rebuild every time.
+    let git_dir = Path::new(&manifest_dir).join("../.git");
+    let head = git_dir.join("HEAD");
+    if head.is_file() {
+        println!("cargo:rerun-if-changed={}", head.display());
+        let branch = fs::read_to_string(&head).unwrap_or_default();
+        if let Some(reference) = branch.trim().strip_prefix("ref: ") {
+            let path = git_dir.join(reference);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+}
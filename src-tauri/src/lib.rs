@@ -1,16 +1,26 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     env, fs,
-    io::{BufRead, BufReader, Write},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
     path::{Component, Path, PathBuf},
-    process::Command,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex, OnceLock,
+    },
     thread,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use portable_pty::{native_pty_system, Child as PtyChild, CommandBuilder, MasterPty, PtySize};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
 use sysinfo::{CpuExt, DiskExt, System, SystemExt};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 type McpResult<T> = Result<T, String>;
 
@@ -21,10 +31,170 @@ const ALLOWED_SHELL_COMMANDS: &[&str] = &[
     "python", "pip", "pip3", "just", "make", "rg",
 ];
 const DEFAULT_SHELL_TIMEOUT_MS: u64 = 60_000;
+// Same as coreutils `timeout`.
+const SHELL_TIMEOUT_EXIT_CODE: i32 = 124;
+// How long to keep reading output after a timed-out child is killed, in case a
+// descendant outside its process group still holds the pipes open.
+const KILLED_OUTPUT_GRACE_MS: u64 = 2_000;
+const DEFAULT_DETACHED_RESTARTS: u32 = 5;
+const MAX_DETACHED_RESTARTS: u32 = 100;
+const DETACHED_RESTART_DELAY_MS: u64 = 1_000; // keeps a command that fails instantly from spinning
+const VERSION_PROBE_TIMEOUT_MS: u64 = 3_000;
+const VERSION_PROBE_PARALLELISM: usize = 4;
+const ORBIT_PROJECT_GIT_TIMEOUT_MS: u64 = 3_000;
+const ORBIT_PROJECT_PARALLELISM: usize = 4;
 const MAX_METRICS_FILE_SIZE: u64 = 2 * 1024 * 1024; // 2 MiB
 const MAX_METRICS_FILE_AGE_SECS: u64 = 7 * 24 * 60 * 60; // 7 days
+const METRICS_RANGE_TAIL_BYTES: u64 = 256 * 1024;
+// sysinfo needs at least ~200 ms between CPU refreshes to report usage.
+const MIN_SYSTEM_DELTA_MS: u64 = 250;
+const MAX_SYSTEM_DELTA_MS: u64 = 60_000;
+const SYSTEM_DELTA_SLACK_MS: u64 = 5_000;
+const MAX_METRICS_RANGE_ENTRIES: usize = 100_000;
+const IGNORE_FILE_NAME: &str = ".cerebroignore";
+const MAX_STAT_BATCH: usize = 1_000;
+const MAX_MTIME_FUTURE_MS: u64 = 24 * 60 * 60 * 1000; // tolerance for clock skew between machines
+const SECRET_KEY_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    "CREDENTIAL",
+    "AUTH",
+];
+const REDACTED_VALUE: &str = "***";
+const MAX_WAIT_FOR_MS: u64 = 10 * 60 * 1000;
+const DEFAULT_TREE_DEPTH: usize = 4;
+const MAX_TREE_DEPTH: usize = 32;
+const MAX_TREE_NODES: usize = 5_000;
+const DEFAULT_MAX_PATH_DEPTH: usize = 64;
+const DEFAULT_LIST_STREAM_BATCH: usize = 200;
+const DEFAULT_LIST_MAX_ENTRIES: usize = 10_000;
+const MAX_LIST_MAX_ENTRIES: usize = 100_000;
+const MAX_LIST_STREAM_BATCH: usize = 5_000;
+const DEFAULT_SEARCH_MATCHES: usize = 1_000;
+const MAX_SEARCH_MATCHES: usize = 20_000;
+const MAX_SEARCH_FILE_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_SEARCH_SNIPPET_CHARS: usize = 240;
+const SEARCH_PROGRESS_EVERY: usize = 100; // files scanned between `search://progress` events
+const DEFAULT_MANIFEST_ENTRIES: usize = 10_000;
+const MAX_MANIFEST_ENTRIES: usize = 100_000;
+const MANIFEST_PROGRESS_EVERY: usize = 100; // files hashed between `manifest://progress` events
+const DEFAULT_GIT_BLAME_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_GIT_FSCK_TIMEOUT_MS: u64 = 120_000;
+const MAX_GIT_FSCK_TIMEOUT_MS: u64 = 30 * 60 * 1000;
+// Backups mirror the orbit layout under this folder: `a/b.txt` -> `.cerebro-backups/a/b.txt.bak-<ms>`.
+const BACKUP_DIR_NAME: &str = ".cerebro-backups";
+const BACKUP_SUFFIX: &str = ".bak-";
+const MAX_DECOMPRESSED_READ_BYTES: u64 = 64 * 1024 * 1024;
+const MIN_READ_CHUNK_BYTES: usize = 1024;
+const DEFAULT_READ_CHUNK_BYTES: usize = 256 * 1024;
+const MAX_READ_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+const CHARSET_SAMPLE_BYTES: usize = 64 * 1024;
+const INDENT_SAMPLE_BYTES: usize = 64 * 1024;
+const DEFAULT_INDENT_SIZE: u8 = 4;
+const MAX_LINE_INDEX_ENTRIES: usize = 1_000_000;
+const MAX_READ_AROUND_CONTEXT: usize = 500;
+const MAX_RENAME_BATCH: usize = 5_000;
+const MAX_NORMALIZE_FILES: usize = 10_000;
+const MAX_TEXT_STATS_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_BENCHMARK_SIZE_MB: u64 = 1024;
+const MAX_NORMALIZE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_LOC_FILES: usize = 20_000;
+// Larger sources are almost always generated.
+const MAX_LOC_FILE_BYTES: u64 = 2 * 1024 * 1024;
+// Kept from the parent when `clean_env` is set; `SystemRoot` is needed by most
+// Windows binaries and is simply absent elsewhere.
+const CLEAN_ENV_VARS: &[&str] = &["PATH", "HOME", "LANG", "SystemRoot"];
+const MAX_HIGHLIGHT_BYTES: u64 = 1024 * 1024;
+const MAX_HIGHLIGHT_TOKENS: usize = 100_000;
+// Scope prefix → token kind. Checked in order, so narrower prefixes come first.
+const SCOPE_KINDS: &[(&str, &str)] = &[
+    ("punctuation.definition.comment", "comment"),
+    ("punctuation.definition.string", "string"),
+    ("comment", "comment"),
+    ("string", "string"),
+    ("constant.numeric", "number"),
+    ("constant", "constant"),
+    ("keyword.operator", "operator"),
+    ("keyword", "keyword"),
+    ("storage", "keyword"),
+    ("entity.name.type", "type"),
+    ("entity.name.class", "type"),
+    ("support.type", "type"),
+    ("support.class", "type"),
+    ("entity.name.function", "function"),
+    ("support.function", "function"),
+    ("variable.function", "function"),
+    ("entity.name.tag", "tag"),
+    ("entity.other.attribute-name", "attribute"),
+    ("variable", "variable"),
+    ("punctuation", "punctuation"),
+];
+const MAX_INDENT_SIZE: usize = 16;
+const MAX_TYPED_VALUES: usize = 65_536;
+const MAX_DIFF_CELLS: usize = 4_000_000; // LCS table cells before falling back to coarser diffs
+const UNIFIED_DIFF_CONTEXT: usize = 3;
+const MAX_LONG_LINES_REPORTED: usize = 1_000;
+const MAX_MERGE_JSON_FILES: usize = 64;
+const GIT_WATCH_DEBOUNCE_MS: u64 = 300;
+const MAX_GIT_REF_LENGTH: usize = 255;
+// Keys `mcp_git_config_set` may write; anything else (e.g. `core.sshCommand`) could run code.
+const GIT_CONFIG_SETTABLE_KEYS: &[&str] = &["user.name", "user.email"];
+const MAX_GIT_DIFF_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_PTY_COLS: u16 = 80;
+const DEFAULT_PTY_ROWS: u16 = 24;
+const DEFAULT_APP_LOG_LINES: usize = 200;
+const MAX_APP_LOG_LINES: usize = 5_000;
+const MAX_APP_LOG_TAIL_BYTES: u64 = 1024 * 1024;
+const SUPPORT_REPORT_LOG_LINES: usize = 200;
+const SUPPORT_REPORT_METRICS: usize = 500;
+const DEFAULT_TAIL_JSON_LINES: usize = 100;
+const MAX_TAIL_JSON_LINES: usize = 10_000;
+const MAX_TAIL_JSON_BYTES: u64 = 32 * 1024 * 1024;
+const TAIL_BLOCK_BYTES: u64 = 64 * 1024;
+// Secondary windows get this label prefix so capabilities can match them with `project-*`.
+const PROJECT_WINDOW_PREFIX: &str = "project-";
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 3.0;
+const QR_MODULE_PIXELS: usize = 8;
+const QR_QUIET_ZONE: usize = 4;
+const DEFAULT_NANOID_SIZE: usize = 21;
+const MAX_NANOID_SIZE: usize = 256;
+// Kernel and virtual filesystems that hold no user data.
+const PSEUDO_FS_TYPES: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+const NANOID_ALPHABET: &[u8; 64] =
+    b"_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct FileEntry {
     name: String,
     path: String,
@@ -38,781 +208,10478 @@ struct FileEntry {
 #[derive(Serialize)]
 struct ListResponse {
     entries: Vec<FileEntry>,
+    // Only a recursive listing can stop early, when it reaches `max_entries`.
+    truncated: bool,
 }
 
 #[derive(Serialize)]
-struct ReadResponse {
+struct NormalizeDirResponse {
     path: String,
-    encoding: String,
-    content: String,
+    target: String,
+    #[serde(rename = "dryRun")]
+    dry_run: bool,
+    scanned: usize,
+    changed: usize,
+    // Binary or oversized files that were left untouched.
+    skipped: usize,
+    files: Vec<String>,
+    truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycles: Vec<String>,
 }
 
 #[derive(Serialize)]
-struct WriteResponse {
-    path: String,
-    bytes: usize,
-    created: bool,
+struct ListingDiffResponse {
+    added: Vec<FileEntry>,
+    removed: Vec<FileEntry>,
+    modified: Vec<FileEntry>,
 }
 
 #[derive(Serialize)]
-struct ExecResponse {
-    command: String,
-    args: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    cwd: Option<String>,
-    #[serde(rename = "exitCode")]
-    exit_code: i32,
-    stdout: String,
-    stderr: String,
-    #[serde(rename = "durationMs")]
-    duration_ms: u128,
+struct EnvLineError {
+    line: usize,
+    message: String,
 }
 
 #[derive(Serialize)]
-struct FilesInfoResponse {
-    root: String,
-    exists: bool,
+struct EnvFileResponse {
+    path: String,
+    values: BTreeMap<String, String>,
+    errors: Vec<EnvLineError>,
+    redacted: Vec<String>,
 }
 
 #[derive(Serialize)]
-struct ShellCapabilities {
-    allowed_commands: Vec<String>,
-    #[serde(rename = "defaultTimeoutMs")]
-    default_timeout_ms: u64,
+struct WaitForResponse {
+    path: String,
+    #[serde(rename = "elapsedMs")]
+    elapsed_ms: u128,
+    // True when the file was already there before the watch started.
+    existing: bool,
 }
 
 #[derive(Serialize)]
-struct GitInfoResponse {
-    version: Option<String>,
-    root: String,
+struct SetMtimeResponse {
+    path: String,
+    #[serde(rename = "modifiedAt")]
+    modified_at: u64,
 }
 
 #[derive(Serialize)]
-struct MemoryInfo {
-    total: u64,
-    used: u64,
-    free: u64,
-    #[serde(rename = "usagePercent", skip_serializing_if = "Option::is_none")]
-    usage_percent: Option<f32>,
-    #[serde(rename = "swapTotal")]
-    swap_total: u64,
-    #[serde(rename = "swapUsed")]
-    swap_used: u64,
+#[cfg_attr(not(unix), allow(dead_code))]
+struct OwnerResponse {
+    path: String,
+    uid: u32,
+    gid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    // Permission bits in octal, e.g. "644".
+    mode: String,
+    #[serde(rename = "ownedByCurrentUser", skip_serializing_if = "Option::is_none")]
+    owned_by_current_user: Option<bool>,
 }
 
 #[derive(Serialize)]
-struct CpuInfo {
+struct StatBatchItem {
+    // The path exactly as requested, so callers can match results to inputs.
+    path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    model: Option<String>,
-    #[serde(rename = "logicalCores")]
-    logical_cores: usize,
-    #[serde(rename = "globalUsage", skip_serializing_if = "Option::is_none")]
-    global_usage: Option<f32>,
+    entry: Option<FileEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
 #[derive(Serialize)]
-struct DiskInfo {
-    mount: String,
-    total: u64,
-    free: u64,
-    #[serde(rename = "usagePercent", skip_serializing_if = "Option::is_none")]
-    usage_percent: Option<f32>,
+struct TreeNode {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+    // `None` when the directory was not expanded because of the depth limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<TreeNode>>,
 }
 
 #[derive(Serialize)]
-struct SystemInfoResponse {
-    #[serde(rename = "timestampMs")]
-    timestamp_ms: u64,
-    hostname: Option<String>,
-    os: String,
-    #[serde(rename = "osVersion", skip_serializing_if = "Option::is_none")]
-    os_version: Option<String>,
-    #[serde(rename = "kernelVersion", skip_serializing_if = "Option::is_none")]
-    kernel_version: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    architecture: Option<String>,
-    memory: MemoryInfo,
-    cpu: CpuInfo,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    disk: Option<DiskInfo>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    uptime: Option<u64>,
-    #[serde(rename = "processCount", skip_serializing_if = "Option::is_none")]
-    process_count: Option<usize>,
+struct TreeResponse {
+    tree: TreeNode,
+    nodes: usize,
+    truncated: bool,
+    // Directories not descended into because the walk had already been there.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycles: Vec<String>,
 }
 
 #[derive(Serialize)]
-struct SystemPathsResponse {
-    home: String,
-    #[serde(rename = "safeOrbit")]
-    safe_orbit: String,
+struct TreeMarkdownResponse {
+    markdown: String,
+    nodes: usize,
+    truncated: bool,
 }
 
-#[derive(Serialize, Deserialize)]
-struct MetricsEntry {
-    #[serde(alias = "timestamp")]
-    ts: u64,
-    mode: String,
-    provider: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    model: Option<String>,
-    #[serde(
-        rename = "latency_ms",
-        alias = "latencyMs",
-        skip_serializing_if = "Option::is_none"
-    )]
-    latency_ms: Option<u64>,
-    #[serde(
-        rename = "prompt_tokens",
-        alias = "promptTokens",
-        alias = "tokensIn",
-        skip_serializing_if = "Option::is_none"
-    )]
-    prompt_tokens: Option<u32>,
-    #[serde(
-        rename = "output_tokens",
-        alias = "outputTokens",
-        alias = "tokensOut",
-        skip_serializing_if = "Option::is_none"
-    )]
-    output_tokens: Option<u32>,
-    success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
 }
 
-fn safe_root() -> McpResult<PathBuf> {
-    let home = resolve_home_dir()?;
-    let root = home.join(SAFE_ORBIT_RELATIVE);
-    if !root.exists() {
-        fs::create_dir_all(&root).map_err(|err| err.to_string())?;
-    }
-    Ok(root)
-}
+impl IgnorePattern {
+    // Parses one line using gitignore syntax; comments and blanks yield `None`.
+    fn parse(line: &str) -> Option<IgnorePattern> {
+        let line = line.trim_end_matches('\r');
+        let line = if line.ends_with("\\ ") {
+            line
+        } else {
+            line.trim_end()
+        };
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
 
-fn resolve_home_dir() -> McpResult<PathBuf> {
-    if let Ok(home) = env::var("HOME") {
-        return Ok(PathBuf::from(home));
+        let (negated, body) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let body = match body.strip_prefix('\\') {
+            Some(rest) if rest.starts_with('#') || rest.starts_with('!') => rest,
+            _ => body,
+        };
+        let dir_only = body.ends_with('/');
+        let body = body.trim_end_matches('/');
+        let anchored = body.contains('/');
+        let glob = body.strip_prefix('/').unwrap_or(body);
+        if glob.is_empty() {
+            return None;
+        }
+
+        Some(IgnorePattern {
+            glob: glob.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
     }
 
-    if cfg!(target_os = "windows") {
-        if let Ok(profile) = env::var("USERPROFILE") {
-            return Ok(PathBuf::from(profile));
+    fn matches(&self, path_in_base: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            glob_match(&self.glob, path_in_base)
+        } else {
+            let name = path_in_base.rsplit('/').next().unwrap_or(path_in_base);
+            glob_match(&self.glob, name)
         }
     }
+}
 
-    Err("No se pudo resolver el directorio home del usuario.".into())
+struct IgnoreRule {
+    // Orbit-relative directory holding the ignore file ("" for the orbit root).
+    base: String,
+    pattern: IgnorePattern,
 }
 
-fn build_path(root: &Path, input: Option<&str>) -> McpResult<PathBuf> {
-    match input {
-        Some(value) => sanitize_relative_path(root, Path::new(value)),
-        None => Ok(root.to_path_buf()),
-    }
+#[derive(Default)]
+struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
 }
 
-fn sanitize_relative_path(root: &Path, path: &Path) -> McpResult<PathBuf> {
-    if path.is_absolute() {
-        let stripped = path
-            .strip_prefix(root)
-            .map_err(|_| "Ruta fuera de la órbita segura.".to_string())?;
-        return sanitize_relative_path(root, stripped);
+impl IgnoreRules {
+    fn load(root: &Path) -> IgnoreRules {
+        let mut rules = IgnoreRules::default();
+        rules.add_file(&root.join(IGNORE_FILE_NAME), "");
+        rules
     }
 
-    let mut resolved = PathBuf::from(root);
-    for component in path.components() {
-        match component {
-            Component::CurDir => {}
-            Component::Normal(part) => resolved.push(part),
-            Component::ParentDir => {
-                if resolved == root {
-                    return Err("Ruta fuera de la órbita segura.".into());
-                }
-                resolved.pop();
+    fn add_file(&mut self, file: &Path, base: &str) {
+        let Ok(contents) = fs::read_to_string(file) else {
+            return;
+        };
+        self.rules.extend(
+            contents
+                .lines()
+                .filter_map(IgnorePattern::parse)
+                .map(|pattern| IgnoreRule {
+                    base: base.to_string(),
+                    pattern,
+                }),
+        );
+    }
+
+    // Later rules win, so deeper ignore files and negations override earlier ones.
+    fn decide(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        self.rules.iter().rev().find_map(|rule| {
+            let path_in_base = if rule.base.is_empty() {
+                relative
+            } else {
+                relative
+                    .strip_prefix(rule.base.as_str())
+                    .and_then(|rest| rest.strip_prefix('/'))?
+            };
+            rule.pattern
+                .matches(path_in_base, is_dir)
+                .then_some(!rule.pattern.negated)
+        })
+    }
+
+    // `relative` is the orbit-relative path using `/` separators. A path is
+    // ignored when it or any of its parent directories is, since git never
+    // descends into an excluded directory.
+    fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let mut prefix_end = 0;
+        while let Some(offset) = relative[prefix_end..].find('/') {
+            prefix_end += offset;
+            if self.decide(&relative[..prefix_end], true) == Some(true) {
+                return true;
             }
-            Component::RootDir | Component::Prefix(_) => {
-                return Err("Ruta inválida".into());
+            prefix_end += 1;
+        }
+        self.decide(relative, is_dir) == Some(true)
+    }
+}
+
+// Combines `.cerebroignore` with, optionally, the `.gitignore` files git would
+// apply. The two sets are kept apart so a gitignore negation can't re-include
+// something `.cerebroignore` hides.
+struct IgnoreFilter {
+    cerebro: IgnoreRules,
+    git: Option<IgnoreRules>,
+}
+
+impl IgnoreFilter {
+    // Loads the rules that apply above `start`; callers then `enter_dir(start)`.
+    fn new(root: &Path, start: &Path, respect_gitignore: bool) -> IgnoreFilter {
+        let git = respect_gitignore.then(|| {
+            let repo_root = start
+                .ancestors()
+                .take_while(|dir| dir.starts_with(root))
+                .find(|dir| dir.join(".git").exists())
+                .unwrap_or(root);
+            let mut rules = IgnoreRules::default();
+            let repo_base = ignore_base(root, repo_root);
+            rules.add_file(
+                &repo_root.join(".git").join("info").join("exclude"),
+                &repo_base,
+            );
+
+            let mut parents: Vec<&Path> = start
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(repo_root))
+                .collect();
+            parents.reverse();
+            for dir in parents {
+                rules.add_file(&dir.join(".gitignore"), &ignore_base(root, dir));
             }
+            rules
+        });
+
+        IgnoreFilter {
+            cerebro: IgnoreRules::load(root),
+            git,
         }
     }
 
-    if !resolved.starts_with(root) {
-        return Err("Ruta fuera de la órbita segura.".into());
+    // Returns a mark for `leave_dir` once the directory's children are done.
+    fn enter_dir(&mut self, root: &Path, dir: &Path) -> usize {
+        match self.git.as_mut() {
+            Some(git) => {
+                let mark = git.rules.len();
+                git.add_file(&dir.join(".gitignore"), &ignore_base(root, dir));
+                mark
+            }
+            None => 0,
+        }
     }
 
-    Ok(resolved)
-}
+    fn leave_dir(&mut self, mark: usize) {
+        if let Some(git) = self.git.as_mut() {
+            git.rules.truncate(mark);
+        }
+    }
 
-fn relative_from_root(root: &Path, target: &Path) -> McpResult<String> {
-    let relative = target
-        .strip_prefix(root)
-        .map_err(|_| "Ruta fuera de la órbita segura.".to_string())?;
+    fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        if self.cerebro.is_ignored(relative, is_dir) {
+            return true;
+        }
+        match &self.git {
+            Some(git) => {
+                relative == ".git"
+                    || relative.ends_with("/.git")
+                    || git.is_ignored(relative, is_dir)
+            }
+            None => false,
+        }
+    }
+}
 
-    if relative.as_os_str().is_empty() {
-        Ok(".".to_string())
-    } else {
-        Ok(relative.to_string_lossy().replace('\\', "/"))
+fn ignore_base(root: &Path, dir: &Path) -> String {
+    match relative_from_root(root, dir) {
+        Ok(relative) if relative != "." => relative,
+        _ => String::new(),
     }
 }
 
-fn system_time_to_millis(time: SystemTime) -> Option<u64> {
-    time.duration_since(UNIX_EPOCH)
-        .ok()
-        .map(|duration| duration.as_millis() as u64)
+#[derive(Serialize)]
+struct ListStreamResponse {
+    #[serde(rename = "streamId")]
+    stream_id: String,
+    path: String,
 }
 
-fn current_timestamp_ms() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_millis() as u64)
-        .unwrap_or(0)
+#[derive(Clone, Serialize)]
+struct ListStreamBatchEvent {
+    #[serde(rename = "streamId")]
+    stream_id: String,
+    entries: Vec<FileEntry>,
 }
 
-fn read_hostname() -> Option<String> {
-    env::var("HOSTNAME")
-        .ok()
-        .or_else(|| {
-            Command::new("hostname")
-                .output()
-                .ok()
-                .and_then(|output| String::from_utf8(output.stdout).ok())
-                .map(|value| value.trim().to_string())
-        })
-        .filter(|value| !value.is_empty())
+#[derive(Clone, Serialize)]
+struct ListStreamDoneEvent {
+    #[serde(rename = "streamId")]
+    stream_id: String,
+    total: usize,
+    cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
-fn read_uname(flag: &str) -> Option<String> {
-    Command::new("uname")
-        .arg(flag)
-        .output()
-        .ok()
-        .and_then(|output| String::from_utf8(output.stdout).ok())
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty())
+#[derive(Serialize)]
+struct SearchStreamResponse {
+    #[serde(rename = "searchId")]
+    search_id: String,
+    path: String,
 }
 
-fn read_linux_meminfo() -> Option<(u64, u64, u64, u64, u64)> {
-    if !cfg!(target_os = "linux") {
-        return None;
-    }
+#[derive(Clone, Serialize)]
+struct SearchMatchEvent {
+    #[serde(rename = "searchId")]
+    search_id: String,
+    path: String,
+    line: usize,
+    // 1-based, in characters.
+    column: usize,
+    snippet: String,
+}
 
-    let contents = fs::read_to_string("/proc/meminfo").ok()?;
-    let mut mem_total = 0_u64;
-    let mut mem_free = 0_u64;
-    let mut mem_available = 0_u64;
-    let mut swap_total = 0_u64;
-    let mut swap_free = 0_u64;
+#[derive(Clone, Serialize)]
+struct SearchProgressEvent {
+    #[serde(rename = "searchId")]
+    search_id: String,
+    #[serde(rename = "filesScanned")]
+    files_scanned: usize,
+    matches: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct SearchDoneEvent {
+    #[serde(rename = "searchId")]
+    search_id: String,
+    #[serde(rename = "filesScanned")]
+    files_scanned: usize,
+    matches: usize,
+    // The match limit was reached before the walk finished.
+    truncated: bool,
+    cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+    #[serde(rename = "mtimeMs", skip_serializing_if = "Option::is_none")]
+    mtime_ms: Option<u64>,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct ManifestResponse {
+    path: String,
+    entries: Vec<ManifestEntry>,
+    // Files and directories that could not be read; they are left out of `entries`.
+    skipped: Vec<String>,
+    // The entry limit was reached before the walk finished.
+    truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ManifestProgressEvent {
+    path: String,
+    files: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct ReadChunksResponse {
+    #[serde(rename = "transferId")]
+    transfer_id: String,
+    path: String,
+    size: u64,
+    #[serde(rename = "chunkSize")]
+    chunk_size: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct ReadChunkEvent {
+    #[serde(rename = "transferId")]
+    transfer_id: String,
+    index: usize,
+    offset: u64,
+    data: String,
+}
+
+#[derive(Serialize)]
+struct ReadProgressResponse {
+    #[serde(rename = "transferId")]
+    transfer_id: String,
+    data: String,
+    // Where the next call should continue reading.
+    offset: u64,
+    size: u64,
+    done: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ReadProgressEvent {
+    #[serde(rename = "transferId")]
+    transfer_id: String,
+    offset: u64,
+    size: u64,
+    percent: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct ReadChunksDoneEvent {
+    #[serde(rename = "transferId")]
+    transfer_id: String,
+    chunks: usize,
+    bytes: u64,
+    cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ReadResponse {
+    path: String,
+    encoding: String,
+    content: String,
+    // Hex digest of the bytes read (after decompression, if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WriteResponse {
+    path: String,
+    bytes: usize,
+    created: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup: Option<String>,
+    // Digest confirmed by re-reading the file; only set with `verify`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    // Resulting permission bits in octal, e.g. "600"; only set with `permissions`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeleteResponse {
+    path: String,
+    // "file", "directory" or "symlink" (the link itself is removed, never its target).
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct MoveResponse {
+    from: String,
+    to: String,
+    // The rename crossed filesystems, so the data was copied and the source removed.
+    copied: bool,
+}
+
+#[derive(Serialize)]
+struct CopyResponse {
+    from: String,
+    to: String,
+    files: usize,
+    bytes: u64,
+    // Symlinks left out because they would point outside the orbit from the copy.
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RestoreBackupResponse {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BackupEntry {
+    path: String,
+    size: u64,
+    #[serde(rename = "createdAtMs")]
+    created_at_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct ExecResponse {
+    command: String,
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(rename = "exitCode")]
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    #[serde(rename = "durationMs")]
+    duration_ms: u128,
+}
+
+// One step of `mcp_shell_pipeline`; mirrors the non-detached `mcp_shell_exec` inputs.
+#[derive(Deserialize)]
+struct ExecStep {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    #[serde(rename = "timeoutMs", alias = "timeout_ms")]
+    timeout_ms: Option<u64>,
+    #[serde(rename = "mergeOutput", alias = "merge_output", default)]
+    merge_output: bool,
+    #[serde(rename = "cleanEnv", alias = "clean_env", default)]
+    clean_env: bool,
+}
+
+#[derive(Serialize)]
+struct PipelineStepResponse {
+    index: usize,
+    // "completed", "failed" (non-zero exit, timeout or spawn error) or "skipped".
+    status: &'static str,
+    #[serde(flatten)]
+    result: Option<ExecResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiffSpan {
+    kind: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct WordDiffResponse {
+    a: String,
+    b: String,
+    granularity: String,
+    spans: Vec<DiffSpan>,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct BackupDiffResponse {
+    path: String,
+    backup: String,
+    // Unified diff from the backup (`a/`) to the current file (`b/`); empty when identical.
+    diff: String,
+    additions: usize,
+    deletions: usize,
+    binary: bool,
+    // The files were too different for an exact line diff; hunks may be coarser than needed.
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct CharsetResponse {
+    path: String,
+    charset: String,
+    confidence: f32,
+    bom: bool,
+    #[serde(rename = "sampledBytes")]
+    sampled_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct LineIndexResponse {
+    path: String,
+    size: u64,
+    // Byte offset at which each line starts; line N (1-based) is `offsets[N - 1]`.
+    offsets: Vec<u64>,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct QrCodeResponse {
+    modules: usize,
+    width: usize,
+    bytes: usize,
+    #[serde(rename = "dataUrl", skip_serializing_if = "Option::is_none")]
+    data_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RenameBatchItem {
+    from: String,
+    to: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RenameBatchResponse {
+    path: String,
+    #[serde(rename = "dryRun")]
+    dry_run: bool,
+    renamed: usize,
+    results: Vec<RenameBatchItem>,
+}
+
+#[derive(Serialize)]
+struct NumberedLine {
+    number: usize,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct ReadAroundResponse {
+    path: String,
+    line: usize,
+    #[serde(rename = "startLine")]
+    start_line: usize,
+    #[serde(rename = "endLine")]
+    end_line: usize,
+    lines: Vec<NumberedLine>,
+}
+
+#[derive(Serialize)]
+struct MalformedJsonLine {
+    // Byte offset of the line in the file.
+    offset: u64,
+    text: String,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct TailJsonResponse {
+    path: String,
+    entries: Vec<serde_json::Value>,
+    malformed: Vec<MalformedJsonLine>,
+    // Set when the byte limit was hit before `lines` lines were found.
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct OutlineItem {
+    // Heading depth for markdown (1-6); always 1 for source declarations.
+    level: usize,
+    title: String,
+    line: usize,
+}
+
+#[derive(Serialize)]
+struct FrontmatterResponse {
+    path: String,
+    // Null when the file has no front matter block.
+    frontmatter: Option<serde_json::Value>,
+    body: String,
+}
+
+#[derive(Serialize)]
+struct QuickSignatureResponse {
+    path: String,
+    size: u64,
+    #[serde(rename = "mtimeMs", skip_serializing_if = "Option::is_none")]
+    mtime_ms: Option<u64>,
+    crc32: u32,
+}
+
+#[derive(Serialize)]
+struct TextStatsResponse {
+    path: String,
+    characters: usize,
+    words: usize,
+    sentences: usize,
+    paragraphs: usize,
+    #[serde(rename = "avgWordsPerSentence")]
+    avg_words_per_sentence: f64,
+    #[serde(rename = "fleschReadingEase", skip_serializing_if = "Option::is_none")]
+    flesch_reading_ease: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct LongLine {
+    line: usize,
+    // In characters, excluding the line terminator.
+    length: usize,
+}
+
+#[derive(Serialize)]
+struct LongLinesResponse {
+    path: String,
+    #[serde(rename = "maxLength")]
+    max_length: usize,
+    lines: Vec<LongLine>,
+    // Every offending line, including those past the reporting cap.
+    total: usize,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct TarGzResponse {
+    path: String,
+    entries: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct SkippedArchiveEntry {
+    path: String,
+    reason: &'static str,
+}
+
+#[derive(Serialize)]
+struct UntarGzResponse {
+    destination: String,
+    extracted: Vec<String>,
+    skipped: Vec<SkippedArchiveEntry>,
+}
+
+#[derive(Serialize)]
+struct BenchmarkResponse {
+    #[serde(rename = "sizeMb")]
+    size_mb: u64,
+    #[serde(rename = "writeMs")]
+    write_ms: u128,
+    #[serde(rename = "readMs")]
+    read_ms: u128,
+    #[serde(rename = "writeMbPerSec")]
+    write_mb_per_sec: f64,
+    #[serde(rename = "readMbPerSec")]
+    read_mb_per_sec: f64,
+}
+
+#[derive(Serialize)]
+struct IndentResponse {
+    path: String,
+    style: String,
+    size: u8,
+    confidence: f32,
+    #[serde(rename = "sampledLines")]
+    sampled_lines: usize,
+}
+
+#[derive(Serialize)]
+struct TypedReadResponse {
+    path: String,
+    dtype: String,
+    endianness: String,
+    offset: u64,
+    count: usize,
+    values: Vec<serde_json::Value>,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct FilesInfoResponse {
+    root: String,
+    exists: bool,
+}
+
+#[derive(Serialize)]
+struct ReadOnlyRootInfo {
+    configured: String,
+    // Canonical location, or None when the directory can't be resolved (and is ignored).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SecurityPolicyResponse {
+    #[serde(rename = "orbitPath")]
+    orbit_path: String,
+    #[serde(rename = "allowedCommands")]
+    allowed_commands: Vec<String>,
+    #[serde(rename = "blockedGitSubcommands")]
+    blocked_git_subcommands: Vec<String>,
+    #[serde(rename = "restrictPathArgs")]
+    restrict_path_args: bool,
+    // Writes are always confined to the orbit; these are readable only.
+    #[serde(rename = "readOnlyRoots")]
+    read_only_roots: Vec<ReadOnlyRootInfo>,
+}
+
+#[derive(Serialize)]
+struct ShellCapabilities {
+    allowed_commands: Vec<String>,
+    #[serde(rename = "defaultTimeoutMs")]
+    default_timeout_ms: u64,
+    #[serde(rename = "commandTimeouts")]
+    command_timeouts: BTreeMap<String, u64>,
+    #[serde(rename = "restrictPathArgs")]
+    restrict_path_args: bool,
+    #[serde(rename = "commandAliases")]
+    command_aliases: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct CatalogEntry {
+    command: String,
+    // "package-manager", "runtime", "build", "search" or "utility".
+    category: &'static str,
+    installed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ConfigValue<T> {
+    value: T,
+    // "default" or "file"; nothing is read from the environment yet.
+    source: &'static str,
+}
+
+#[derive(Serialize)]
+struct EffectiveConfigResponse {
+    #[serde(rename = "configPath")]
+    config_path: String,
+    #[serde(rename = "configLoaded")]
+    config_loaded: bool,
+    #[serde(rename = "orbitPath")]
+    orbit_path: ConfigValue<String>,
+    #[serde(rename = "allowedCommands")]
+    allowed_commands: ConfigValue<Vec<String>>,
+    #[serde(rename = "defaultTimeoutMs")]
+    default_timeout_ms: ConfigValue<u64>,
+    #[serde(rename = "commandTimeouts")]
+    command_timeouts: ConfigValue<BTreeMap<String, u64>>,
+    #[serde(rename = "restrictPathArgs")]
+    restrict_path_args: ConfigValue<bool>,
+    #[serde(rename = "commandAliases")]
+    command_aliases: ConfigValue<BTreeMap<String, Vec<String>>>,
+    #[serde(rename = "ignoreFile")]
+    ignore_file: ConfigValue<Option<String>>,
+    #[serde(rename = "readOnlyRoots")]
+    read_only_roots: ConfigValue<Vec<String>>,
+    #[serde(rename = "maxPathDepth")]
+    max_path_depth: ConfigValue<usize>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CerebroConfig {
+    #[serde(rename = "commandTimeouts", alias = "command_timeouts")]
+    command_timeouts: HashMap<String, u64>,
+    #[serde(rename = "restrictPathArgs", alias = "restrict_path_args")]
+    restrict_path_args: bool,
+    // Alias -> [command, prepended args...]; the command must itself be allowlisted.
+    #[serde(rename = "commandAliases", alias = "command_aliases")]
+    command_aliases: HashMap<String, Vec<String>>,
+    // Absolute directories outside the orbit that read commands may also use.
+    // Only editable in the file itself, never through `mcp_config_set`.
+    #[serde(rename = "readOnlyRoots", alias = "read_only_roots")]
+    read_only_roots: Vec<String>,
+    // Components allowed below the orbit; unset means `DEFAULT_MAX_PATH_DEPTH`.
+    #[serde(
+        rename = "maxPathDepth",
+        alias = "max_path_depth",
+        skip_serializing_if = "Option::is_none"
+    )]
+    max_path_depth: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct PtySpawnResponse {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+    command: String,
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+struct PtyDataEvent {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+    data: String,
+}
+
+#[derive(Serialize)]
+struct DetachedExecResponse {
+    #[serde(rename = "runId")]
+    run_id: String,
+    command: String,
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    pid: u32,
+    detached: bool,
+}
+
+// A detached run answers right away; its result arrives later as `shell://exit`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ShellExecResponse {
+    Completed(ExecResponse),
+    Detached(DetachedExecResponse),
+}
+
+// A failed `expect_exit` assertion, with the output kept so it still shows why.
+#[derive(Serialize)]
+struct UnexpectedExitError {
+    kind: &'static str,
+    message: String,
+    command: String,
+    expected: i32,
+    actual: i32,
+    stdout: String,
+    stderr: String,
+}
+
+// Policy and spawn failures stay plain messages; a failed assertion reaches the
+// frontend as an object, so callers can branch on `kind` instead of parsing text.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ShellExecError {
+    Message(String),
+    UnexpectedExit(UnexpectedExitError),
+}
+
+impl From<String> for ShellExecError {
+    fn from(message: String) -> Self {
+        ShellExecError::Message(message)
+    }
+}
+
+impl From<&str> for ShellExecError {
+    fn from(message: &str) -> Self {
+        ShellExecError::Message(message.to_string())
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ShellOutputEvent {
+    #[serde(rename = "runId")]
+    run_id: String,
+    stream: &'static str,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+struct ShellExitEvent {
+    #[serde(rename = "runId")]
+    run_id: String,
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    killed: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ShellRestartEvent {
+    #[serde(rename = "runId")]
+    run_id: String,
+    restarts: u32,
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    pid: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct PtyExitEvent {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    exit_code: Option<u32>,
+}
+
+struct PtySession {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn PtyChild + Send + Sync>,
+}
+
+#[derive(Default)]
+struct PtyState {
+    sessions: Mutex<HashMap<String, PtySession>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct StreamRegistry {
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    next_id: AtomicU64,
+}
+
+impl StreamRegistry {
+    fn start(&self, prefix: &str) -> McpResult<(String, Arc<AtomicBool>)> {
+        let id = format!(
+            "{}-{}",
+            prefix,
+            self.next_id.fetch_add(1, Ordering::SeqCst) + 1
+        );
+        let flag = Arc::new(AtomicBool::new(false));
+        self.active
+            .lock()
+            .map_err(|err| err.to_string())?
+            .insert(id.clone(), flag.clone());
+        Ok((id, flag))
+    }
+
+    fn finish(&self, id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(id);
+        }
+    }
+
+    fn cancel(&self, id: &str) -> McpResult<bool> {
+        let active = self.active.lock().map_err(|err| err.to_string())?;
+        Ok(match active.get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+struct TrackedJob {
+    pid: u32,
+    command: String,
+    started_at_ms: u64,
+    started: Instant,
+    // Polled by whoever waits on the child; set by `mcp_shell_kill`.
+    kill: Arc<AtomicBool>,
+    restarts: u32,
+}
+
+#[derive(Serialize)]
+struct ActiveJob {
+    #[serde(rename = "runId")]
+    run_id: String,
+    // Unset for the brief moment between reserving the id and spawning the child.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    command: String,
+    #[serde(rename = "startedAtMs")]
+    started_at_ms: u64,
+    #[serde(rename = "elapsedMs")]
+    elapsed_ms: u128,
+    // Times a detached job was respawned after crashing.
+    restarts: u32,
+}
+
+// One entry of `~/.cerebro/pids.json`. Entries whose owner is no longer
+// running belong to a session that crashed before it could clean up.
+#[derive(Serialize, Deserialize)]
+struct PidRecord {
+    pid: u32,
+    command: String,
+    #[serde(rename = "ownerPid")]
+    owner_pid: u32,
+    #[serde(rename = "startedAtMs")]
+    started_at_ms: u64,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(not(unix), allow(dead_code))]
+struct ReapOrphansResponse {
+    reaped: usize,
+    pids: Vec<u32>,
+}
+
+#[derive(Default)]
+struct JobRegistry {
+    jobs: Mutex<HashMap<String, TrackedJob>>,
+}
+
+impl JobRegistry {
+    fn reserve(&self, run_id: &str, command: &str) -> McpResult<()> {
+        let mut jobs = self.jobs.lock().map_err(|err| err.to_string())?;
+        if jobs.contains_key(run_id) {
+            return Err("El identificador de ejecución ya está en uso.".into());
+        }
+        jobs.insert(
+            run_id.to_string(),
+            TrackedJob {
+                pid: 0,
+                command: command.to_string(),
+                started_at_ms: current_timestamp_ms(),
+                started: Instant::now(),
+                kill: Arc::new(AtomicBool::new(false)),
+                restarts: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn attach_pid(&self, run_id: &str, pid: u32) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if let Some(job) = jobs.get_mut(run_id) {
+                job.pid = pid;
+            }
+            persist_job_pids(&jobs);
+        }
+    }
+
+    // Points the job at its respawned child and returns the new restart count.
+    fn record_restart(&self, run_id: &str, pid: u32) -> u32 {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return 0;
+        };
+        let restarts = match jobs.get_mut(run_id) {
+            Some(job) => {
+                job.pid = pid;
+                job.restarts += 1;
+                job.restarts
+            }
+            None => 0,
+        };
+        persist_job_pids(&jobs);
+        restarts
+    }
+
+    fn restarts(&self, run_id: &str) -> u32 {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(run_id).map(|job| job.restarts))
+            .unwrap_or(0)
+    }
+
+    fn finish(&self, run_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if jobs.remove(run_id).is_some_and(|job| job.pid != 0) {
+                persist_job_pids(&jobs);
+            }
+        }
+    }
+
+    // Drops this session's entries from the pid file on a clean exit.
+    fn forget_pids(&self) {
+        if let Ok(_jobs) = self.jobs.lock() {
+            if let Err(err) = write_pid_records(Vec::new()) {
+                eprintln!("[shell] failed to clear pid file: {err}");
+            }
+        }
+    }
+
+    fn lookup(&self, run_id: &str) -> McpResult<(u32, String)> {
+        let jobs = self.jobs.lock().map_err(|err| err.to_string())?;
+        match jobs.get(run_id) {
+            Some(job) if job.pid != 0 => Ok((job.pid, job.command.clone())),
+            _ => Err("No hay ninguna ejecución activa con ese identificador.".into()),
+        }
+    }
+
+    fn kill_flag(&self, run_id: &str) -> Option<Arc<AtomicBool>> {
+        let jobs = self.jobs.lock().ok()?;
+        jobs.get(run_id).map(|job| job.kill.clone())
+    }
+
+    fn request_kill(&self, run_id: &str) -> McpResult<bool> {
+        let jobs = self.jobs.lock().map_err(|err| err.to_string())?;
+        Ok(match jobs.get(run_id) {
+            Some(job) => {
+                job.kill.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn active(&self) -> McpResult<Vec<ActiveJob>> {
+        let jobs = self.jobs.lock().map_err(|err| err.to_string())?;
+        let mut active: Vec<ActiveJob> = jobs
+            .iter()
+            .map(|(run_id, job)| active_job(run_id, job))
+            .collect();
+        active.sort_by_key(|job| job.started_at_ms);
+        Ok(active)
+    }
+
+    fn stats(&self, run_id: &str) -> McpResult<ActiveJob> {
+        let jobs = self.jobs.lock().map_err(|err| err.to_string())?;
+        jobs.get(run_id)
+            .map(|job| active_job(run_id, job))
+            .ok_or_else(|| "No hay ninguna ejecución activa con ese identificador.".into())
+    }
+}
+
+fn pids_file_path() -> McpResult<PathBuf> {
+    let home = resolve_home_dir()?;
+    Ok(home.join(".cerebro").join("pids.json"))
+}
+
+fn read_pid_records(path: &Path) -> Vec<PidRecord> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+// Replaces this process's entries in the pid file with `own`, keeping the ones
+// other (possibly crashed) sessions left behind.
+fn write_pid_records(own: Vec<PidRecord>) -> McpResult<()> {
+    let path = pids_file_path()?;
+    let current = std::process::id();
+    let mut records: Vec<PidRecord> = read_pid_records(&path)
+        .into_iter()
+        .filter(|record| record.owner_pid != current)
+        .collect();
+    records.extend(own);
+    store_pid_records(&path, &records)
+}
+
+fn store_pid_records(path: &Path, records: &[PidRecord]) -> McpResult<()> {
+    if records.is_empty() {
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let serialized = serde_json::to_vec_pretty(records).map_err(|err| err.to_string())?;
+    write_file_atomic(path, &serialized)
+}
+
+// Called with the registry locked, which also serializes writers of the file.
+fn persist_job_pids(jobs: &HashMap<String, TrackedJob>) {
+    let current = std::process::id();
+    let own = jobs
+        .values()
+        .filter(|job| job.pid != 0)
+        .map(|job| PidRecord {
+            pid: job.pid,
+            command: job.command.clone(),
+            owner_pid: current,
+            started_at_ms: job.started_at_ms,
+        })
+        .collect();
+    if let Err(err) = write_pid_records(own) {
+        eprintln!("[shell] failed to record pids: {err}");
+    }
+}
+
+// Arguments of a running process, or None once it has exited.
+#[cfg(unix)]
+fn process_arguments(pid: u32) -> Option<Vec<String>> {
+    if cfg!(target_os = "linux") {
+        let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+        return Some(
+            raw.split(|byte| *byte == 0)
+                .filter(|part| !part.is_empty())
+                .map(|part| String::from_utf8_lossy(part).to_string())
+                .collect(),
+        );
+    }
+    let output = Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty())
+        .then(|| line.split_whitespace().map(str::to_string).collect())
+}
+
+// Scripts run through their interpreter (`node /usr/bin/npm ...`), so the
+// command may show up as either of the first two arguments.
+#[cfg(unix)]
+fn process_matches_command(arguments: &[String], command: &str) -> bool {
+    arguments.iter().take(2).any(|argument| {
+        Path::new(argument)
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(command))
+    })
+}
+
+// Jobs lead their own process group, so the whole group is signalled and the
+// orphan's children go with it. Records from before jobs had their own group
+// fall back to the pid alone.
+#[cfg(unix)]
+fn terminate_process(pid: u32) -> bool {
+    let signal = |name: &str| {
+        let send = |target: String| {
+            Command::new("kill")
+                .args([name, "--", &target])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        };
+        send(format!("-{pid}")) || send(pid.to_string())
+    };
+    if !signal("-TERM") {
+        return false;
+    }
+    for _ in 0..20 {
+        if process_arguments(pid).is_none() {
+            // Whatever in the group ignored the TERM doesn't outlive its leader.
+            kill_process_group(pid);
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    signal("-KILL")
+}
+
+fn active_job(run_id: &str, job: &TrackedJob) -> ActiveJob {
+    ActiveJob {
+        run_id: run_id.to_string(),
+        pid: (job.pid != 0).then_some(job.pid),
+        command: job.command.clone(),
+        started_at_ms: job.started_at_ms,
+        elapsed_ms: job.started.elapsed().as_millis(),
+        restarts: job.restarts,
+    }
+}
+
+#[derive(Default)]
+struct ExecOptions<'a> {
+    timeout: Option<Duration>,
+    job: Option<JobTracker<'a>>,
+    // Points stderr at the stdout pipe. Both streams then share one pipe, so the
+    // merged text keeps the order in which the child wrote it (modulo the
+    // child's own buffering, e.g. a block-buffered stdout).
+    merge_output: bool,
+}
+
+// Ties a running child to its run id for the duration of `spawn_command`.
+struct JobTracker<'a> {
+    registry: &'a JobRegistry,
+    run_id: String,
+}
+
+#[derive(Serialize)]
+struct OpenFilesResponse {
+    #[serde(rename = "runId")]
+    run_id: String,
+    pid: u32,
+    command: String,
+    files: Vec<String>,
+}
+
+// Most recent result per command name, so a reopened panel can show it again.
+#[derive(Default)]
+struct LastExecStore {
+    results: Mutex<HashMap<String, ExecResponse>>,
+}
+
+impl LastExecStore {
+    fn record(&self, response: &ExecResponse) {
+        if let Ok(mut results) = self.results.lock() {
+            results.insert(response.command.to_lowercase(), response.clone());
+        }
+    }
+}
+
+#[derive(Default)]
+struct MetricsSubscribers {
+    count: AtomicUsize,
+}
+
+// Last (millisecond, counter) pair handed out for UUID v7 so ids minted within the
+// same millisecond still sort in creation order.
+#[derive(Default)]
+struct IdState {
+    last_v7: Mutex<(u64, u16)>,
+}
+
+#[derive(Serialize)]
+struct GitInfoResponse {
+    version: Option<String>,
+    root: String,
+}
+
+#[derive(PartialEq, Serialize)]
+struct Diagnostic {
+    file: String,
+    line: u64,
+    column: u64,
+    severity: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiagnosticsResponse {
+    format: String,
+    diagnostics: Vec<Diagnostic>,
+    result: ExecResponse,
+}
+
+#[derive(Clone, Serialize)]
+struct ProjectScript {
+    name: String,
+    source: String,
+    // The script body as written in its manifest.
+    command: String,
+    runner: String,
+    #[serde(rename = "runArgs")]
+    run_args: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ProjectScriptsResponse {
+    path: String,
+    scripts: Vec<ProjectScript>,
+}
+
+#[derive(Serialize)]
+struct Dependency {
+    name: String,
+    version: String,
+    // runtime, dev, build, peer or optional.
+    kind: String,
+    manifest: String,
+}
+
+#[derive(Serialize)]
+struct ProjectDependenciesResponse {
+    path: String,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Serialize, Default)]
+struct LangStats {
+    language: &'static str,
+    files: usize,
+    #[serde(rename = "codeLines")]
+    code_lines: usize,
+    #[serde(rename = "commentLines")]
+    comment_lines: usize,
+    #[serde(rename = "blankLines")]
+    blank_lines: usize,
+}
+
+#[derive(Serialize)]
+struct ProjectLocResponse {
+    path: String,
+    languages: Vec<LangStats>,
+    truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycles: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct GitShowFileResponse {
+    rev: String,
+    path: String,
+    encoding: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct DiffStat {
+    path: String,
+    // `None` for binary files, which git reports as `-`.
+    added: Option<u64>,
+    deleted: Option<u64>,
+    binary: bool,
+}
+
+#[derive(Serialize)]
+struct BlameLine {
+    #[serde(rename = "lineNumber")]
+    line_number: usize,
+    #[serde(rename = "commitHash")]
+    commit_hash: String,
+    author: String,
+    timestamp: i64,
+    summary: String,
+    // Lines not yet committed come back from git with an all-zero hash.
+    uncommitted: bool,
+}
+
+#[derive(Serialize)]
+struct GitFsckResponse {
+    ok: bool,
+    issues: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct GitDiffRefsResponse {
+    from: String,
+    to: String,
+    stats: Vec<DiffStat>,
+    diff: String,
+    truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct GitStatusEntry {
+    path: String,
+    #[serde(rename = "originalPath", skip_serializing_if = "Option::is_none")]
+    original_path: Option<String>,
+    index: String,
+    worktree: String,
+}
+
+#[derive(Clone, Serialize)]
+struct GitStatusSnapshot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<String>,
+    ahead: u32,
+    behind: u32,
+    clean: bool,
+    entries: Vec<GitStatusEntry>,
+}
+
+#[derive(Serialize)]
+struct ProjectSummary {
+    name: String,
+    path: String,
+    // Detected from manifest files: "rust", "node", "python", "go", ... or "unknown".
+    kind: &'static str,
+    #[serde(rename = "isGitRepo")]
+    is_git_repo: bool,
+    // Unset when the repo is on a detached HEAD or `git status` didn't answer in time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(rename = "isDirty", skip_serializing_if = "Option::is_none")]
+    is_dirty: Option<bool>,
+    #[serde(rename = "lastModified", skip_serializing_if = "Option::is_none")]
+    last_modified: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct GitStatusEvent {
+    cwd: String,
+    status: GitStatusSnapshot,
+}
+
+// Tauri can set the webview zoom but not read it back, so the last applied
+// factor is remembered here.
+struct ZoomState {
+    factor: Mutex<f64>,
+}
+
+impl Default for ZoomState {
+    fn default() -> Self {
+        ZoomState {
+            factor: Mutex::new(1.0),
+        }
+    }
+}
+
+// Probe results per orbit root; the orbit can change while the app runs.
+#[derive(Default)]
+struct CaseSensitivityState {
+    probed: Mutex<HashMap<PathBuf, bool>>,
+}
+
+#[derive(Serialize)]
+struct ToRelativeResponse {
+    #[serde(rename = "insideOrbit")]
+    inside_orbit: bool,
+    // Orbit-relative form, only when the path is inside the orbit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CaseSensitivityResponse {
+    #[serde(rename = "caseSensitive")]
+    case_sensitive: bool,
+}
+
+// Labels of the windows opened through `open-window`. Users can close a window
+// themselves, so entries are pruned against the live windows when listed.
+#[derive(Default)]
+struct WindowRegistry {
+    labels: Mutex<BTreeSet<String>>,
+}
+
+// Loading syntect's bundled grammars is slow, so it happens once on first use.
+#[derive(Default)]
+struct HighlightState {
+    syntaxes: OnceLock<SyntaxSet>,
+}
+
+#[derive(Clone, Serialize)]
+struct HighlightToken {
+    start: usize,
+    end: usize,
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct HighlightResponse {
+    path: String,
+    language: String,
+    tokens: Vec<HighlightToken>,
+    truncated: bool,
+}
+
+#[derive(Default)]
+struct GitWatchState {
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+#[derive(Serialize)]
+struct MemoryInfo {
+    total: u64,
+    used: u64,
+    free: u64,
+    #[serde(rename = "usagePercent", skip_serializing_if = "Option::is_none")]
+    usage_percent: Option<f32>,
+    #[serde(rename = "swapTotal")]
+    swap_total: u64,
+    #[serde(rename = "swapUsed")]
+    swap_used: u64,
+}
+
+#[derive(Serialize)]
+struct CpuInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(rename = "logicalCores")]
+    logical_cores: usize,
+    #[serde(rename = "globalUsage", skip_serializing_if = "Option::is_none")]
+    global_usage: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct ResourceSample {
+    #[serde(rename = "timestampMs")]
+    timestamp_ms: u64,
+    #[serde(rename = "memoryUsed")]
+    memory_used: u64,
+    #[serde(rename = "memoryAvailable")]
+    memory_available: u64,
+    #[serde(rename = "swapUsed")]
+    swap_used: u64,
+    // Average over all cores since the previous sample.
+    #[serde(rename = "cpuUsage", skip_serializing_if = "Option::is_none")]
+    cpu_usage: Option<f32>,
+    #[serde(rename = "processCount", skip_serializing_if = "Option::is_none")]
+    process_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ResourceDelta {
+    #[serde(rename = "memoryUsed")]
+    memory_used: i64,
+    #[serde(rename = "memoryAvailable")]
+    memory_available: i64,
+    #[serde(rename = "swapUsed")]
+    swap_used: i64,
+    #[serde(rename = "cpuUsage", skip_serializing_if = "Option::is_none")]
+    cpu_usage: Option<f32>,
+    #[serde(rename = "processCount", skip_serializing_if = "Option::is_none")]
+    process_count: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct SystemDeltaResponse {
+    #[serde(rename = "intervalMs")]
+    interval_ms: u64,
+    start: ResourceSample,
+    end: ResourceSample,
+    // `end - start`; positive means the value grew during the interval.
+    delta: ResourceDelta,
+}
+
+#[derive(Serialize)]
+struct DiskInfo {
+    mount: String,
+    total: u64,
+    free: u64,
+    #[serde(rename = "usagePercent", skip_serializing_if = "Option::is_none")]
+    usage_percent: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct MountInfo {
+    device: String,
+    #[serde(rename = "mountPoint")]
+    mount_point: String,
+    #[serde(rename = "fsType")]
+    fs_type: String,
+    options: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct MetricsRepairResponse {
+    valid: usize,
+    invalid: usize,
+    rewritten: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sidecar: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AppInfoResponse {
+    name: String,
+    version: String,
+    #[serde(rename = "tauriVersion")]
+    tauri_version: String,
+    #[serde(rename = "buildTimestamp", skip_serializing_if = "Option::is_none")]
+    build_timestamp: Option<u64>,
+    #[serde(rename = "gitCommit", skip_serializing_if = "Option::is_none")]
+    git_commit: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SystemInfoResponse {
+    #[serde(rename = "timestampMs")]
+    timestamp_ms: u64,
+    hostname: Option<String>,
+    os: String,
+    #[serde(rename = "osVersion", skip_serializing_if = "Option::is_none")]
+    os_version: Option<String>,
+    #[serde(rename = "kernelVersion", skip_serializing_if = "Option::is_none")]
+    kernel_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    architecture: Option<String>,
+    memory: MemoryInfo,
+    cpu: CpuInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<DiskInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uptime: Option<u64>,
+    #[serde(rename = "processCount", skip_serializing_if = "Option::is_none")]
+    process_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct SystemPathsResponse {
+    home: String,
+    #[serde(rename = "safeOrbit")]
+    safe_orbit: String,
+}
+
+#[derive(Serialize)]
+struct AppLogEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct AppLogsResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    entries: Vec<AppLogEntry>,
+    truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct MetricsRotatedEvent {
+    // File name of the archive inside the metrics directory.
+    file: String,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct MetricsFileInfo {
+    name: String,
+    // The live log `mcp_metrics_append` writes to; archives are read-only history.
+    current: bool,
+    size: u64,
+    #[serde(rename = "rotatedAtMs", skip_serializing_if = "Option::is_none")]
+    rotated_at_ms: Option<u64>,
+    #[serde(rename = "firstTs", skip_serializing_if = "Option::is_none")]
+    first_ts: Option<u64>,
+    #[serde(rename = "lastTs", skip_serializing_if = "Option::is_none")]
+    last_ts: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct MetricsSummary {
+    entries: usize,
+    failures: usize,
+    #[serde(rename = "firstTs", skip_serializing_if = "Option::is_none")]
+    first_ts: Option<u64>,
+    #[serde(rename = "lastTs", skip_serializing_if = "Option::is_none")]
+    last_ts: Option<u64>,
+    #[serde(rename = "avgLatencyMs", skip_serializing_if = "Option::is_none")]
+    avg_latency_ms: Option<u64>,
+    #[serde(rename = "promptTokens")]
+    prompt_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    output_tokens: u64,
+    #[serde(rename = "byProvider")]
+    by_provider: BTreeMap<String, usize>,
+    #[serde(rename = "byMode")]
+    by_mode: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct SupportReport {
+    #[serde(rename = "generatedAtMs")]
+    generated_at_ms: u64,
+    app: AppInfoResponse,
+    system: SystemInfoResponse,
+    #[serde(rename = "securityPolicy")]
+    security_policy: SecurityPolicyResponse,
+    // There is no dedicated audit log; the app log is the closest record of recent activity.
+    #[serde(rename = "recentLogs")]
+    recent_logs: AppLogsResponse,
+    metrics: MetricsSummary,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct MetricsEntry {
+    #[serde(alias = "timestamp")]
+    ts: u64,
+    mode: String,
+    provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(
+        rename = "latency_ms",
+        alias = "latencyMs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    latency_ms: Option<u64>,
+    #[serde(
+        rename = "prompt_tokens",
+        alias = "promptTokens",
+        alias = "tokensIn",
+        skip_serializing_if = "Option::is_none"
+    )]
+    prompt_tokens: Option<u32>,
+    #[serde(
+        rename = "output_tokens",
+        alias = "outputTokens",
+        alias = "tokensOut",
+        skip_serializing_if = "Option::is_none"
+    )]
+    output_tokens: Option<u32>,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn safe_root() -> McpResult<PathBuf> {
+    let home = resolve_home_dir()?;
+    let root = home.join(SAFE_ORBIT_RELATIVE);
+    if !root.exists() {
+        fs::create_dir_all(&root).map_err(|err| err.to_string())?;
+    }
+    Ok(root)
+}
+
+fn resolve_home_dir() -> McpResult<PathBuf> {
+    if let Ok(home) = env::var("HOME") {
+        return Ok(PathBuf::from(home));
+    }
+
+    if cfg!(target_os = "windows") {
+        if let Ok(profile) = env::var("USERPROFILE") {
+            return Ok(PathBuf::from(profile));
+        }
+    }
+
+    Err("No se pudo resolver el directorio home del usuario.".into())
+}
+
+fn build_path(root: &Path, input: Option<&str>) -> McpResult<PathBuf> {
+    match input {
+        Some(value) => sanitize_relative_path(root, Path::new(value)),
+        None => Ok(root.to_path_buf()),
+    }
+}
+
+fn resolve_read_only_root(configured: &str) -> Option<PathBuf> {
+    let expanded = match configured.strip_prefix("~/") {
+        Some(rest) => resolve_home_dir().ok()?.join(rest),
+        None => PathBuf::from(configured),
+    };
+    if !expanded.is_absolute() {
+        return None;
+    }
+    expanded.canonicalize().ok().filter(|path| path.is_dir())
+}
+
+// Resolves a path for read-only commands. Orbit paths behave as in
+// `build_path`; absolute paths may also point under a configured read-only
+// root, in which case that root is returned alongside the target.
+fn resolve_readable_path(
+    root: &Path,
+    input: Option<&str>,
+) -> McpResult<(PathBuf, Option<PathBuf>)> {
+    let orbit_error = match build_path(root, input) {
+        Ok(target) => return Ok((target, None)),
+        Err(err) => err,
+    };
+    let Some(requested) = input.map(Path::new).filter(|path| path.is_absolute()) else {
+        return Err(orbit_error);
+    };
+
+    for external in load_config()
+        .read_only_roots
+        .iter()
+        .filter_map(|configured| resolve_read_only_root(configured))
+    {
+        let Ok(target) = sanitize_relative_path(&external, requested) else {
+            continue;
+        };
+        // Lexically inside is not enough: a symlink under the root may lead anywhere.
+        if target.exists() {
+            let real = target.canonicalize().map_err(|err| err.to_string())?;
+            if !real.starts_with(&external) {
+                return Err("Ruta fuera de la órbita segura.".into());
+            }
+        }
+        return Ok((target, Some(external)));
+    }
+    Err(orbit_error)
+}
+
+// Orbit paths are reported relative to the orbit; external ones stay absolute
+// so they can be passed straight back to another read.
+fn display_readable_path(root: &Path, external: Option<&Path>, target: &Path) -> McpResult<String> {
+    match external {
+        None => relative_from_root(root, target),
+        Some(_) => Ok(target.to_string_lossy().replace('\\', "/")),
+    }
+}
+
+fn sanitize_relative_path(root: &Path, path: &Path) -> McpResult<PathBuf> {
+    sanitize_path_with_depth(root, path, max_path_depth())
+}
+
+fn sanitize_path_with_depth(root: &Path, path: &Path, max_depth: usize) -> McpResult<PathBuf> {
+    if path.is_absolute() {
+        let stripped = path
+            .strip_prefix(root)
+            .map_err(|_| "Ruta fuera de la órbita segura.".to_string())?;
+        return sanitize_path_with_depth(root, stripped, max_depth);
+    }
+
+    let mut resolved = PathBuf::from(root);
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(part) => resolved.push(part),
+            Component::ParentDir => {
+                if resolved == root {
+                    return Err("Ruta fuera de la órbita segura.".into());
+                }
+                resolved.pop();
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err("Ruta inválida".into());
+            }
+        }
+    }
+
+    if !resolved.starts_with(root) {
+        return Err("Ruta fuera de la órbita segura.".into());
+    }
+    check_path_depth(root, &resolved, max_depth)?;
+
+    Ok(resolved)
+}
+
+// Every path a command receives goes through `sanitize_relative_path`, so the
+// limit is cached and the config only re-read when its file changes on disk.
+static MAX_PATH_DEPTH_CACHE: Mutex<Option<(Option<SystemTime>, usize)>> = Mutex::new(None);
+
+fn max_path_depth() -> usize {
+    let modified = config_path()
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok());
+    let mut cache = MAX_PATH_DEPTH_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((cached_at, depth)) = *cache {
+        if cached_at == modified {
+            return depth;
+        }
+    }
+    let depth = load_config()
+        .max_path_depth
+        .unwrap_or(DEFAULT_MAX_PATH_DEPTH);
+    *cache = Some((modified, depth));
+    depth
+}
+
+fn check_path_depth(root: &Path, target: &Path, max_depth: usize) -> McpResult<()> {
+    let depth = target
+        .strip_prefix(root)
+        .map_or(0, |relative| relative.components().count());
+    if depth > max_depth {
+        return Err(format!(
+            "La ruta supera la profundidad máxima permitida ({depth} niveles; el máximo es {max_depth})."
+        ));
+    }
+    Ok(())
+}
+
+fn relative_from_root(root: &Path, target: &Path) -> McpResult<String> {
+    let relative = target
+        .strip_prefix(root)
+        .map_err(|_| "Ruta fuera de la órbita segura.".to_string())?;
+
+    if relative.as_os_str().is_empty() {
+        Ok(".".to_string())
+    } else {
+        Ok(relative.to_string_lossy().replace('\\', "/"))
+    }
+}
+
+// The entry itself may be a link, but the directories leading to it must not
+// pass through one that leaves the orbit.
+fn ensure_parent_inside(root: &Path, target: &Path) -> McpResult<()> {
+    let real_root = root.canonicalize().map_err(|err| err.to_string())?;
+    let real_parent = target
+        .parent()
+        .unwrap_or(root)
+        .canonicalize()
+        .map_err(|err| err.to_string())?;
+    if !real_parent.starts_with(&real_root) {
+        return Err("Ruta fuera de la órbita segura.".into());
+    }
+    Ok(())
+}
+
+fn is_case_only_rename(from: &Path, to: &Path) -> bool {
+    if from.parent() != to.parent() {
+        return false;
+    }
+
+    match (from.file_name(), to.file_name()) {
+        (Some(source), Some(target)) => {
+            source != target
+                && source.to_string_lossy().to_lowercase()
+                    == target.to_string_lossy().to_lowercase()
+        }
+        _ => false,
+    }
+}
+
+// Whether both paths name the same directory entry, as the two spellings of a
+// case-only rename do on case-insensitive filesystems. On a case-sensitive one
+// `a.txt` and `A.txt` can be two different files.
+#[cfg(unix)]
+fn is_same_entry(first: &Path, second: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::symlink_metadata(first), fs::symlink_metadata(second)) {
+        (Ok(first), Ok(second)) => first.dev() == second.dev() && first.ino() == second.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_entry(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
+
+// Case-insensitive filesystems (macOS, Windows) treat `Readme.md` -> `README.md`
+// as a no-op, so case-only renames hop through a temporary sibling name.
+fn rename_path(from: &Path, to: &Path) -> io::Result<()> {
+    if !is_case_only_rename(from, to) {
+        return fs::rename(from, to);
+    }
+
+    let parent = from.parent().unwrap_or_else(|| Path::new("."));
+    let timestamp = current_timestamp_ms();
+    let mut index = 0_u32;
+    let mut temporary = parent.join(format!(".cerebro-rename-{}", timestamp));
+    while temporary.exists() {
+        index += 1;
+        temporary = parent.join(format!(".cerebro-rename-{}-{}", timestamp, index));
+    }
+
+    fs::rename(from, &temporary)?;
+    if let Err(err) = fs::rename(&temporary, to) {
+        let _ = fs::rename(&temporary, from);
+        return Err(err);
+    }
+    Ok(())
+}
+
+// Whether a symlink created in `parent` pointing to `link` resolves inside
+// `real_root`. Links already on disk are followed, so chains are caught.
+// Whatever part doesn't exist yet may not contain `..`: one of its components
+// could later become a link and change where it lands.
+fn link_resolves_inside(parent: &Path, link: &Path, real_root: &Path) -> bool {
+    let mut existing = parent.join(link);
+    let real = loop {
+        if let Ok(real) = existing.canonicalize() {
+            break real;
+        }
+        match existing.components().next_back() {
+            Some(Component::Normal(_)) => {}
+            _ => return false,
+        }
+        existing.pop();
+    };
+    real.starts_with(real_root)
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let link = fs::read_link(from)?;
+    if fs::metadata(from).is_ok_and(|target| target.is_dir()) {
+        std::os::windows::fs::symlink_dir(link, to)
+    } else {
+        std::os::windows::fs::symlink_file(link, to)
+    }
+}
+
+// Copies a file, symlink or whole directory tree, recreating symlinks as links
+// rather than copying what they point to. With `merge`, existing directories
+// are reused and existing files replaced. With `links_within`, links that would
+// resolve outside that (canonical) directory from their new place are not
+// recreated; their sources are added to `skipped` instead.
+// Returns the number of files and bytes copied.
+fn copy_tree(
+    from: &Path,
+    to: &Path,
+    merge: bool,
+    links_within: Option<&Path>,
+    skipped: &mut Vec<PathBuf>,
+) -> io::Result<(usize, u64)> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        if let Some(real_root) = links_within {
+            let parent = to.parent().unwrap_or_else(|| Path::new("."));
+            if !link_resolves_inside(parent, &fs::read_link(from)?, real_root) {
+                skipped.push(from.to_path_buf());
+                return Ok((0, 0));
+            }
+        }
+        if merge && fs::symlink_metadata(to).is_ok_and(|existing| !existing.is_dir()) {
+            fs::remove_file(to)?;
+        }
+        copy_symlink(from, to)?;
+        return Ok((1, 0));
+    }
+    if !metadata.is_dir() {
+        return Ok((1, fs::copy(from, to)?));
+    }
+
+    if !(merge && to.is_dir()) {
+        fs::create_dir(to)?;
+    }
+    let (mut files, mut bytes) = (0, 0);
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (entry_files, entry_bytes) = copy_tree(
+            &entry.path(),
+            &to.join(entry.file_name()),
+            merge,
+            links_within,
+            skipped,
+        )?;
+        files += entry_files;
+        bytes += entry_bytes;
+    }
+    fs::set_permissions(to, metadata.permissions())?;
+    Ok((files, bytes))
+}
+
+// Fallback for renames across filesystems: copy next to the destination, swap
+// it into place, then remove the source. A failed copy leaves both sides as they were.
+fn move_by_copy(from: &Path, to: &Path) -> io::Result<()> {
+    let parent = to.parent().unwrap_or_else(|| Path::new("."));
+    let timestamp = current_timestamp_ms();
+    let mut index = 0_u32;
+    let mut temporary = parent.join(format!(".cerebro-move-{}", timestamp));
+    while temporary.exists() {
+        index += 1;
+        temporary = parent.join(format!(".cerebro-move-{}-{}", timestamp, index));
+    }
+
+    // Links move unchanged, exactly as a rename would leave them.
+    let copied = copy_tree(from, &temporary, false, None, &mut Vec::new());
+    if let Err(err) = copied.and_then(|_| fs::rename(&temporary, to)) {
+        let _ = if temporary.is_dir() {
+            fs::remove_dir_all(&temporary)
+        } else {
+            fs::remove_file(&temporary)
+        };
+        return Err(err);
+    }
+
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+// `*`, `?` and `[...]` stay within one path segment, `**` spans segments.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            if pattern.get(2) == Some(&'/') {
+                // `**/` also matches zero directories.
+                (0..=text.len()).any(|index| {
+                    (index == 0 || text[index - 1] == '/')
+                        && glob_match_chars(&pattern[3..], &text[index..])
+                })
+            } else {
+                (0..=text.len()).any(|index| glob_match_chars(&pattern[2..], &text[index..]))
+            }
+        }
+        Some('*') => {
+            for index in 0..=text.len() {
+                if glob_match_chars(&pattern[1..], &text[index..]) {
+                    return true;
+                }
+                if index < text.len() && text[index] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            !text.is_empty() && text[0] != '/' && glob_match_chars(&pattern[1..], &text[1..])
+        }
+        Some('[') => match match_char_class(&pattern[1..], text.first().copied()) {
+            Some((matched, consumed)) => {
+                matched && glob_match_chars(&pattern[1 + consumed..], &text[1..])
+            }
+            // No closing bracket: treat `[` literally.
+            None => text.first() == Some(&'[') && glob_match_chars(&pattern[1..], &text[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match_chars(&pattern[2..], &text[1..])
+        }
+        Some(expected) => {
+            text.first() == Some(expected) && glob_match_chars(&pattern[1..], &text[1..])
+        }
+    }
+}
+
+// `class` starts right after `[`. Returns whether `candidate` matched and how
+// many pattern chars the class used, or `None` if the class never closes.
+fn match_char_class(class: &[char], candidate: Option<char>) -> Option<(bool, usize)> {
+    let negated = matches!(class.first(), Some('!') | Some('^'));
+    let mut index = usize::from(negated);
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let current = *class.get(index)?;
+        if current == ']' && !first {
+            break;
+        }
+        first = false;
+        if class.get(index + 1) == Some(&'-') && class.get(index + 2).is_some_and(|end| *end != ']')
+        {
+            let end = class[index + 2];
+            if candidate.is_some_and(|ch| current <= ch && ch <= end) {
+                matched = true;
+            }
+            index += 3;
+        } else {
+            if candidate == Some(current) {
+                matched = true;
+            }
+            index += 1;
+        }
+    }
+    let matched = match candidate {
+        Some(ch) if ch != '/' => matched != negated,
+        _ => false,
+    };
+    Some((matched, index + 1))
+}
+
+fn system_time_to_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_millis() as u64)
+}
+
+fn current_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn read_hostname() -> Option<String> {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| {
+            Command::new("hostname")
+                .output()
+                .ok()
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|value| value.trim().to_string())
+        })
+        .filter(|value| !value.is_empty())
+}
+
+fn read_uname(flag: &str) -> Option<String> {
+    Command::new("uname")
+        .arg(flag)
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn read_linux_meminfo() -> Option<(u64, u64, u64, u64, u64)> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let contents = fs::read_to_string("/proc/meminfo").ok()?;
+    let mut mem_total = 0_u64;
+    let mut mem_free = 0_u64;
+    let mut mem_available = 0_u64;
+    let mut swap_total = 0_u64;
+    let mut swap_free = 0_u64;
 
     for line in contents.lines() {
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() < 2 {
             continue;
         }
-        let key = parts[0].trim_end_matches(':');
-        let value = parts[1].parse::<u64>().unwrap_or(0) * 1024; // kB -> bytes
-        match key {
-            "MemTotal" => mem_total = value,
-            "MemFree" => mem_free = value,
-            "MemAvailable" => mem_available = value,
-            "SwapTotal" => swap_total = value,
-            "SwapFree" => swap_free = value,
-            _ => {}
+        let key = parts[0].trim_end_matches(':');
+        let value = parts[1].parse::<u64>().unwrap_or(0) * 1024; // kB -> bytes
+        match key {
+            "MemTotal" => mem_total = value,
+            "MemFree" => mem_free = value,
+            "MemAvailable" => mem_available = value,
+            "SwapTotal" => swap_total = value,
+            "SwapFree" => swap_free = value,
+            _ => {}
+        }
+    }
+
+    if mem_total == 0 {
+        return None;
+    }
+
+    Some((mem_total, mem_free, mem_available, swap_total, swap_free))
+}
+
+fn read_linux_uptime() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let contents = fs::read_to_string("/proc/uptime").ok()?;
+    let first = contents.split_whitespace().next()?;
+    let seconds = first.split('.').next()?.parse::<u64>().ok()?;
+    Some(seconds)
+}
+
+fn count_linux_processes() -> Option<usize> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let entries = fs::read_dir("/proc").ok()?;
+    let mut count = 0_usize;
+    for entry in entries {
+        if let Ok(dir) = entry {
+            if let Some(name) = dir.file_name().to_str() {
+                if name.chars().all(|c| c.is_ascii_digit()) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    Some(count)
+}
+
+fn config_path() -> McpResult<PathBuf> {
+    let home = resolve_home_dir()?;
+    Ok(home.join(".cerebro").join("config.json"))
+}
+
+fn load_config() -> CerebroConfig {
+    let Ok(path) = config_path() else {
+        return CerebroConfig::default();
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return CerebroConfig::default();
+    };
+
+    serde_json::from_str(&contents).unwrap_or_else(|error| {
+        eprintln!("[config] failed to parse {}: {error}", path.display());
+        CerebroConfig::default()
+    })
+}
+
+fn resolve_shell_timeout_ms(config: &CerebroConfig, command: &str, explicit: Option<u64>) -> u64 {
+    explicit
+        .or_else(|| {
+            config
+                .command_timeouts
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(command))
+                .map(|(_, timeout)| *timeout)
+        })
+        .filter(|timeout| *timeout > 0)
+        .unwrap_or(DEFAULT_SHELL_TIMEOUT_MS)
+}
+
+fn looks_like_path_arg(value: &str) -> bool {
+    value.contains('/') || value.contains('\\') || value.starts_with('.')
+}
+
+// Lexically resolves each path-like argument against the working directory and
+// rejects anything that would land outside the orbit. Flags of the form
+// `--output=../x` are checked on their value.
+fn validate_path_args(root: &Path, working_dir: &Path, args: &[String]) -> McpResult<()> {
+    for arg in args {
+        let value = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => value,
+            _ if arg.starts_with('-') => continue,
+            _ => arg.as_str(),
+        };
+        if !looks_like_path_arg(value) {
+            continue;
+        }
+
+        let candidate = Path::new(value);
+        let mut resolved = if candidate.is_absolute() {
+            PathBuf::new()
+        } else {
+            working_dir.to_path_buf()
+        };
+        for component in candidate.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                other => resolved.push(other.as_os_str()),
+            }
+        }
+
+        if !resolved.starts_with(root) {
+            return Err(format!(
+                "El argumento '{arg}' apunta fuera de la órbita segura."
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn metrics_log_path() -> McpResult<PathBuf> {
+    let home = resolve_home_dir()?;
+    let directory = home.join(".cerebro").join("logs");
+    if !directory.exists() {
+        fs::create_dir_all(&directory).map_err(|err| err.to_string())?;
+    }
+    Ok(directory.join("metrics.jsonl"))
+}
+
+// Returns the archive the log was moved to, if it was rotated.
+fn rotate_metrics_log_if_needed(path: &Path) -> McpResult<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let metadata = fs::metadata(path).map_err(|err| err.to_string())?;
+    let mut should_rotate = metadata.len() > MAX_METRICS_FILE_SIZE;
+
+    if let Ok(modified) = metadata.modified() {
+        if let Ok(age) = SystemTime::now().duration_since(modified) {
+            if age.as_secs() > MAX_METRICS_FILE_AGE_SECS {
+                should_rotate = true;
+            }
+        }
+    }
+
+    if !should_rotate {
+        return Ok(None);
+    }
+
+    let directory = path
+        .parent()
+        .ok_or_else(|| "No se pudo resolver el directorio de métricas.".to_string())?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let mut candidate = directory.join(format!("metrics-{}.jsonl", timestamp));
+    let mut index = 1_u32;
+
+    while candidate.exists() {
+        candidate = directory.join(format!("metrics-{}-{}.jsonl", timestamp, index));
+        index += 1;
+    }
+
+    fs::rename(path, &candidate).map_err(|err| err.to_string())?;
+    Ok(Some(candidate))
+}
+
+// Archives are named `metrics-<rotated at, secs>[-n].jsonl`; sorted oldest first.
+fn rotated_metrics_logs(directory: &Path) -> Vec<(u64, u32, PathBuf)> {
+    let mut archives = Vec::new();
+    let Ok(entries) = fs::read_dir(directory) else {
+        return archives;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(stem) = file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix("metrics-"))
+            .and_then(|name| name.strip_suffix(".jsonl"))
+        else {
+            continue;
+        };
+        let (seconds, index) = match stem.split_once('-') {
+            Some((seconds, index)) => (seconds.parse().ok(), index.parse().ok()),
+            None => (stem.parse().ok(), Some(0)),
+        };
+        if let (Some(seconds), Some(index)) = (seconds, index) {
+            archives.push((seconds, index, entry.path()));
+        }
+    }
+    archives.sort();
+    archives
+}
+
+fn disk_info_for_path(system: &System, target: &Path) -> Option<DiskInfo> {
+    let mut selected_index: Option<usize> = None;
+    let mut best_depth = 0_usize;
+
+    for (index, disk) in system.disks().iter().enumerate() {
+        let mount_point = disk.mount_point();
+        if target.starts_with(mount_point) {
+            let depth = mount_point.components().count();
+            if depth > best_depth {
+                best_depth = depth;
+                selected_index = Some(index);
+            }
+        }
+    }
+
+    selected_index.map(|index| {
+        let disk = &system.disks()[index];
+        let total = disk.total_space();
+        let free = disk.available_space();
+        let usage_percent = if total > 0 {
+            Some(((total.saturating_sub(free)) as f64 / total as f64 * 100.0) as f32)
+        } else {
+            None
+        };
+        DiskInfo {
+            mount: disk.mount_point().to_string_lossy().replace('\\', "/"),
+            total,
+            free,
+            usage_percent,
+        }
+    })
+}
+
+const CRC32_TABLE: [u32; 256] = build_crc32_table();
+
+const fn build_crc32_table() -> [u32; 256] {
+    let mut table = [0_u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut value = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                0xEDB8_8320 ^ (value >> 1)
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+        table[index] = value;
+        index += 1;
+    }
+    table
+}
+
+// Standard CRC-32 (IEEE 802.3), fed incrementally: start from 0 and pass the
+// previous result back in for each chunk.
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut value = !crc;
+    for byte in data {
+        value = CRC32_TABLE[((value ^ *byte as u32) & 0xff) as usize] ^ (value >> 8);
+    }
+    !value
+}
+
+const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    if data.is_empty() {
+        return String::new();
+    }
+
+    let mut encoded = String::with_capacity(((data.len() + 2) / 3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+        let idx0 = ((triple >> 18) & 0x3F) as usize;
+        let idx1 = ((triple >> 12) & 0x3F) as usize;
+        let idx2 = ((triple >> 6) & 0x3F) as usize;
+        let idx3 = (triple & 0x3F) as usize;
+
+        encoded.push(BASE64_TABLE[idx0] as char);
+        encoded.push(BASE64_TABLE[idx1] as char);
+        if chunk.len() > 1 {
+            encoded.push(BASE64_TABLE[idx2] as char);
+        } else {
+            encoded.push('=');
+        }
+        if chunk.len() > 2 {
+            encoded.push(BASE64_TABLE[idx3] as char);
+        } else {
+            encoded.push('=');
+        }
+    }
+
+    encoded
+}
+
+fn decode_base64(input: &str) -> McpResult<Vec<u8>> {
+    let cleaned: String = input.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+    if cleaned.len() % 4 != 0 {
+        return Err("Cadena base64 inválida.".into());
+    }
+
+    let mut output = Vec::with_capacity((cleaned.len() / 4) * 3);
+
+    for chunk in cleaned.as_bytes().chunks(4) {
+        let mut buffer = [0_u32; 4];
+        let mut padding = 0;
+
+        for (index, ch) in chunk.iter().enumerate() {
+            if *ch == b'=' {
+                buffer[index] = 0;
+                padding += 1;
+            } else {
+                buffer[index] =
+                    decode_base64_char(*ch).ok_or_else(|| "Cadena base64 inválida.".to_string())?;
+            }
+        }
+
+        let triple = (buffer[0] << 18) | (buffer[1] << 12) | (buffer[2] << 6) | buffer[3];
+        output.push(((triple >> 16) & 0xFF) as u8);
+        if padding < 2 {
+            output.push(((triple >> 8) & 0xFF) as u8);
+        }
+        if padding < 1 {
+            output.push((triple & 0xFF) as u8);
+        }
+    }
+
+    Ok(output)
+}
+
+fn decode_base64_char(byte: u8) -> Option<u32> {
+    match byte {
+        b'A'..=b'Z' => Some((byte - b'A') as u32),
+        b'a'..=b'z' => Some((byte - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((byte - b'0' + 52) as u32),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Hashes through a buffered reader so large files never sit in memory whole.
+// Returns the digest together with the number of bytes actually hashed.
+fn sha256_file_hex(path: &Path) -> io::Result<(String, u64)> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut reader, &mut hasher)?;
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((digest, size))
+}
+
+fn read_file_bytes(target: &Path) -> McpResult<Vec<u8>> {
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    fs::read(target).map_err(|err| err.to_string())
+}
+
+// Streams through the decoder so a small archive that inflates past the limit
+// (a "zip bomb") is rejected without ever holding the full output.
+fn read_file_decompressed(target: &Path, format: &str) -> McpResult<Vec<u8>> {
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let file = BufReader::new(fs::File::open(target).map_err(|err| err.to_string())?);
+    let decoder: Box<dyn Read> = match format.to_ascii_lowercase().as_str() {
+        "gzip" | "gz" => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+        "zlib" => Box::new(flate2::bufread::ZlibDecoder::new(file)),
+        "deflate" => Box::new(flate2::bufread::DeflateDecoder::new(file)),
+        _ => return Err("Formato de compresión no soportado. Usa gzip, zlib o deflate.".into()),
+    };
+
+    let mut data = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_READ_BYTES + 1)
+        .read_to_end(&mut data)
+        .map_err(|err| format!("No se pudo descomprimir el archivo: {err}"))?;
+    if data.len() as u64 > MAX_DECOMPRESSED_READ_BYTES {
+        return Err(format!(
+            "El contenido descomprimido supera el máximo de {} MiB.",
+            MAX_DECOMPRESSED_READ_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(data)
+}
+
+// Runs `task` on a worker thread and gives up after `limit`. A stalled worker is
+// left to finish (or hang) on its own; its result is simply dropped.
+fn run_with_deadline<T, F>(limit: Duration, task: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(task());
+    });
+    receiver.recv_timeout(limit).ok()
+}
+
+fn read_text_file(target: &Path) -> McpResult<String> {
+    let data = read_file_bytes(target)?;
+    String::from_utf8(data).map_err(|_| "El archivo no está codificado como UTF-8.".to_string())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+impl DiffKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            DiffKind::Equal => "equal",
+            DiffKind::Insert => "insert",
+            DiffKind::Delete => "delete",
+        }
+    }
+}
+
+fn tokenize_words(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0_usize;
+    let mut current: Option<bool> = None; // Some(true) = word, Some(false) = whitespace
+
+    for (index, ch) in text.char_indices() {
+        let class = if ch.is_alphanumeric() || ch == '_' {
+            Some(true)
+        } else if ch.is_whitespace() {
+            Some(false)
+        } else {
+            None
+        };
+
+        if index > start && (class.is_none() || class != current) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        current = class;
+    }
+
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+// LCS diff over token sequences. When the table would exceed `max_cells` the
+// differing middle is reported as one delete/insert pair and `false` is returned.
+fn diff_tokens<'a>(
+    a: &[&'a str],
+    b: &[&'a str],
+    max_cells: usize,
+) -> (Vec<(DiffKind, &'a str)>, bool) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+    let mut ops: Vec<(DiffKind, &'a str)> = a[..prefix]
+        .iter()
+        .map(|token| (DiffKind::Equal, *token))
+        .collect();
+
+    let n = a_mid.len();
+    let m = b_mid.len();
+    let exact = (n + 1).saturating_mul(m + 1) <= max_cells;
+
+    if exact {
+        let width = m + 1;
+        let mut table = vec![0_u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                table[i * width + j] = if a_mid[i] == b_mid[j] {
+                    table[(i + 1) * width + j + 1] + 1
+                } else {
+                    table[(i + 1) * width + j].max(table[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0_usize, 0_usize);
+        while i < n && j < m {
+            if a_mid[i] == b_mid[j] {
+                ops.push((DiffKind::Equal, a_mid[i]));
+                i += 1;
+                j += 1;
+            } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+                ops.push((DiffKind::Delete, a_mid[i]));
+                i += 1;
+            } else {
+                ops.push((DiffKind::Insert, b_mid[j]));
+                j += 1;
+            }
+        }
+        ops.extend(a_mid[i..].iter().map(|token| (DiffKind::Delete, *token)));
+        ops.extend(b_mid[j..].iter().map(|token| (DiffKind::Insert, *token)));
+    } else {
+        ops.extend(a_mid.iter().map(|token| (DiffKind::Delete, *token)));
+        ops.extend(b_mid.iter().map(|token| (DiffKind::Insert, *token)));
+    }
+
+    ops.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .map(|token| (DiffKind::Equal, *token)),
+    );
+    (ops, exact)
+}
+
+fn merge_diff_ops(ops: Vec<(DiffKind, &str)>) -> Vec<DiffSpan> {
+    let mut spans: Vec<(DiffKind, String)> = Vec::new();
+    for (kind, text) in ops {
+        match spans.last_mut() {
+            Some((last_kind, last_text)) if *last_kind == kind => last_text.push_str(text),
+            _ => spans.push((kind, text.to_string())),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(kind, text)| DiffSpan {
+            kind: kind.as_str().to_string(),
+            text,
+        })
+        .collect()
+}
+
+// With `create_new` an existing file is an error, raised atomically so there is
+// no window between the existence check and the write; otherwise it is
+// truncated. On Unix `mode` is set before any content is written: a new file is
+// created with it and an existing one switched to it, so the payload is never
+// readable with looser (umask or previous) permissions.
+fn write_file_contents(
+    target: &Path,
+    payload: &[u8],
+    create_new: bool,
+    mode: Option<u32>,
+) -> McpResult<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    let mut file = options.open(target).map_err(|err| {
+        if err.kind() == io::ErrorKind::AlreadyExists {
+            "El archivo ya existe.".to_string()
+        } else {
+            err.to_string()
+        }
+    })?;
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        // The umask may have narrowed the creation mode, and an existing file keeps its own.
+        file.set_permissions(fs::Permissions::from_mode(mode))
+            .map_err(|err| err.to_string())?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    file.write_all(payload).map_err(|err| err.to_string())
+}
+
+fn read_file_sample(target: &Path, limit: usize) -> McpResult<Vec<u8>> {
+    let file = fs::File::open(target).map_err(|err| err.to_string())?;
+    let mut sample = Vec::with_capacity(limit);
+    file.take(limit as u64)
+        .read_to_end(&mut sample)
+        .map_err(|err| err.to_string())?;
+    Ok(sample)
+}
+
+// Returns (style, size, confidence, indented lines seen). Space width is taken
+// from the most common change in indentation between consecutive lines, which
+// is robust to deeply nested files where absolute widths vary.
+fn detect_indent(text: &str) -> (&'static str, u8, f32, usize) {
+    let mut tab_lines = 0_usize;
+    let mut space_lines = 0_usize;
+    let mut deltas = [0_usize; MAX_INDENT_SIZE + 1];
+    let mut previous_width = 0_usize;
+
+    for line in text.lines() {
+        let content = line.trim_start_matches([' ', '\t']);
+        if content.is_empty() {
+            continue;
+        }
+        let leading = &line[..line.len() - content.len()];
+
+        if leading.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+        // ` * ` continuation lines in block comments are aligned, not indented.
+        if content.starts_with('*') {
+            continue;
+        }
+
+        let width = leading.len();
+        if width > 0 {
+            space_lines += 1;
+        }
+        let delta = width.abs_diff(previous_width);
+        if (1..=MAX_INDENT_SIZE).contains(&delta) {
+            deltas[delta] += 1;
+        }
+        previous_width = width;
+    }
+
+    let indented = tab_lines + space_lines;
+    if indented == 0 {
+        return ("space", DEFAULT_INDENT_SIZE, 0.0, 0);
+    }
+
+    if tab_lines > space_lines {
+        return ("tab", 1, tab_lines as f32 / indented as f32, indented);
+    }
+
+    let total_deltas: usize = deltas.iter().sum();
+    // `max_by_key` keeps the last maximum, so walking widths in reverse breaks
+    // ties toward the smaller one.
+    let (size, hits) = deltas
+        .iter()
+        .enumerate()
+        .skip(1)
+        .rev()
+        .max_by_key(|(_, count)| **count)
+        .map(|(size, count)| (size, *count))
+        .unwrap_or((DEFAULT_INDENT_SIZE as usize, 0));
+    let delta_share = if total_deltas > 0 {
+        hits as f32 / total_deltas as f32
+    } else {
+        0.0
+    };
+
+    (
+        "space",
+        size as u8,
+        space_lines as f32 / indented as f32 * delta_share,
+        indented,
+    )
+}
+
+// Returns (charset, confidence, has_bom) for a byte sample.
+fn detect_charset(sample: &[u8]) -> (&'static str, f32, bool) {
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return ("utf-8", 1.0, true);
+    }
+    if sample.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        return ("utf-32le", 1.0, true);
+    }
+    if sample.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        return ("utf-32be", 1.0, true);
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return ("utf-16le", 1.0, true);
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return ("utf-16be", 1.0, true);
+    }
+
+    if sample.is_empty() || sample.is_ascii() {
+        return ("ascii", 1.0, false);
+    }
+
+    if sample.contains(&0) {
+        // Text in UTF-16 without a BOM shows NULs in every other byte.
+        let pairs = sample.len() / 2;
+        let even_nuls = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+        let odd_nuls = sample
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count();
+        if pairs > 0 && odd_nuls * 10 >= pairs * 4 && even_nuls * 10 < pairs {
+            return ("utf-16le", 0.6, false);
+        }
+        if pairs > 0 && even_nuls * 10 >= pairs * 4 && odd_nuls * 10 < pairs {
+            return ("utf-16be", 0.6, false);
+        }
+        return ("binary", 0.8, false);
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => return ("utf-8", 0.99, false),
+        // The sample may cut a multi-byte sequence at its end.
+        Err(error) if error.error_len().is_none() => return ("utf-8", 0.95, false),
+        Err(_) => {}
+    }
+
+    // 0x80-0x9F are control codes in latin1 but printable in windows-1252,
+    // except for the five positions windows-1252 leaves undefined.
+    let c1_bytes: Vec<u8> = sample
+        .iter()
+        .copied()
+        .filter(|byte| (0x80..=0x9F).contains(byte))
+        .collect();
+    if c1_bytes.is_empty() {
+        return ("iso-8859-1", 0.6, false);
+    }
+
+    let undefined_in_1252 = c1_bytes
+        .iter()
+        .filter(|byte| matches!(byte, 0x81 | 0x8D | 0x8F | 0x90 | 0x9D))
+        .count();
+    if undefined_in_1252 == 0 {
+        ("windows-1252", 0.8, false)
+    } else {
+        ("iso-8859-1", 0.4, false)
+    }
+}
+
+fn dtype_size(dtype: &str) -> Option<usize> {
+    match dtype {
+        "u8" | "i8" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" | "f32" => Some(4),
+        "u64" | "i64" | "f64" => Some(8),
+        _ => None,
+    }
+}
+
+fn fixed_bytes<const N: usize>(chunk: &[u8]) -> [u8; N] {
+    let mut bytes = [0_u8; N];
+    bytes.copy_from_slice(&chunk[..N]);
+    bytes
+}
+
+fn float_value(value: f64) -> serde_json::Value {
+    // JSON has no NaN/Infinity; those samples come back as null.
+    serde_json::Number::from_f64(value)
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
+
+fn decode_typed_value(dtype: &str, chunk: &[u8], little_endian: bool) -> serde_json::Value {
+    macro_rules! decode {
+        ($ty:ty, $n:literal) => {{
+            let bytes = fixed_bytes::<$n>(chunk);
+            if little_endian {
+                <$ty>::from_le_bytes(bytes)
+            } else {
+                <$ty>::from_be_bytes(bytes)
+            }
+        }};
+    }
+
+    match dtype {
+        "u8" => serde_json::Value::from(chunk[0]),
+        "i8" => serde_json::Value::from(chunk[0] as i8),
+        "u16" => serde_json::Value::from(decode!(u16, 2)),
+        "i16" => serde_json::Value::from(decode!(i16, 2)),
+        "u32" => serde_json::Value::from(decode!(u32, 4)),
+        "i32" => serde_json::Value::from(decode!(i32, 4)),
+        "u64" => serde_json::Value::from(decode!(u64, 8)),
+        "i64" => serde_json::Value::from(decode!(i64, 8)),
+        "f32" => float_value(decode!(f32, 4) as f64),
+        "f64" => float_value(decode!(f64, 8)),
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn spawn_command(
+    mut cmd: Command,
+    command_name: String,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    options: ExecOptions<'_>,
+) -> McpResult<ExecResponse> {
+    if let Some(ref directory) = cwd {
+        cmd.current_dir(directory);
+    }
+
+    let start = Instant::now();
+    let (output, timed_out) = match options.job {
+        Some(job) => {
+            job.registry.reserve(&job.run_id, &command_name)?;
+            let result = run_collecting(cmd, options.timeout, Some(&job), options.merge_output);
+            job.registry.finish(&job.run_id);
+            result?
+        }
+        None => run_collecting(cmd, options.timeout, None, options.merge_output)?,
+    };
+    let duration = start.elapsed().as_millis();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    // A timeout still reports whatever the command printed before it was killed.
+    let exit_code = if timed_out {
+        if !stderr.is_empty() && !stderr.ends_with('\n') {
+            stderr.push('\n');
+        }
+        stderr.push_str(&timeout_message(options.timeout));
+        stderr.push('\n');
+        SHELL_TIMEOUT_EXIT_CODE
+    } else {
+        output.status.code().unwrap_or(-1)
+    };
+
+    let root = safe_root()?;
+    let cwd_relative = cwd.and_then(|dir| relative_from_root(&root, &dir).ok());
+
+    Ok(ExecResponse {
+        command: command_name,
+        args,
+        cwd: cwd_relative,
+        exit_code,
+        stdout,
+        stderr,
+        duration_ms: duration,
+    })
+}
+
+fn apply_command_env(cmd: &mut Command, env: Option<HashMap<String, String>>, clean_env: bool) {
+    if clean_env {
+        cmd.env_clear();
+        for key in CLEAN_ENV_VARS {
+            if let Some(value) = env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+    }
+
+    if let Some(env_vars) = env {
+        for (key, value) in env_vars {
+            cmd.env(key, value);
+        }
+    }
+}
+
+// Output read so far stays reachable through `buffer` even if the reader
+// thread never sees EOF.
+struct PipeDrain {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl PipeDrain {
+    // Waits for EOF, or at most `grace` when given, and returns what was read.
+    fn collect(self, grace: Option<Duration>) -> Vec<u8> {
+        match grace {
+            None => {
+                let _ = self.handle.join();
+            }
+            Some(grace) => {
+                let deadline = Instant::now() + grace;
+                while !self.handle.is_finished() && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        }
+        self.buffer
+            .lock()
+            .map(|mut buffer| std::mem::take(&mut *buffer))
+            .unwrap_or_default()
+    }
+}
+
+fn drain_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> PipeDrain {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let sink = buffer.clone();
+    let handle = thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0_u8; 8192];
+        loop {
+            match pipe.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => match sink.lock() {
+                    Ok(mut sink) => sink.extend_from_slice(&chunk[..read]),
+                    Err(_) => break,
+                },
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+    });
+    PipeDrain { buffer, handle }
+}
+
+fn timeout_message(limit: Option<Duration>) -> String {
+    format!(
+        "El comando superó el tiempo límite de {} ms.",
+        limit.unwrap_or_default().as_millis()
+    )
+}
+
+// Kills the child together with anything it spawned into its process group
+// (npm -> node, cargo -> rustc), so no orphan keeps running or holds the pipes.
+fn kill_child_tree(child: &mut Child) {
+    kill_process_group(child.id());
+    let _ = child.kill();
+}
+
+// Children spawned with `process_group(0)` lead a group whose id is their pid.
+fn kill_process_group(pgid: u32) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{pgid}")])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+fn run_with_timeout(
+    cmd: Command,
+    limit: Option<Duration>,
+    job: Option<&JobTracker<'_>>,
+    merge_output: bool,
+) -> McpResult<Output> {
+    match run_collecting(cmd, limit, job, merge_output)? {
+        (_, true) => Err(timeout_message(limit)),
+        (output, false) => Ok(output),
+    }
+}
+
+// Like `run_with_timeout`, but a timeout returns the output captured until the
+// kill, flagged with `true`, instead of an error.
+fn run_collecting(
+    mut cmd: Command,
+    limit: Option<Duration>,
+    job: Option<&JobTracker<'_>>,
+    merge_output: bool,
+) -> McpResult<(Output, bool)> {
+    cmd.stdin(Stdio::null());
+    // Its own process group lets a timeout take down the whole tree at once.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let merged_reader = if merge_output {
+        let (reader, writer) = io::pipe().map_err(|err| err.to_string())?;
+        let writer_clone = writer.try_clone().map_err(|err| err.to_string())?;
+        cmd.stdout(writer_clone);
+        cmd.stderr(writer);
+        Some(reader)
+    } else {
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        None
+    };
+
+    let mut child = cmd.spawn().map_err(|err| err.to_string())?;
+    // The command still owns our copies of the merged pipe's write end; drop it so
+    // the reader sees EOF once the child exits.
+    drop(cmd);
+    if let Some(job) = job {
+        job.registry.attach_pid(&job.run_id, child.id());
+    }
+    // Drain both pipes while waiting so a chatty child can't block on a full buffer.
+    let (stdout, stderr) = match merged_reader {
+        Some(reader) => (drain_pipe(Some(reader)), drain_pipe(None::<io::PipeReader>)),
+        None => (
+            drain_pipe(child.stdout.take()),
+            drain_pipe(child.stderr.take()),
+        ),
+    };
+
+    let kill = job.and_then(|job| job.registry.kill_flag(&job.run_id));
+    let (status, timed_out) =
+        match wait_for_child(&mut child, limit, kill.as_deref()).map_err(|err| err.to_string())? {
+            Some(status) => (status, false),
+            None => {
+                kill_child_tree(&mut child);
+                let status = child.wait().map_err(|err| err.to_string())?;
+                if kill.is_some_and(|kill| kill.load(Ordering::SeqCst)) {
+                    return Err("La ejecución se detuvo a petición del usuario.".into());
+                }
+                (status, true)
+            }
+        };
+
+    let grace = timed_out.then(|| Duration::from_millis(KILLED_OUTPUT_GRACE_MS));
+    Ok((
+        Output {
+            status,
+            stdout: stdout.collect(grace),
+            stderr: stderr.collect(grace),
+        },
+        timed_out,
+    ))
+}
+
+fn file_entry_from(root: &Path, entry: &fs::DirEntry) -> McpResult<FileEntry> {
+    let metadata = entry.metadata().map_err(|err| err.to_string())?;
+    file_entry_from_metadata(
+        root,
+        &entry.path(),
+        entry.file_name().to_string_lossy().to_string(),
+        &metadata,
+    )
+}
+
+fn file_entry_from_metadata(
+    root: &Path,
+    entry_path: &Path,
+    name: String,
+    metadata: &fs::Metadata,
+) -> McpResult<FileEntry> {
+    let entry_relative = relative_from_root(root, entry_path)?;
+    let modified = metadata.modified().ok().and_then(system_time_to_millis);
+
+    Ok(FileEntry {
+        name,
+        path: entry_relative,
+        entry_type: if metadata.is_dir() {
+            "directory".to_string()
+        } else {
+            "file".to_string()
+        },
+        size: if metadata.is_file() {
+            metadata.len()
+        } else {
+            0
+        },
+        modified_at: modified,
+    })
+}
+
+fn is_hidden_name(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+struct ListWalk<'a> {
+    root: &'a Path,
+    // Set for recursive listings; symlinks resolving outside it are left out.
+    contained_in: Option<PathBuf>,
+    include_hidden: bool,
+    filter: Option<IgnoreFilter>,
+    visited: VisitedDirs,
+    max_depth: usize,
+    max_entries: usize,
+    entries: Vec<FileEntry>,
+    truncated: bool,
+}
+
+impl ListWalk<'_> {
+    // Lists `dir` sorted by name, descending into each subdirectory right after
+    // its own entry so every level keeps the alphabetical order.
+    fn list_dir(&mut self, dir: &Path, depth: usize) -> McpResult<()> {
+        let mut level = Vec::new();
+        for entry in fs::read_dir(dir).map_err(|err| err.to_string())? {
+            let entry = entry.map_err(|err| err.to_string())?;
+            if !self.include_hidden && is_hidden_name(&entry.file_name()) {
+                continue;
+            }
+            let file_type = entry.file_type().map_err(|err| err.to_string())?;
+            if let Some(contained_in) = &self.contained_in {
+                if file_type.is_symlink()
+                    && !canonicalize_lenient(&entry.path()).starts_with(contained_in)
+                {
+                    continue;
+                }
+            }
+            let file_entry = file_entry_from(self.root, &entry)?;
+            if let Some(filter) = &self.filter {
+                if filter.is_ignored(&file_entry.path, file_entry.entry_type == "directory") {
+                    continue;
+                }
+            }
+            level.push((file_entry, file_type.is_dir()));
+        }
+        level.sort_by_key(|(entry, _)| entry.name.to_lowercase());
+
+        for (file_entry, is_dir) in level {
+            if self.entries.len() >= self.max_entries {
+                self.truncated = true;
+                return Ok(());
+            }
+            let entry_path = self.root.join(&file_entry.path);
+            self.entries.push(file_entry);
+            // Symlinked directories are listed but never followed.
+            if !is_dir || depth >= self.max_depth || !self.visited.enter(self.root, &entry_path) {
+                continue;
+            }
+            let mark = self
+                .filter
+                .as_mut()
+                .map(|filter| filter.enter_dir(self.root, &entry_path));
+            // An unreadable subdirectory still shows up; only its contents are missing.
+            let _ = self.list_dir(&entry_path, depth + 1);
+            if let (Some(filter), Some(mark)) = (self.filter.as_mut(), mark) {
+                filter.leave_dir(mark);
+            }
+            if self.truncated {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tauri::command(async)]
+fn mcp_files_list(
+    path: Option<String>,
+    show_hidden: Option<bool>,
+    respect_gitignore: Option<bool>,
+    recursive: Option<bool>,
+    max_depth: Option<usize>,
+    max_entries: Option<usize>,
+) -> McpResult<ListResponse> {
+    let orbit = safe_root()?;
+    let (target, external) = resolve_readable_path(&orbit, path.as_deref())?;
+    // Entries under a read-only root are built relative to it, then made absolute.
+    let root = external.clone().unwrap_or_else(|| orbit.clone());
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let include_hidden = show_hidden.unwrap_or(true);
+    // Without the flag the listing stays exactly as before: no ignore rules at all.
+    let filter = respect_gitignore.unwrap_or(false).then(|| {
+        let mut filter = IgnoreFilter::new(&root, &target, true);
+        filter.enter_dir(&root, &target);
+        filter
+    });
+    let recursive = recursive.unwrap_or(false);
+    let mut walk = ListWalk {
+        root: &root,
+        contained_in: recursive.then(|| canonicalize_lenient(&root)),
+        include_hidden,
+        filter,
+        visited: VisitedDirs::default(),
+        max_depth: if recursive {
+            max_depth.unwrap_or(usize::MAX).min(max_path_depth()).max(1)
+        } else {
+            1
+        },
+        max_entries: if recursive {
+            max_entries
+                .unwrap_or(DEFAULT_LIST_MAX_ENTRIES)
+                .clamp(1, MAX_LIST_MAX_ENTRIES)
+        } else {
+            usize::MAX
+        },
+        entries: Vec::new(),
+        truncated: false,
+    };
+    walk.visited.enter(&root, &target);
+    walk.list_dir(&target, 1)?;
+    let ListWalk {
+        mut entries,
+        truncated,
+        ..
+    } = walk;
+
+    if external.is_some() {
+        for entry in &mut entries {
+            entry.path = display_readable_path(&orbit, Some(&root), &root.join(&entry.path))?;
+        }
+    }
+
+    Ok(ListResponse { entries, truncated })
+}
+
+#[tauri::command]
+fn mcp_files_diff_listing(
+    path: Option<String>,
+    previous: Vec<FileEntry>,
+) -> McpResult<ListingDiffResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, path.as_deref())?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let mut previous: HashMap<String, FileEntry> = previous
+        .into_iter()
+        .map(|entry| (entry.name.clone(), entry))
+        .collect();
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+
+    for entry in fs::read_dir(&target).map_err(|err| err.to_string())? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let current = file_entry_from(&root, &entry)?;
+        match previous.remove(&current.name) {
+            None => added.push(current),
+            Some(known) => {
+                if known.entry_type != current.entry_type
+                    || known.size != current.size
+                    || known.modified_at != current.modified_at
+                {
+                    modified.push(current);
+                }
+            }
+        }
+    }
+
+    let mut removed: Vec<FileEntry> = previous.into_values().collect();
+    for entries in [&mut added, &mut removed, &mut modified] {
+        entries.sort_by_key(|entry| entry.name.to_lowercase());
+    }
+
+    Ok(ListingDiffResponse {
+        added,
+        removed,
+        modified,
+    })
+}
+
+// Writes to a temporary sibling and renames it over `target`, so readers never
+// observe a half-written file.
+fn write_file_atomic(target: &Path, data: &[u8]) -> McpResult<()> {
+    let parent = target
+        .parent()
+        .ok_or_else(|| "No se pudo resolver el directorio del archivo.".to_string())?;
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary = parent.join(format!(".{name}.cerebro-tmp-{}", current_timestamp_ms()));
+
+    let result = fs::write(&temporary, data)
+        .and_then(|_| match fs::metadata(target) {
+            Ok(metadata) => fs::set_permissions(&temporary, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&temporary, target));
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temporary);
+        return Err(err.to_string());
+    }
+    Ok(())
+}
+
+// Rewrites every line break as `\n` or `\r\n`; a lone `\r` is left alone.
+fn convert_line_endings(data: &[u8], crlf: bool) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len() / 32);
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        if byte == b'\r' && data.get(index + 1) == Some(&b'\n') {
+            index += 1;
+            continue;
+        }
+        if byte == b'\n' && crlf {
+            output.push(b'\r');
+        }
+        output.push(byte);
+        index += 1;
+    }
+    output
+}
+
+fn collect_normalize_candidates(
+    root: &Path,
+    dir: &Path,
+    extensions: &[String],
+    filter: &mut IgnoreFilter,
+    visited: &mut VisitedDirs,
+    files: &mut Vec<PathBuf>,
+) -> McpResult<bool> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .map_err(|err| err.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string())?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let file_type = entry.file_type().map_err(|err| err.to_string())?;
+        let path = entry.path();
+        let relative = relative_from_root(root, &path)?;
+        // Symlinks could lead out of the walk (or into a loop); git internals are never text to fix.
+        if file_type.is_symlink()
+            || entry.file_name() == ".git"
+            || filter.is_ignored(&relative, file_type.is_dir())
+        {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !visited.enter(root, &path) {
+                continue;
+            }
+            let mark = filter.enter_dir(root, &path);
+            let truncated =
+                collect_normalize_candidates(root, &path, extensions, filter, visited, files);
+            filter.leave_dir(mark);
+            if truncated? {
+                return Ok(true);
+            }
+            continue;
+        }
+        let matches = extensions.is_empty()
+            || path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .is_some_and(|extension| extensions.contains(&extension));
+        if !matches {
+            continue;
+        }
+        if files.len() >= MAX_NORMALIZE_FILES {
+            return Ok(true);
+        }
+        files.push(path);
+    }
+    Ok(false)
+}
+
+#[tauri::command(async)]
+fn mcp_files_normalize_dir(
+    path: String,
+    target: String,
+    extensions: Option<Vec<String>>,
+    dry_run: Option<bool>,
+) -> McpResult<NormalizeDirResponse> {
+    let crlf = match target.to_ascii_lowercase().as_str() {
+        "lf" => false,
+        "crlf" => true,
+        _ => return Err("El destino debe ser lf o crlf.".into()),
+    };
+
+    let root = safe_root()?;
+    let directory = build_path(&root, Some(path.as_str()))?;
+
+    if !directory.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !directory.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let extensions: Vec<String> = extensions
+        .unwrap_or_default()
+        .iter()
+        .map(|extension| extension.trim_start_matches('.').to_lowercase())
+        .filter(|extension| !extension.is_empty())
+        .collect();
+    let dry_run = dry_run.unwrap_or(false);
+
+    let mut filter = IgnoreFilter::new(&root, &directory, false);
+    let mut visited = VisitedDirs::default();
+    visited.enter(&root, &directory);
+    let mut candidates = Vec::new();
+    let truncated = collect_normalize_candidates(
+        &root,
+        &directory,
+        &extensions,
+        &mut filter,
+        &mut visited,
+        &mut candidates,
+    )?;
+
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    for file in &candidates {
+        let size = fs::metadata(file).map_err(|err| err.to_string())?.len();
+        if size > MAX_NORMALIZE_FILE_BYTES {
+            skipped += 1;
+            continue;
+        }
+        let data = fs::read(file).map_err(|err| err.to_string())?;
+        if data.contains(&0) {
+            skipped += 1;
+            continue;
+        }
+        let converted = convert_line_endings(&data, crlf);
+        if converted == data {
+            continue;
+        }
+        if !dry_run {
+            write_file_atomic(file, &converted)?;
+        }
+        files.push(relative_from_root(&root, file)?);
+    }
+
+    Ok(NormalizeDirResponse {
+        path: relative_from_root(&root, &directory)?,
+        target: if crlf { "crlf" } else { "lf" }.to_string(),
+        dry_run,
+        scanned: candidates.len(),
+        changed: files.len(),
+        skipped,
+        files,
+        truncated,
+        cycles: visited.cycles,
+    })
+}
+
+fn validate_file_name(name: &str) -> McpResult<()> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err("El nombre resultante no es válido.".into());
+    }
+    if name.contains(['/', '\\', '\0']) {
+        return Err("El nombre resultante no puede contener separadores de ruta.".into());
+    }
+    Ok(())
+}
+
+fn probe_case_sensitivity(root: &Path) -> McpResult<bool> {
+    let stamp = format!("{}-{}", current_timestamp_ms(), std::process::id());
+    let probe = root.join(format!(".cerebro-CaseProbe-{stamp}"));
+    let folded = root.join(format!(".cerebro-caseprobe-{stamp}"));
+
+    fs::File::create_new(&probe)
+        .map_err(|err| format!("No se pudo crear el archivo de prueba en la órbita: {err}"))?;
+    let case_sensitive = fs::symlink_metadata(&folded).is_err();
+    fs::remove_file(&probe).map_err(|err| err.to_string())?;
+    Ok(case_sensitive)
+}
+
+#[tauri::command]
+fn mcp_files_case_sensitivity(
+    state: tauri::State<'_, CaseSensitivityState>,
+) -> McpResult<CaseSensitivityResponse> {
+    let root = safe_root()?;
+    let mut probed = state.probed.lock().map_err(|err| err.to_string())?;
+    let case_sensitive = match probed.get(&root) {
+        Some(known) => *known,
+        None => {
+            let detected = probe_case_sensitivity(&root)?;
+            probed.insert(root, detected);
+            detected
+        }
+    };
+    Ok(CaseSensitivityResponse { case_sensitive })
+}
+
+#[tauri::command]
+fn mcp_files_find_up(start: String, filename: String) -> McpResult<Option<String>> {
+    validate_file_name(&filename)?;
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(start.as_str()))?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    let mut directory = if target.is_dir() {
+        target
+    } else {
+        target
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or(root.clone())
+    };
+    loop {
+        let candidate = directory.join(&filename);
+        if candidate.exists() {
+            return relative_from_root(&root, &candidate).map(Some);
+        }
+        // The orbit root is the last directory searched, never its parents.
+        if directory == root || !directory.pop() || !directory.starts_with(&root) {
+            return Ok(None);
+        }
+    }
+}
+
+// Resolves symlinks in the longest existing prefix of `path` and appends the
+// rest lexically, so paths that no longer exist can still be compared.
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    let mut existing = path.to_path_buf();
+    let mut rest: Vec<std::ffi::OsString> = Vec::new();
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            let mut resolved = real;
+            for part in rest.iter().rev() {
+                resolved.push(part);
+            }
+            return resolved;
+        }
+        match existing.file_name() {
+            Some(name) => rest.push(name.to_os_string()),
+            None => return path.to_path_buf(),
+        }
+        existing.pop();
+    }
+}
+
+#[tauri::command]
+fn mcp_files_to_relative(absolute: String) -> McpResult<ToRelativeResponse> {
+    let input = Path::new(&absolute);
+    if !input.is_absolute() {
+        return Err("La ruta indicada debe ser absoluta.".into());
+    }
+
+    // Drop `.` and fold `..` first so they can't walk out after the prefix check.
+    let mut normalized = PathBuf::new();
+    for component in input.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    let root = safe_root()?;
+    let real_root = canonicalize_lenient(&root);
+    let real_target = canonicalize_lenient(&normalized);
+    let relative = [(&real_root, &real_target), (&root, &normalized)]
+        .into_iter()
+        .find_map(|(root, target)| relative_from_root(root, target).ok());
+
+    Ok(ToRelativeResponse {
+        inside_orbit: relative.is_some(),
+        path: relative,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_rename_batch(
+    path: String,
+    pattern: String,
+    replacement: String,
+    regex: Option<bool>,
+    dry_run: Option<bool>,
+) -> McpResult<RenameBatchResponse> {
+    if pattern.is_empty() {
+        return Err("El patrón no puede estar vacío.".into());
+    }
+    // Captures come from a single file name, so only the replacement can add separators.
+    if replacement.contains(['/', '\\']) {
+        return Err("El reemplazo no puede contener separadores de ruta.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let matcher = if regex.unwrap_or(false) {
+        Some(Regex::new(&pattern).map_err(|err| format!("Expresión regular inválida: {err}"))?)
+    } else {
+        None
+    };
+    let dry_run = dry_run.unwrap_or(false);
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&target).map_err(|err| err.to_string())? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        if !entry.file_type().map_err(|err| err.to_string())?.is_file() {
+            continue;
+        }
+        // Names that aren't valid UTF-8 can't be matched against a text pattern.
+        if let Ok(name) = entry.file_name().into_string() {
+            names.push(name);
+        }
+    }
+    names.sort();
+
+    let mut planned = Vec::new();
+    for name in names {
+        let renamed = match &matcher {
+            Some(matcher) => matcher
+                .replace_all(&name, replacement.as_str())
+                .into_owned(),
+            None => name.replace(&pattern, &replacement),
+        };
+        if renamed != name {
+            planned.push((name, renamed));
+        }
+    }
+    if planned.len() > MAX_RENAME_BATCH {
+        return Err(format!(
+            "Demasiados archivos a renombrar ({}); el máximo es {MAX_RENAME_BATCH}.",
+            planned.len()
+        ));
+    }
+
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    for (_, renamed) in &planned {
+        *targets.entry(renamed.clone()).or_default() += 1;
+    }
+
+    let mut results = Vec::with_capacity(planned.len());
+    let mut renamed_count = 0;
+    for (name, renamed) in planned {
+        let from = target.join(&name);
+        let to = target.join(&renamed);
+        let outcome = validate_file_name(&renamed)
+            .and_then(|_| build_path(&root, Some(relative_from_root(&root, &to)?.as_str())))
+            .and_then(|_| {
+                if targets.get(&renamed).copied().unwrap_or(0) > 1 {
+                    return Err("Varios archivos acabarían con el mismo nombre.".into());
+                }
+                // A case-only rename is fine only if `to` is `from` under another spelling.
+                if to.exists() && !(is_case_only_rename(&from, &to) && is_same_entry(&from, &to)) {
+                    return Err("Ya existe un archivo con el nombre de destino.".into());
+                }
+                Ok(())
+            });
+
+        let (status, error) = match outcome {
+            Err(err) => ("conflict", Some(err)),
+            Ok(()) if dry_run => ("planned", None),
+            Ok(()) => match rename_path(&from, &to) {
+                Ok(()) => {
+                    renamed_count += 1;
+                    ("renamed", None)
+                }
+                Err(err) => ("error", Some(err.to_string())),
+            },
+        };
+        results.push(RenameBatchItem {
+            from: name,
+            to: renamed,
+            status,
+            error,
+        });
+    }
+
+    Ok(RenameBatchResponse {
+        path: relative_from_root(&root, &target)?,
+        dry_run,
+        renamed: renamed_count,
+        results,
+    })
+}
+
+fn stream_directory_entries(
+    app: &tauri::AppHandle,
+    root: &Path,
+    target: &Path,
+    stream_id: &str,
+    batch_size: usize,
+    include_hidden: bool,
+    cancelled: &AtomicBool,
+) -> McpResult<(usize, bool)> {
+    let mut total = 0_usize;
+    let mut batch = Vec::with_capacity(batch_size);
+
+    for entry in fs::read_dir(target).map_err(|err| err.to_string())? {
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok((total, true));
+        }
+
+        let entry = entry.map_err(|err| err.to_string())?;
+        if !include_hidden && is_hidden_name(&entry.file_name()) {
+            continue;
+        }
+        match file_entry_from(root, &entry) {
+            Ok(file_entry) => batch.push(file_entry),
+            Err(err) => {
+                eprintln!("[files] skipping unreadable entry: {err}");
+                continue;
+            }
+        }
+
+        if batch.len() >= batch_size {
+            total += batch.len();
+            app.emit(
+                "files://entry",
+                ListStreamBatchEvent {
+                    stream_id: stream_id.to_string(),
+                    entries: std::mem::take(&mut batch),
+                },
+            )
+            .map_err(|err| err.to_string())?;
+        }
+    }
+
+    if !batch.is_empty() {
+        total += batch.len();
+        app.emit(
+            "files://entry",
+            ListStreamBatchEvent {
+                stream_id: stream_id.to_string(),
+                entries: batch,
+            },
+        )
+        .map_err(|err| err.to_string())?;
+    }
+
+    Ok((total, false))
+}
+
+#[cfg(unix)]
+type DirIdentity = (u64, u64);
+#[cfg(not(unix))]
+type DirIdentity = PathBuf;
+
+#[cfg(unix)]
+fn dir_identity(dir: &Path) -> Option<DirIdentity> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(dir).ok().map(|meta| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_identity(dir: &Path) -> Option<DirIdentity> {
+    dir.canonicalize().ok()
+}
+
+// Directories a recursive walk has entered, keyed by device and inode on Unix
+// (canonical path elsewhere). The walks never follow symlinks, but bind mounts
+// and junctions can still make a tree contain itself.
+#[derive(Default)]
+struct VisitedDirs {
+    seen: HashSet<DirIdentity>,
+    cycles: Vec<String>,
+}
+
+impl VisitedDirs {
+    // False, with `dir` recorded as a cycle, when the walk has been there before.
+    fn enter(&mut self, root: &Path, dir: &Path) -> bool {
+        let Some(identity) = dir_identity(dir) else {
+            return true;
+        };
+        if self.seen.insert(identity) {
+            return true;
+        }
+        self.cycles.push(
+            relative_from_root(root, dir).unwrap_or_else(|_| dir.to_string_lossy().to_string()),
+        );
+        false
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_tree_node(
+    root: &Path,
+    target: &Path,
+    depth: usize,
+    max_depth: usize,
+    filter: &mut IgnoreFilter,
+    visited: &mut VisitedDirs,
+    nodes: &mut usize,
+    truncated: &mut bool,
+) -> McpResult<TreeNode> {
+    let relative = relative_from_root(root, target)?;
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| relative.clone());
+    *nodes += 1;
+
+    if !target.is_dir() {
+        return Ok(TreeNode {
+            name,
+            path: relative,
+            entry_type: "file".to_string(),
+            children: None,
+        });
+    }
+
+    if depth >= max_depth || !visited.enter(root, target) {
+        return Ok(TreeNode {
+            name,
+            path: relative,
+            entry_type: "directory".to_string(),
+            children: None,
+        });
+    }
+
+    let mark = filter.enter_dir(root, target);
+    let children = build_tree_children(
+        root, target, depth, max_depth, filter, visited, nodes, truncated,
+    );
+    filter.leave_dir(mark);
+
+    Ok(TreeNode {
+        name,
+        path: relative,
+        entry_type: "directory".to_string(),
+        children: Some(children?),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_tree_children(
+    root: &Path,
+    target: &Path,
+    depth: usize,
+    max_depth: usize,
+    filter: &mut IgnoreFilter,
+    visited: &mut VisitedDirs,
+    nodes: &mut usize,
+    truncated: &mut bool,
+) -> McpResult<Vec<TreeNode>> {
+    let mut candidates = Vec::new();
+    for entry in fs::read_dir(target).map_err(|err| err.to_string())? {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let file_type = entry.file_type().map_err(|err| err.to_string())?;
+        // Symlinks are skipped outright so the walk can never loop.
+        if file_type.is_symlink() {
+            continue;
+        }
+        let entry_path = entry.path();
+        let entry_relative = relative_from_root(root, &entry_path)?;
+        if filter.is_ignored(&entry_relative, file_type.is_dir()) {
+            continue;
+        }
+        candidates.push((
+            entry.file_name().to_string_lossy().to_lowercase(),
+            entry_path,
+        ));
+    }
+    candidates.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut children = Vec::with_capacity(candidates.len());
+    for (_, entry_path) in candidates {
+        if *nodes >= MAX_TREE_NODES {
+            *truncated = true;
+            break;
+        }
+        children.push(build_tree_node(
+            root,
+            &entry_path,
+            depth + 1,
+            max_depth,
+            filter,
+            visited,
+            nodes,
+            truncated,
+        )?);
+    }
+
+    Ok(children)
+}
+
+fn stat_path(root: &Path, path: &str) -> McpResult<FileEntry> {
+    let target = build_path(root, Some(path))?;
+    let metadata = fs::metadata(&target).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => "La ruta indicada no existe.".to_string(),
+        _ => err.to_string(),
+    })?;
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string());
+    file_entry_from_metadata(root, &target, name, &metadata)
+}
+
+#[tauri::command]
+fn mcp_files_set_mtime(path: String, epoch_ms: u64) -> McpResult<SetMtimeResponse> {
+    if epoch_ms > current_timestamp_ms().saturating_add(MAX_MTIME_FUTURE_MS) {
+        return Err("La fecha indicada está demasiado lejos en el futuro.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(&target)
+        .map_err(|err| err.to_string())?;
+    file.set_modified(UNIX_EPOCH + Duration::from_millis(epoch_ms))
+        .map_err(|err| err.to_string())?;
+
+    // Report what the filesystem actually stored; some round to coarser units.
+    let modified_at = file
+        .metadata()
+        .ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(system_time_to_millis)
+        .unwrap_or(epoch_ms);
+
+    Ok(SetMtimeResponse {
+        path: relative_from_root(&root, &target)?,
+        modified_at,
+    })
+}
+
+fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(ch) if ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '-'))
+}
+
+fn is_secret_env_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    SECRET_KEY_MARKERS
+        .iter()
+        .any(|marker| upper.contains(marker))
+}
+
+fn unescape_double_quoted(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            value.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some(other) => value.push(other),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+// Index of the closing quote in `text`, skipping backslash-escaped ones when
+// `escapes` is set (double quotes only).
+fn closing_quote(text: &str, quote: char, escapes: bool) -> Option<usize> {
+    let mut escaped = false;
+    for (index, ch) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if escapes && ch == '\\' {
+            escaped = true;
+        } else if ch == quote {
+            return Some(index);
+        }
+    }
+    None
+}
+
+// dotenv-style parsing: `#` comments, optional `export`, single quotes are
+// literal, double quotes support escapes, and both may span several lines.
+// No variable expansion is performed.
+fn parse_env_file(contents: &str) -> (BTreeMap<String, String>, Vec<EnvLineError>) {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut values = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line_number = index + 1;
+        let line = lines[index].trim();
+        index += 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(line);
+        let Some((key, raw_value)) = line.split_once('=') else {
+            errors.push(EnvLineError {
+                line: line_number,
+                message: "Falta el signo '=' en la línea.".into(),
+            });
+            continue;
+        };
+        let key = key.trim();
+        if !is_valid_env_key(key) {
+            errors.push(EnvLineError {
+                line: line_number,
+                message: format!("Nombre de variable inválido: '{key}'."),
+            });
+            continue;
+        }
+
+        let raw_value = raw_value.trim_start();
+        let quote = raw_value
+            .chars()
+            .next()
+            .filter(|ch| *ch == '"' || *ch == '\'');
+        let Some(quote) = quote else {
+            let value = match raw_value.find(" #").or_else(|| raw_value.find("\t#")) {
+                Some(comment) => &raw_value[..comment],
+                None => raw_value,
+            };
+            values.insert(key.to_string(), value.trim_end().to_string());
+            continue;
+        };
+
+        let escapes = quote == '"';
+        let mut body = raw_value[1..].to_string();
+        let resume = index;
+        let closed = loop {
+            if let Some(end) = closing_quote(&body, quote, escapes) {
+                let trailing = body[end + 1..].trim();
+                if !trailing.is_empty() && !trailing.starts_with('#') {
+                    break Err("Hay texto después de la comilla de cierre.");
+                }
+                body.truncate(end);
+                break Ok(());
+            }
+            match lines.get(index) {
+                Some(next) => {
+                    body.push('\n');
+                    body.push_str(next);
+                    index += 1;
+                }
+                None => {
+                    // Report it on the opening line and keep parsing after it.
+                    index = resume;
+                    break Err("Falta la comilla de cierre.");
+                }
+            }
+        };
+
+        match closed {
+            Ok(()) => {
+                let value = if escapes {
+                    unescape_double_quoted(&body)
+                } else {
+                    body
+                };
+                values.insert(key.to_string(), value);
+            }
+            Err(message) => errors.push(EnvLineError {
+                line: line_number,
+                message: message.to_string(),
+            }),
+        }
+    }
+
+    (values, errors)
+}
+
+#[tauri::command]
+fn mcp_files_read_env(path: String, redact: Option<bool>) -> McpResult<EnvFileResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let contents = read_text_file(&target)?;
+
+    let (mut values, errors) = parse_env_file(&contents);
+    let mut redacted = Vec::new();
+    if redact.unwrap_or(false) {
+        for (key, value) in values.iter_mut() {
+            if is_secret_env_key(key) {
+                *value = REDACTED_VALUE.to_string();
+                redacted.push(key.clone());
+            }
+        }
+    }
+
+    Ok(EnvFileResponse {
+        path: relative_from_root(&root, &target)?,
+        values,
+        errors,
+        redacted,
+    })
+}
+
+// Patterns with a `/` match the orbit-relative path, others just the file name,
+// mirroring how ignore files treat them.
+fn path_matches_pattern(pattern: &str, relative: &str) -> bool {
+    if pattern.contains('/') {
+        glob_match(pattern.trim_start_matches('/'), relative)
+    } else {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        glob_match(pattern, name)
+    }
+}
+
+fn find_matching_file(
+    root: &Path,
+    dir: &Path,
+    pattern: &str,
+    filter: &IgnoreFilter,
+    visited: &mut VisitedDirs,
+) -> Option<String> {
+    if !visited.enter(root, dir) {
+        return None;
+    }
+    let entries = fs::read_dir(dir).ok()?;
+    let mut subdirectories = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(relative) = relative_from_root(root, &path) else {
+            continue;
+        };
+        if filter.is_ignored(&relative, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            subdirectories.push(path);
+        } else if path_matches_pattern(pattern, &relative) {
+            return Some(relative);
+        }
+    }
+    subdirectories
+        .iter()
+        .find_map(|subdirectory| find_matching_file(root, subdirectory, pattern, filter, visited))
+}
+
+#[tauri::command(async)]
+fn mcp_files_wait_for(pattern: String, timeout_ms: u64) -> McpResult<WaitForResponse> {
+    let pattern = pattern.trim().to_string();
+    if pattern.is_empty() {
+        return Err("Debes indicar un patrón.".into());
+    }
+    if timeout_ms == 0 || timeout_ms > MAX_WAIT_FOR_MS {
+        return Err(format!(
+            "El tiempo de espera debe estar entre 1 y {MAX_WAIT_FOR_MS} ms."
+        ));
+    }
+
+    let root = safe_root()?;
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(timeout_ms);
+
+    // Watch before scanning so a file created in between is still reported.
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            if event.kind.is_create() || event.kind.is_modify() {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+        }
+    })
+    .map_err(|err| err.to_string())?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|err| err.to_string())?;
+
+    let filter = IgnoreFilter::new(&root, &root, false);
+    let mut visited = VisitedDirs::default();
+    if let Some(path) = find_matching_file(&root, &root, &pattern, &filter, &mut visited) {
+        return Ok(WaitForResponse {
+            path,
+            elapsed_ms: start.elapsed().as_millis(),
+            existing: true,
+        });
+    }
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let Ok(path) = receiver.recv_timeout(remaining) else {
+            break;
+        };
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(relative) = relative_from_root(&root, &path) else {
+            continue;
+        };
+        if !filter.is_ignored(&relative, false) && path_matches_pattern(&pattern, &relative) {
+            return Ok(WaitForResponse {
+                path: relative,
+                elapsed_ms: start.elapsed().as_millis(),
+                existing: false,
+            });
+        }
+    }
+
+    Err(format!(
+        "Ningún archivo coincidió con '{pattern}' en {timeout_ms} ms."
+    ))
+}
+
+// Finds the name for `id` in a passwd/group style file (`name:x:id:...`).
+#[cfg(unix)]
+fn lookup_unix_name(file: &str, id: u32) -> Option<String> {
+    let contents = fs::read_to_string(file).ok()?;
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let value = fields.nth(1)?.parse::<u32>().ok()?;
+            (value == id).then(|| name.to_string())
+        })
+}
+
+#[cfg(unix)]
+#[tauri::command]
+fn mcp_files_owner(path: String) -> McpResult<OwnerResponse> {
+    use std::os::unix::fs::MetadataExt;
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let metadata = fs::symlink_metadata(&target).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => "La ruta indicada no existe.".to_string(),
+        _ => err.to_string(),
+    })?;
+
+    // Users from LDAP/NIS aren't in these files, so names stay best-effort.
+    let uid = metadata.uid();
+    let gid = metadata.gid();
+    // `/proc/self` is owned by the process's effective uid.
+    let current_uid = fs::metadata("/proc/self").ok().map(|meta| meta.uid());
+
+    Ok(OwnerResponse {
+        path: relative_from_root(&root, &target)?,
+        uid,
+        gid,
+        user: lookup_unix_name("/etc/passwd", uid),
+        group: lookup_unix_name("/etc/group", gid),
+        mode: format!("{:o}", metadata.mode() & 0o7777),
+        owned_by_current_user: current_uid.map(|current| current == uid),
+    })
+}
+
+#[cfg(not(unix))]
+#[tauri::command]
+fn mcp_files_owner(path: String) -> McpResult<OwnerResponse> {
+    let _ = path;
+    Err("La información de propietario solo está disponible en sistemas Unix.".into())
+}
+
+#[tauri::command]
+fn mcp_files_stat_batch(paths: Vec<String>) -> McpResult<Vec<StatBatchItem>> {
+    if paths.len() > MAX_STAT_BATCH {
+        return Err(format!(
+            "Se admiten como máximo {MAX_STAT_BATCH} rutas por consulta."
+        ));
+    }
+
+    let root = safe_root()?;
+    Ok(paths
+        .into_iter()
+        .map(|path| match stat_path(&root, &path) {
+            Ok(entry) => StatBatchItem {
+                path,
+                entry: Some(entry),
+                error: None,
+            },
+            Err(error) => StatBatchItem {
+                path,
+                entry: None,
+                error: Some(error),
+            },
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn mcp_files_tree(
+    path: Option<String>,
+    max_depth: Option<usize>,
+    respect_gitignore: Option<bool>,
+) -> McpResult<TreeResponse> {
+    collect_tree(
+        path.as_deref(),
+        max_depth,
+        respect_gitignore.unwrap_or(false),
+    )
+}
+
+fn collect_tree(
+    path: Option<&str>,
+    max_depth: Option<usize>,
+    respect_gitignore: bool,
+) -> McpResult<TreeResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, path)?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let max_depth = max_depth.unwrap_or(DEFAULT_TREE_DEPTH).min(MAX_TREE_DEPTH);
+    let mut filter = IgnoreFilter::new(&root, &target, respect_gitignore);
+    let mut visited = VisitedDirs::default();
+    let mut nodes = 0_usize;
+    let mut truncated = false;
+    let tree = build_tree_node(
+        &root,
+        &target,
+        0,
+        max_depth,
+        &mut filter,
+        &mut visited,
+        &mut nodes,
+        &mut truncated,
+    )?;
+
+    Ok(TreeResponse {
+        tree,
+        nodes,
+        truncated,
+        cycles: visited.cycles,
+    })
+}
+
+fn tree_display_name(node: &TreeNode) -> String {
+    if node.entry_type == "directory" {
+        format!("{}/", node.name)
+    } else {
+        node.name.clone()
+    }
+}
+
+fn render_tree_list(node: &TreeNode, depth: usize, output: &mut String) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str("- ");
+    output.push_str(&tree_display_name(node));
+    output.push('\n');
+    for child in node.children.iter().flatten() {
+        render_tree_list(child, depth + 1, output);
+    }
+}
+
+fn render_tree_box(node: &TreeNode, prefix: &str, output: &mut String) {
+    let children = node.children.as_deref().unwrap_or_default();
+    for (index, child) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        output.push_str(prefix);
+        output.push_str(if last { "└── " } else { "├── " });
+        output.push_str(&tree_display_name(child));
+        output.push('\n');
+        let nested = format!("{prefix}{}", if last { "    " } else { "│   " });
+        render_tree_box(child, &nested, output);
+    }
+}
+
+#[tauri::command]
+fn mcp_files_tree_markdown(
+    path: Option<String>,
+    max_depth: Option<usize>,
+    style: Option<String>,
+) -> McpResult<TreeMarkdownResponse> {
+    let boxed = match style.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("list") => false,
+        Some("box") => true,
+        Some(_) => return Err("Estilo no soportado. Usa list o box.".into()),
+    };
+    let TreeResponse {
+        tree,
+        nodes,
+        truncated,
+        ..
+    } = collect_tree(path.as_deref(), max_depth, false)?;
+
+    let mut markdown = String::new();
+    if boxed {
+        markdown.push_str("```text\n");
+        markdown.push_str(&tree_display_name(&tree));
+        markdown.push('\n');
+        render_tree_box(&tree, "", &mut markdown);
+        if truncated {
+            markdown.push_str("…\n");
+        }
+        markdown.push_str("```\n");
+    } else {
+        render_tree_list(&tree, 0, &mut markdown);
+        if truncated {
+            markdown.push_str("- …\n");
+        }
+    }
+
+    Ok(TreeMarkdownResponse {
+        markdown,
+        nodes,
+        truncated,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_list_stream(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, StreamRegistry>,
+    path: Option<String>,
+    batch_size: Option<usize>,
+    show_hidden: Option<bool>,
+) -> McpResult<ListStreamResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, path.as_deref())?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let batch_size = batch_size
+        .unwrap_or(DEFAULT_LIST_STREAM_BATCH)
+        .clamp(1, MAX_LIST_STREAM_BATCH);
+    let include_hidden = show_hidden.unwrap_or(true);
+    let relative = relative_from_root(&root, &target)?;
+    let (stream_id, cancelled) = streams.start("list")?;
+
+    let worker_id = stream_id.clone();
+    thread::spawn(move || {
+        let result = stream_directory_entries(
+            &app,
+            &root,
+            &target,
+            &worker_id,
+            batch_size,
+            include_hidden,
+            &cancelled,
+        );
+        app.state::<StreamRegistry>().finish(&worker_id);
+
+        let done = match result {
+            Ok((total, was_cancelled)) => ListStreamDoneEvent {
+                stream_id: worker_id,
+                total,
+                cancelled: was_cancelled,
+                error: None,
+            },
+            Err(err) => ListStreamDoneEvent {
+                stream_id: worker_id,
+                total: 0,
+                cancelled: false,
+                error: Some(err),
+            },
+        };
+        if let Err(err) = app.emit("files://entry-done", done) {
+            eprintln!("[files] failed to emit listing completion: {err}");
+        }
+    });
+
+    Ok(ListStreamResponse {
+        stream_id,
+        path: relative,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_list_stream_stop(
+    streams: tauri::State<'_, StreamRegistry>,
+    stream_id: String,
+) -> McpResult<bool> {
+    streams.cancel(&stream_id)
+}
+
+struct SearchWalk<'a> {
+    app: &'a tauri::AppHandle,
+    search_id: &'a str,
+    matcher: &'a Regex,
+    cancelled: &'a AtomicBool,
+    max_matches: usize,
+    files_scanned: usize,
+    matches: usize,
+}
+
+enum SearchStop {
+    Finished,
+    Truncated,
+    Cancelled,
+}
+
+impl SearchWalk<'_> {
+    fn walk(
+        &mut self,
+        root: &Path,
+        dir: &Path,
+        filter: &mut IgnoreFilter,
+        visited: &mut VisitedDirs,
+    ) -> McpResult<SearchStop> {
+        let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+            .map_err(|err| err.to_string())?
+            .filter_map(Result::ok)
+            .collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return Ok(SearchStop::Cancelled);
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let relative = relative_from_root(root, &path)?;
+            if file_type.is_symlink()
+                || entry.file_name() == ".git"
+                || filter.is_ignored(&relative, file_type.is_dir())
+            {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !visited.enter(root, &path) {
+                    continue;
+                }
+                let mark = filter.enter_dir(root, &path);
+                let stop = self.walk(root, &path, filter, visited);
+                filter.leave_dir(mark);
+                match stop? {
+                    SearchStop::Finished => continue,
+                    stop => return Ok(stop),
+                }
+            }
+
+            let too_large = entry
+                .metadata()
+                .ok()
+                .is_none_or(|meta| meta.len() > MAX_SEARCH_FILE_BYTES);
+            if too_large {
+                continue;
+            }
+            // Unreadable files are skipped rather than aborting the whole search.
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            self.files_scanned += 1;
+            if !bytes[..bytes.len().min(8192)].contains(&0) {
+                if let SearchStop::Truncated = self.search_file(&relative, &bytes)? {
+                    return Ok(SearchStop::Truncated);
+                }
+            }
+            if self.files_scanned.is_multiple_of(SEARCH_PROGRESS_EVERY) {
+                self.emit_progress()?;
+            }
+        }
+        Ok(SearchStop::Finished)
+    }
+
+    fn search_file(&mut self, relative: &str, bytes: &[u8]) -> McpResult<SearchStop> {
+        let text = String::from_utf8_lossy(bytes);
+        for (index, line) in text.lines().enumerate() {
+            let Some(found) = self.matcher.find(line) else {
+                continue;
+            };
+            if self.matches >= self.max_matches {
+                return Ok(SearchStop::Truncated);
+            }
+            self.matches += 1;
+            self.app
+                .emit(
+                    "search://match",
+                    SearchMatchEvent {
+                        search_id: self.search_id.to_string(),
+                        path: relative.to_string(),
+                        line: index + 1,
+                        column: line[..found.start()].chars().count() + 1,
+                        snippet: line.trim().chars().take(MAX_SEARCH_SNIPPET_CHARS).collect(),
+                    },
+                )
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(SearchStop::Finished)
+    }
+
+    fn emit_progress(&self) -> McpResult<()> {
+        self.app
+            .emit(
+                "search://progress",
+                SearchProgressEvent {
+                    search_id: self.search_id.to_string(),
+                    files_scanned: self.files_scanned,
+                    matches: self.matches,
+                },
+            )
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+fn mcp_files_search_stream(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, StreamRegistry>,
+    query: String,
+    path: Option<String>,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    max_matches: Option<usize>,
+    respect_gitignore: Option<bool>,
+) -> McpResult<SearchStreamResponse> {
+    if query.is_empty() {
+        return Err("La búsqueda no puede estar vacía.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, path.as_deref())?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let pattern = if regex.unwrap_or(false) {
+        query
+    } else {
+        regex::escape(&query)
+    };
+    let matcher = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive.unwrap_or(false))
+        .build()
+        .map_err(|err| format!("Expresión regular inválida: {err}"))?;
+    let max_matches = max_matches
+        .unwrap_or(DEFAULT_SEARCH_MATCHES)
+        .clamp(1, MAX_SEARCH_MATCHES);
+    let respect_gitignore = respect_gitignore.unwrap_or(true);
+    let relative = relative_from_root(&root, &target)?;
+    let (search_id, cancelled) = streams.start("search")?;
+
+    let worker_id = search_id.clone();
+    thread::spawn(move || {
+        let mut search = SearchWalk {
+            app: &app,
+            search_id: &worker_id,
+            matcher: &matcher,
+            cancelled: &cancelled,
+            max_matches,
+            files_scanned: 0,
+            matches: 0,
+        };
+        let mut filter = IgnoreFilter::new(&root, &target, respect_gitignore);
+        let mut visited = VisitedDirs::default();
+        visited.enter(&root, &target);
+        let result = search.walk(&root, &target, &mut filter, &mut visited);
+        app.state::<StreamRegistry>().finish(&worker_id);
+
+        let (truncated, was_cancelled, error) = match result {
+            Ok(SearchStop::Finished) => (false, false, None),
+            Ok(SearchStop::Truncated) => (true, false, None),
+            Ok(SearchStop::Cancelled) => (false, true, None),
+            Err(err) => (false, false, Some(err)),
+        };
+        let done = SearchDoneEvent {
+            search_id: worker_id.clone(),
+            files_scanned: search.files_scanned,
+            matches: search.matches,
+            truncated,
+            cancelled: was_cancelled,
+            error,
+        };
+        if let Err(err) = app.emit("search://done", done) {
+            eprintln!("[files] failed to emit search completion: {err}");
+        }
+    });
+
+    Ok(SearchStreamResponse {
+        search_id,
+        path: relative,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_search_stop(
+    streams: tauri::State<'_, StreamRegistry>,
+    search_id: String,
+) -> McpResult<bool> {
+    streams.cancel(&search_id)
+}
+
+struct ManifestWalk<'a> {
+    app: &'a tauri::AppHandle,
+    // The requested directory, echoed in progress events.
+    path: &'a str,
+    max_entries: usize,
+    entries: Vec<ManifestEntry>,
+    skipped: Vec<String>,
+    bytes: u64,
+}
+
+impl ManifestWalk<'_> {
+    // False once the entry limit stops the walk.
+    fn walk(
+        &mut self,
+        root: &Path,
+        dir: &Path,
+        filter: &mut IgnoreFilter,
+        visited: &mut VisitedDirs,
+    ) -> McpResult<bool> {
+        // An unreadable directory is reported like an unreadable file.
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            self.skipped.push(relative_from_root(root, dir)?);
+            return Ok(true);
+        };
+        let mut entries: Vec<fs::DirEntry> = read_dir.filter_map(Result::ok).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let relative = relative_from_root(root, &path)?;
+            if file_type.is_symlink() || filter.is_ignored(&relative, file_type.is_dir()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !visited.enter(root, &path) {
+                    continue;
+                }
+                let mark = filter.enter_dir(root, &path);
+                let result = self.walk(root, &path, filter, visited);
+                filter.leave_dir(mark);
+                if !result? {
+                    return Ok(false);
+                }
+                continue;
+            }
+
+            if self.entries.len() >= self.max_entries {
+                return Ok(false);
+            }
+            let mtime_ms = entry
+                .metadata()
+                .ok()
+                .and_then(|meta| meta.modified().ok())
+                .and_then(system_time_to_millis);
+            // Unreadable files are reported rather than aborting the whole manifest.
+            let Ok((sha256, size)) = sha256_file_hex(&path) else {
+                self.skipped.push(relative);
+                continue;
+            };
+            self.bytes += size;
+            self.entries.push(ManifestEntry {
+                path: relative,
+                size,
+                mtime_ms,
+                sha256,
+            });
+            if self.entries.len().is_multiple_of(MANIFEST_PROGRESS_EVERY) {
+                self.emit_progress();
+            }
+        }
+        Ok(true)
+    }
+
+    fn emit_progress(&self) {
+        let progress = ManifestProgressEvent {
+            path: self.path.to_string(),
+            files: self.entries.len(),
+            bytes: self.bytes,
+        };
+        if let Err(err) = self.app.emit("manifest://progress", progress) {
+            eprintln!("[files] failed to emit manifest progress: {err}");
+        }
+    }
+}
+
+// Every file under `path` with its size, mtime and SHA-256, in walk order, so
+// two locations can be compared without transferring contents. Honours
+// `.cerebroignore`; symlinks are skipped.
+#[tauri::command(async)]
+fn mcp_files_manifest_full(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    max_entries: Option<usize>,
+) -> McpResult<ManifestResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, path.as_deref())?;
+
+    if !target.exists() {
+        return Err("La ruta indicada no existe.".into());
+    }
+
+    if !target.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
+
+    let relative = relative_from_root(&root, &target)?;
+    let mut manifest = ManifestWalk {
+        app: &app,
+        path: &relative,
+        max_entries: max_entries
+            .unwrap_or(DEFAULT_MANIFEST_ENTRIES)
+            .clamp(1, MAX_MANIFEST_ENTRIES),
+        entries: Vec::new(),
+        skipped: Vec::new(),
+        bytes: 0,
+    };
+    let mut filter = IgnoreFilter::new(&root, &target, false);
+    let mut visited = VisitedDirs::default();
+    visited.enter(&root, &target);
+    let finished = manifest.walk(&root, &target, &mut filter, &mut visited)?;
+    manifest.emit_progress();
+
+    let ManifestWalk {
+        entries, skipped, ..
+    } = manifest;
+
+    Ok(ManifestResponse {
+        path: relative,
+        entries,
+        skipped,
+        truncated: !finished,
+    })
+}
+
+fn stream_file_chunks(
+    app: &tauri::AppHandle,
+    target: &Path,
+    transfer_id: &str,
+    chunk_size: usize,
+    cancelled: &AtomicBool,
+) -> McpResult<(usize, u64, bool)> {
+    let mut file = fs::File::open(target).map_err(|err| err.to_string())?;
+    let mut buffer = vec![0_u8; chunk_size];
+    let mut index = 0_usize;
+    let mut offset = 0_u64;
+
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok((index, offset, true));
+        }
+
+        // Fill the whole buffer so every chunk but the last has exactly `chunk_size` bytes.
+        let mut filled = 0;
+        while filled < chunk_size {
+            let read = file
+                .read(&mut buffer[filled..])
+                .map_err(|err| err.to_string())?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        if filled == 0 {
+            return Ok((index, offset, false));
+        }
+
+        app.emit(
+            "files://chunk",
+            ReadChunkEvent {
+                transfer_id: transfer_id.to_string(),
+                index,
+                offset,
+                data: encode_base64(&buffer[..filled]),
+            },
+        )
+        .map_err(|err| err.to_string())?;
+        index += 1;
+        offset += filled as u64;
+    }
+}
+
+#[tauri::command]
+fn mcp_files_read_chunks(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, StreamRegistry>,
+    path: String,
+    chunk_size: Option<usize>,
+) -> McpResult<ReadChunksResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let chunk_size = chunk_size
+        .unwrap_or(DEFAULT_READ_CHUNK_BYTES)
+        .clamp(MIN_READ_CHUNK_BYTES, MAX_READ_CHUNK_BYTES);
+    let size = fs::metadata(&target).map_err(|err| err.to_string())?.len();
+    let relative = relative_from_root(&root, &target)?;
+    let (transfer_id, cancelled) = streams.start("read")?;
+
+    let worker_id = transfer_id.clone();
+    thread::spawn(move || {
+        let result = stream_file_chunks(&app, &target, &worker_id, chunk_size, &cancelled);
+        app.state::<StreamRegistry>().finish(&worker_id);
+
+        let done = match result {
+            Ok((chunks, bytes, was_cancelled)) => ReadChunksDoneEvent {
+                transfer_id: worker_id,
+                chunks,
+                bytes,
+                cancelled: was_cancelled,
+                error: None,
+            },
+            Err(err) => ReadChunksDoneEvent {
+                transfer_id: worker_id,
+                chunks: 0,
+                bytes: 0,
+                cancelled: false,
+                error: Some(err),
+            },
+        };
+        if let Err(err) = app.emit("files://chunk-done", done) {
+            eprintln!("[files] failed to emit chunked read completion: {err}");
+        }
+    });
+
+    Ok(ReadChunksResponse {
+        transfer_id,
+        path: relative,
+        size,
+        chunk_size,
+    })
+}
+
+// Poll-driven counterpart of `mcp_files_read_chunks`: the caller asks for one
+// chunk at a time and keeps no server-side state between calls.
+#[tauri::command]
+fn mcp_files_read_progress(
+    app: tauri::AppHandle,
+    path: String,
+    transfer_id: String,
+    offset: u64,
+    chunk_size: Option<usize>,
+) -> McpResult<ReadProgressResponse> {
+    if transfer_id.trim().is_empty() {
+        return Err("El identificador de transferencia no puede estar vacío.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let chunk_size = chunk_size
+        .unwrap_or(DEFAULT_READ_CHUNK_BYTES)
+        .clamp(MIN_READ_CHUNK_BYTES, MAX_READ_CHUNK_BYTES);
+    let mut file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let size = file.metadata().map_err(|err| err.to_string())?.len();
+    if offset > size {
+        return Err("El desplazamiento supera el tamaño del archivo.".into());
+    }
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|err| err.to_string())?;
+    let mut buffer = Vec::with_capacity(chunk_size);
+    // A file that grows meanwhile is only read up to the size seen at the start of this call.
+    file.take((chunk_size as u64).min(size - offset))
+        .read_to_end(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let next_offset = offset + buffer.len() as u64;
+    let done = next_offset >= size;
+
+    let percent = if size == 0 {
+        100.0
+    } else {
+        (next_offset.min(size) as f64 / size as f64) * 100.0
+    };
+    let progress = ReadProgressEvent {
+        transfer_id: transfer_id.clone(),
+        offset: next_offset,
+        size,
+        percent,
+    };
+    if let Err(err) = app.emit("files://progress", progress) {
+        eprintln!("[files] failed to emit read progress: {err}");
+    }
+
+    Ok(ReadProgressResponse {
+        transfer_id,
+        data: encode_base64(&buffer),
+        offset: next_offset,
+        size,
+        done,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_read_chunks_stop(
+    streams: tauri::State<'_, StreamRegistry>,
+    transfer_id: String,
+) -> McpResult<bool> {
+    streams.cancel(&transfer_id)
+}
+
+#[tauri::command(async)]
+fn mcp_files_read(
+    path: String,
+    encoding: Option<String>,
+    timeout_ms: Option<u64>,
+    decompress: Option<String>,
+    include_hash: Option<bool>,
+) -> McpResult<ReadResponse> {
+    let root = safe_root()?;
+
+    // Resolving the path already stats it, so a stalled mount is covered by the
+    // deadline too.
+    let worker_root = root.clone();
+    let read = move || {
+        let (target, external) = resolve_readable_path(&worker_root, Some(path.as_str()))?;
+        let data = match decompress.as_deref() {
+            Some(format) => read_file_decompressed(&target, format),
+            None => read_file_bytes(&target),
+        }?;
+        Ok::<_, String>((target, external, data))
+    };
+    let (target, external, data) = match timeout_ms.filter(|timeout| *timeout > 0) {
+        Some(timeout) => {
+            run_with_deadline(Duration::from_millis(timeout), read).ok_or_else(|| {
+                format!("Tiempo de espera agotado al leer el archivo ({timeout} ms).")
+            })??
+        }
+        None => read()?,
+    };
+    let sha256 = include_hash.unwrap_or(false).then(|| sha256_hex(&data));
+    let encoding_pref = encoding.unwrap_or_else(|| "utf8".to_string());
+    let content = if encoding_pref.eq_ignore_ascii_case("base64") {
+        encode_base64(&data)
+    } else {
+        String::from_utf8(data).map_err(|_| {
+            "El archivo no está codificado como UTF-8. Usa encoding base64.".to_string()
+        })?
+    };
+
+    let relative = display_readable_path(&root, external.as_deref(), &target)?;
+
+    Ok(ReadResponse {
+        path: relative,
+        encoding: if encoding_pref.eq_ignore_ascii_case("base64") {
+            "base64".to_string()
+        } else {
+            "utf8".to_string()
+        },
+        content,
+        sha256,
+    })
+}
+
+// Splits `name.bak-<ms>` into the original file name and the backup timestamp.
+fn parse_backup_name(name: &str) -> Option<(&str, u64)> {
+    let (original, stamp) = name.rsplit_once(BACKUP_SUFFIX)?;
+    if original.is_empty() || stamp.is_empty() || !stamp.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((original, stamp.parse().ok()?))
+}
+
+fn backup_dir_for(root: &Path, target: &Path) -> McpResult<PathBuf> {
+    let relative = relative_from_root(root, target)?;
+    let mut directory = root.join(BACKUP_DIR_NAME);
+    if let Some(parent) = Path::new(&relative).parent() {
+        directory.push(parent);
+    }
+    Ok(directory)
+}
+
+fn create_backup(root: &Path, target: &Path) -> McpResult<String> {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "La ruta indicada no tiene nombre de archivo.".to_string())?;
+    let directory = backup_dir_for(root, target)?;
+    fs::create_dir_all(&directory).map_err(|err| err.to_string())?;
+
+    // Bump the stamp on a same-millisecond collision so names stay parseable.
+    let mut stamp = current_timestamp_ms();
+    let mut backup = directory.join(format!("{name}{BACKUP_SUFFIX}{stamp}"));
+    while backup.exists() {
+        stamp += 1;
+        backup = directory.join(format!("{name}{BACKUP_SUFFIX}{stamp}"));
+    }
+
+    fs::copy(target, &backup).map_err(|err| err.to_string())?;
+    relative_from_root(root, &backup)
+}
+
+#[tauri::command]
+fn mcp_files_delete(path: String, recursive: Option<bool>) -> McpResult<DeleteResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if target == root {
+        return Err("No se puede eliminar la raíz de la órbita.".into());
+    }
+
+    let metadata =
+        fs::symlink_metadata(&target).map_err(|_| "La ruta indicada no existe.".to_string())?;
+    let relative = relative_from_root(&root, &target)?;
+    // Only a link itself is removed, never what it points to.
+    ensure_parent_inside(&root, &target)?;
+
+    let kind = if metadata.file_type().is_symlink() {
+        // On Windows a link to a directory is itself removed like a directory.
+        fs::remove_file(&target)
+            .or_else(|_| fs::remove_dir(&target))
+            .map_err(|err| err.to_string())?;
+        "symlink"
+    } else if metadata.is_dir() {
+        if recursive.unwrap_or(false) {
+            fs::remove_dir_all(&target).map_err(|err| err.to_string())?;
+        } else {
+            let empty = fs::read_dir(&target)
+                .map_err(|err| err.to_string())?
+                .next()
+                .is_none();
+            if !empty {
+                return Err(
+                    "El directorio no está vacío; usa recursive para eliminarlo con su contenido."
+                        .into(),
+                );
+            }
+            fs::remove_dir(&target).map_err(|err| err.to_string())?;
+        }
+        "directory"
+    } else {
+        fs::remove_file(&target).map_err(|err| err.to_string())?;
+        "file"
+    };
+
+    Ok(DeleteResponse {
+        path: relative,
+        kind,
+    })
+}
+
+#[tauri::command(async)]
+fn mcp_files_move(from: String, to: String, overwrite: Option<bool>) -> McpResult<MoveResponse> {
+    let root = safe_root()?;
+    let source = build_path(&root, Some(from.as_str()))?;
+    let target = build_path(&root, Some(to.as_str()))?;
+
+    if source == root || target == root {
+        return Err("No se puede mover la raíz de la órbita.".into());
+    }
+    ensure_parent_inside(&root, &source)?;
+    let source_metadata =
+        fs::symlink_metadata(&source).map_err(|_| "La ruta de origen no existe.".to_string())?;
+    if source == target {
+        return Err("El origen y el destino son la misma ruta.".into());
+    }
+    if target.starts_with(&source) {
+        return Err("No se puede mover un directorio dentro de sí mismo.".into());
+    }
+
+    // A case-only rename points at the source itself on case-insensitive filesystems.
+    if let Ok(existing) = fs::symlink_metadata(&target) {
+        if !(is_case_only_rename(&source, &target) && is_same_entry(&source, &target)) {
+            if !overwrite.unwrap_or(false) {
+                return Err("Ya existe un archivo en la ruta de destino.".into());
+            }
+            if existing.is_dir() || source_metadata.is_dir() {
+                return Err("Solo se puede sobrescribir un archivo con otro archivo.".into());
+            }
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+    ensure_parent_inside(&root, &target)?;
+
+    let copied = match rename_path(&source, &target) {
+        Ok(()) => false,
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            move_by_copy(&source, &target).map_err(|err| err.to_string())?;
+            true
+        }
+        Err(err) => return Err(err.to_string()),
+    };
+
+    Ok(MoveResponse {
+        from: relative_from_root(&root, &source)?,
+        to: relative_from_root(&root, &target)?,
+        copied,
+    })
+}
+
+#[tauri::command(async)]
+fn mcp_files_copy(from: String, to: String, overwrite: Option<bool>) -> McpResult<CopyResponse> {
+    let root = safe_root()?;
+    let source = build_path(&root, Some(from.as_str()))?;
+    let target = build_path(&root, Some(to.as_str()))?;
+    ensure_parent_inside(&root, &source)?;
+
+    let source_metadata =
+        fs::metadata(&source).map_err(|_| "La ruta de origen no existe.".to_string())?;
+    if target == root {
+        return Err("No se puede sobrescribir la raíz de la órbita.".into());
+    }
+    if source == target {
+        return Err("El origen y el destino son la misma ruta.".into());
+    }
+    // Copying a directory into itself would keep finding the copy it is writing.
+    if source_metadata.is_dir() && target.starts_with(&source) {
+        return Err("No se puede copiar un directorio dentro de sí mismo.".into());
+    }
+
+    let overwrite = overwrite.unwrap_or(false);
+    if let Ok(existing) = fs::metadata(&target) {
+        if !overwrite {
+            return Err("Ya existe un archivo en la ruta de destino.".into());
+        }
+        if existing.is_dir() != source_metadata.is_dir() {
+            return Err("El destino existe y no es del mismo tipo que el origen.".into());
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+    ensure_parent_inside(&root, &target)?;
+    // A copied link lands at a different place, so a relative one can now point
+    // elsewhere; those that would leave the orbit are not recreated.
+    let real_root = root.canonicalize().map_err(|err| err.to_string())?;
+    let mut skipped_links = Vec::new();
+    let (files, bytes) = copy_tree(
+        &source,
+        &target,
+        overwrite,
+        Some(&real_root),
+        &mut skipped_links,
+    )
+    .map_err(|err| err.to_string())?;
+    let skipped = skipped_links
+        .iter()
+        .map(|link| relative_from_root(&root, link))
+        .collect::<McpResult<Vec<_>>>()?;
+
+    Ok(CopyResponse {
+        from: relative_from_root(&root, &source)?,
+        to: relative_from_root(&root, &target)?,
+        files,
+        bytes,
+        skipped,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_backups_list(path: String) -> McpResult<Vec<BackupEntry>> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "La ruta indicada no tiene nombre de archivo.".to_string())?;
+
+    // The original may already be gone; its backups are still listed.
+    let directory = backup_dir_for(&root, &target)?;
+    let mut backups = Vec::new();
+    let Ok(entries) = fs::read_dir(&directory) else {
+        return Ok(backups);
+    };
+    for entry in entries {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let entry_name = entry.file_name().to_string_lossy().to_string();
+        let Some((original, created_at_ms)) = parse_backup_name(&entry_name) else {
+            continue;
+        };
+        let metadata = entry.metadata().map_err(|err| err.to_string())?;
+        if original != name || !metadata.is_file() {
+            continue;
+        }
+        backups.push(BackupEntry {
+            path: relative_from_root(&root, &entry.path())?,
+            size: metadata.len(),
+            created_at_ms,
+        });
+    }
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at_ms));
+    Ok(backups)
+}
+
+#[tauri::command]
+fn mcp_files_restore_backup(
+    backup_path: String,
+    backup_current: Option<bool>,
+) -> McpResult<RestoreBackupResponse> {
+    let root = safe_root()?;
+    let source = build_path(&root, Some(backup_path.as_str()))?;
+    let relative = source
+        .strip_prefix(root.join(BACKUP_DIR_NAME))
+        .map_err(|_| format!("La copia debe estar dentro de {BACKUP_DIR_NAME}."))?
+        .to_path_buf();
+
+    let original_name = relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(parse_backup_name)
+        .map(|(original, _)| original.to_string())
+        .ok_or_else(|| "El nombre no corresponde a una copia de seguridad.".to_string())?;
+
+    if !source.is_file() {
+        return Err("La copia de seguridad indicada no existe.".into());
+    }
+
+    let mut target = root.clone();
+    if let Some(parent) = relative.parent() {
+        target.push(parent);
+    }
+    target.push(original_name);
+
+    if target.is_dir() {
+        return Err("La ruta original es ahora un directorio.".into());
+    }
+
+    // Restoring is itself an overwrite, so by default it stays undoable too.
+    let backup = if backup_current.unwrap_or(true) && target.is_file() {
+        Some(create_backup(&root, &target)?)
+    } else {
+        None
+    };
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+    fs::copy(&source, &target).map_err(|err| err.to_string())?;
+
+    Ok(RestoreBackupResponse {
+        path: relative_from_root(&root, &target)?,
+        backup,
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn mcp_files_write(
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    overwrite: Option<bool>,
+    mode: Option<String>,
+    backup: Option<bool>,
+    if_match_sha256: Option<String>,
+    verify: Option<bool>,
+    permissions: Option<u32>,
+) -> McpResult<WriteResponse> {
+    let create_new = match mode.as_deref() {
+        None => false,
+        Some(value) if value.eq_ignore_ascii_case("overwrite") => false,
+        Some(value) if value.eq_ignore_ascii_case("create_new") => true,
+        Some(_) => return Err("Modo de escritura no soportado.".into()),
+    };
+    // Plain rwx bits only: setuid, setgid and sticky have no business on a written file.
+    if permissions.is_some_and(|permissions| permissions > 0o777) {
+        return Err("Los permisos deben ser bits rwx entre 0 y 0o777.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+
+    let existed = !create_new && target.exists();
+    if existed && !overwrite.unwrap_or(true) {
+        return Err("El archivo ya existe y overwrite=false.".into());
+    }
+
+    // Optimistic concurrency: refuse to clobber a file that changed since it was read.
+    if let Some(expected) = if_match_sha256.as_deref() {
+        let current = fs::read(&target).ok().map(|data| sha256_hex(&data));
+        if !current.is_some_and(|current| current.eq_ignore_ascii_case(expected.trim())) {
+            return Err("El archivo cambió desde la última lectura (sha256 no coincide).".into());
+        }
+    }
+
+    let encoding_pref = encoding.unwrap_or_else(|| "utf8".to_string());
+    let payload = if encoding_pref.eq_ignore_ascii_case("base64") {
+        decode_base64(&content)?
+    } else {
+        content.into_bytes()
+    };
+
+    let backup_path = if existed && backup.unwrap_or(false) && target.is_file() {
+        Some(create_backup(&root, &target)?)
+    } else {
+        None
+    };
+
+    write_file_contents(&target, &payload, create_new, permissions)?;
+
+    let mode = match permissions {
+        Some(_) => written_file_mode(&target)?,
+        None => None,
+    };
+
+    let sha256 = if verify.unwrap_or(false) {
+        Some(verify_written_file(&target, &payload)?)
+    } else {
+        None
+    };
+
+    let relative = relative_from_root(&root, &target)?;
+
+    Ok(WriteResponse {
+        path: relative,
+        bytes: payload.len(),
+        created: !existed,
+        backup: backup_path,
+        sha256,
+        mode,
+    })
+}
+
+// The mode the file ended up with, as octal.
+#[cfg(unix)]
+fn written_file_mode(target: &Path) -> McpResult<Option<String>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(target)
+        .map_err(|err| err.to_string())?
+        .permissions()
+        .mode();
+    Ok(Some(format!("{:o}", mode & 0o7777)))
+}
+
+#[cfg(not(unix))]
+fn written_file_mode(target: &Path) -> McpResult<Option<String>> {
+    eprintln!(
+        "[files] permissions ignored for {}: Unix only",
+        target.display()
+    );
+    Ok(None)
+}
+
+// Flushes `target` and checks that what reads back hashes like `expected`.
+fn verify_written_file(target: &Path, expected: &[u8]) -> McpResult<String> {
+    // Windows only flushes through a handle with write access.
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(target)
+        .map_err(|err| err.to_string())?;
+    file.sync_all().map_err(|err| err.to_string())?;
+    drop(file);
+
+    let intended = sha256_hex(expected);
+    let actual = sha256_hex(&fs::read(target).map_err(|err| err.to_string())?);
+    if actual != intended {
+        return Err(format!(
+            "La verificación falló: el archivo en disco no coincide con lo escrito (sha256 {actual}, esperado {intended})."
+        ));
+    }
+    Ok(actual)
+}
+
+#[tauri::command]
+fn mcp_files_info() -> McpResult<FilesInfoResponse> {
+    let root = safe_root()?;
+    Ok(FilesInfoResponse {
+        root: root.to_string_lossy().replace('\\', "/"),
+        exists: true,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_word_diff(a: String, b: String) -> McpResult<WordDiffResponse> {
+    let root = safe_root()?;
+    let path_a = build_path(&root, Some(a.as_str()))?;
+    let path_b = build_path(&root, Some(b.as_str()))?;
+    let text_a = read_text_file(&path_a)?;
+    let text_b = read_text_file(&path_b)?;
+
+    let (word_ops, exact) = diff_tokens(
+        &tokenize_words(&text_a),
+        &tokenize_words(&text_b),
+        MAX_DIFF_CELLS,
+    );
+
+    let (granularity, spans, truncated) = if exact {
+        ("word", merge_diff_ops(word_ops), false)
+    } else {
+        let lines_a: Vec<&str> = text_a.split_inclusive('\n').collect();
+        let lines_b: Vec<&str> = text_b.split_inclusive('\n').collect();
+        let (line_ops, exact) = diff_tokens(&lines_a, &lines_b, MAX_DIFF_CELLS);
+        ("line", merge_diff_ops(line_ops), !exact)
+    };
+
+    Ok(WordDiffResponse {
+        a: relative_from_root(&root, &path_a)?,
+        b: relative_from_root(&root, &path_b)?,
+        granularity: granularity.to_string(),
+        spans,
+        truncated,
+    })
+}
+
+// Renders line-level diff ops as unified diff hunks. Returns the hunks and the
+// number of added and removed lines.
+fn unified_diff_hunks(ops: &[(DiffKind, &str)], context: usize) -> (String, usize, usize) {
+    let mut old_before = Vec::with_capacity(ops.len() + 1);
+    let mut new_before = Vec::with_capacity(ops.len() + 1);
+    let (mut old_line, mut new_line) = (0_usize, 0_usize);
+    for (kind, _) in ops {
+        old_before.push(old_line);
+        new_before.push(new_line);
+        match kind {
+            DiffKind::Equal => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffKind::Delete => old_line += 1,
+            DiffKind::Insert => new_line += 1,
+        }
+    }
+
+    let mut output = String::new();
+    let (mut additions, mut deletions) = (0_usize, 0_usize);
+    let mut index = 0;
+    while index < ops.len() {
+        if ops[index].0 == DiffKind::Equal {
+            index += 1;
+            continue;
+        }
+
+        // Changes separated by at most twice the context share one hunk.
+        let start = index.saturating_sub(context);
+        let mut end = index;
+        loop {
+            while end < ops.len() && ops[end].0 != DiffKind::Equal {
+                end += 1;
+            }
+            let mut next = end;
+            while next < ops.len() && ops[next].0 == DiffKind::Equal {
+                next += 1;
+            }
+            if next < ops.len() && next - end <= context * 2 {
+                end = next;
+                continue;
+            }
+            break;
+        }
+        let stop = (end + context).min(ops.len());
+
+        let hunk = &ops[start..stop];
+        let old_count = hunk
+            .iter()
+            .filter(|(kind, _)| *kind != DiffKind::Insert)
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|(kind, _)| *kind != DiffKind::Delete)
+            .count();
+        // An empty side is numbered by the line before it, as `diff -u` does.
+        let old_start = old_before[start] + usize::from(old_count > 0);
+        let new_start = new_before[start] + usize::from(new_count > 0);
+        output.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+        for (kind, line) in hunk {
+            output.push(match kind {
+                DiffKind::Equal => ' ',
+                DiffKind::Delete => {
+                    deletions += 1;
+                    '-'
+                }
+                DiffKind::Insert => {
+                    additions += 1;
+                    '+'
+                }
+            });
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        index = stop;
+    }
+    (output, additions, deletions)
+}
+
+#[tauri::command]
+fn mcp_files_diff_backup(path: String, backup_path: String) -> McpResult<BackupDiffResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let backup = build_path(&root, Some(backup_path.as_str()))?;
+
+    // The backup must sit in the file's mirrored backup folder and carry its name.
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "La ruta indicada no tiene nombre de archivo.".to_string())?;
+    let belongs = backup.parent() == Some(backup_dir_for(&root, &target)?.as_path())
+        && backup
+            .file_name()
+            .and_then(|backup_name| backup_name.to_str())
+            .and_then(parse_backup_name)
+            .is_some_and(|(original, _)| original == name);
+    if !belongs {
+        return Err("La copia indicada no pertenece a este archivo.".into());
+    }
+
+    let current = read_file_bytes(&target)?;
+    let previous = read_file_bytes(&backup)
+        .map_err(|_| "La copia de seguridad indicada no existe.".to_string())?;
+    let path = relative_from_root(&root, &target)?;
+    let backup = relative_from_root(&root, &backup)?;
+
+    let texts = match (
+        std::str::from_utf8(&previous),
+        std::str::from_utf8(&current),
+    ) {
+        (Ok(old), Ok(new)) if !old.contains('\0') && !new.contains('\0') => Some((old, new)),
+        _ => None,
+    };
+    let Some((old, new)) = texts else {
+        let diff = if previous == current {
+            String::new()
+        } else {
+            format!("Binary files a/{backup} and b/{path} differ\n")
+        };
+        return Ok(BackupDiffResponse {
+            path,
+            backup,
+            diff,
+            additions: 0,
+            deletions: 0,
+            binary: true,
+            truncated: false,
+        });
+    };
+
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let (ops, exact) = diff_tokens(&old_lines, &new_lines, MAX_DIFF_CELLS);
+    let (hunks, additions, deletions) = unified_diff_hunks(&ops, UNIFIED_DIFF_CONTEXT);
+    let diff = if hunks.is_empty() {
+        hunks
+    } else {
+        format!("--- a/{backup}\n+++ b/{path}\n{hunks}")
+    };
+
+    Ok(BackupDiffResponse {
+        path,
+        backup,
+        diff,
+        additions,
+        deletions,
+        binary: false,
+        truncated: !exact,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_detect_charset(path: String) -> McpResult<CharsetResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let sample = read_file_sample(&target, CHARSET_SAMPLE_BYTES)?;
+    let (charset, confidence, bom) = detect_charset(&sample);
+
+    Ok(CharsetResponse {
+        path: relative_from_root(&root, &target)?,
+        charset: charset.to_string(),
+        confidence,
+        bom,
+        sampled_bytes: sample.len(),
+    })
+}
+
+#[tauri::command]
+fn mcp_files_line_index(path: String) -> McpResult<LineIndexResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let mut file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let size = file.metadata().map_err(|err| err.to_string())?.len();
+    let mut offsets = Vec::new();
+    if size > 0 {
+        offsets.push(0);
+    }
+
+    let mut buffer = vec![0_u8; 64 * 1024];
+    let mut position = 0_u64;
+    let mut truncated = false;
+    'scan: loop {
+        let read = file.read(&mut buffer).map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        for (index, byte) in buffer[..read].iter().enumerate() {
+            if *byte != b'\n' {
+                continue;
+            }
+            let next = position + index as u64 + 1;
+            // A trailing newline doesn't start another line.
+            if next >= size {
+                continue;
+            }
+            if offsets.len() >= MAX_LINE_INDEX_ENTRIES {
+                truncated = true;
+                break 'scan;
+            }
+            offsets.push(next);
+        }
+        position += read as u64;
+    }
+
+    Ok(LineIndexResponse {
+        path: relative_from_root(&root, &target)?,
+        size,
+        offsets,
+        truncated,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_read_around(
+    path: String,
+    line: usize,
+    context: usize,
+) -> McpResult<ReadAroundResponse> {
+    if line == 0 {
+        return Err("Las líneas se numeran desde 1.".into());
+    }
+    if context > MAX_READ_AROUND_CONTEXT {
+        return Err(format!(
+            "El contexto no puede superar {MAX_READ_AROUND_CONTEXT} líneas."
+        ));
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let first = line.saturating_sub(context).max(1);
+    let last = line.saturating_add(context);
+    let file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    let mut lines = Vec::new();
+    let mut number = 0;
+    while number < last {
+        buffer.clear();
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+        if number < first {
+            continue;
+        }
+        if buffer.ends_with(b"\n") {
+            buffer.pop();
+            if buffer.ends_with(b"\r") {
+                buffer.pop();
+            }
+        }
+        lines.push(NumberedLine {
+            number,
+            text: String::from_utf8_lossy(&buffer).into_owned(),
+        });
+    }
+
+    if number < line {
+        return Err(format!(
+            "La línea {line} está fuera del archivo ({number} líneas)."
+        ));
+    }
+
+    Ok(ReadAroundResponse {
+        path: relative_from_root(&root, &target)?,
+        line,
+        start_line: first,
+        end_line: number,
+        lines,
+    })
+}
+
+// Splits a leading `---` ... `---` (or `...`) block off `text`. An unterminated
+// block isn't front matter, just a document that starts with a rule.
+fn split_frontmatter(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = text.split_inclusive('\n');
+    let opening = lines.next()?;
+    if opening.trim_end_matches(['\r', '\n']) != "---" {
+        return None;
+    }
+
+    let start = opening.len();
+    let mut offset = start;
+    for line in lines {
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&text[start..offset], &text[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[tauri::command]
+fn mcp_files_read_frontmatter(path: String) -> McpResult<FrontmatterResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let content = String::from_utf8(read_file_bytes(&target)?)
+        .map_err(|_| "El archivo no está codificado como UTF-8.".to_string())?;
+
+    let (frontmatter, body) = match split_frontmatter(&content) {
+        Some((yaml, body)) if yaml.trim().is_empty() => {
+            (Some(serde_json::json!({})), body.to_string())
+        }
+        Some((yaml, body)) => {
+            // The leading newline stands in for the opening `---`, so the line
+            // numbers serde_yaml reports match the file's.
+            let value: serde_json::Value = serde_yaml::from_str(&format!("\n{yaml}"))
+                .map_err(|err| format!("Front matter YAML inválido: {err}"))?;
+            (Some(value), body.to_string())
+        }
+        None => (None, content),
+    };
+
+    Ok(FrontmatterResponse {
+        path: relative_from_root(&root, &target)?,
+        frontmatter,
+        body,
+    })
+}
+
+// Each pattern captures the declaration keyword and the declared name. Only
+// unindented lines are matched, so nested methods and closures stay out.
+fn outline_patterns(path: &Path) -> Option<&'static [&'static str]> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let patterns: &'static [&'static str] = match extension.as_str() {
+        "rs" => &[
+            r#"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*(fn|struct|enum|union|trait|type|mod|const|static)\s+([A-Za-z_][A-Za-z0-9_]*)"#,
+            r"^(?:unsafe\s+)?(impl)(?:<[^{]*?>)?\s+([^{]+?)\s*(?:\{|where\b|$)",
+            r"^(macro_rules!)\s*([A-Za-z_][A-Za-z0-9_]*)",
+        ],
+        "py" | "pyi" => &[r"^(class|def|async\s+def)\s+([A-Za-z_][A-Za-z0-9_]*)"],
+        "ts" | "tsx" | "mts" | "cts" | "js" | "jsx" | "mjs" | "cjs" => &[
+            r"^(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|type|enum)\s+([A-Za-z_$][A-Za-z0-9_$]*)",
+            r"^(?:export\s+)?(const|let|var)\s+([A-Za-z_$][A-Za-z0-9_$]*)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*(?::[^=]+)?=>|[A-Za-z_$][A-Za-z0-9_$]*\s*=>)",
+        ],
+        "go" => &[
+            r"^(func)\s+(?:\([^)]*\)\s*)?([A-Za-z_][A-Za-z0-9_]*)",
+            r"^(type)\s+([A-Za-z_][A-Za-z0-9_]*)",
+        ],
+        "rb" => &[r"^(class|module|def)\s+([A-Za-z_][A-Za-z0-9_:.?!]*)"],
+        _ => return None,
+    };
+    Some(patterns)
+}
+
+fn markdown_outline(text: &str) -> Vec<OutlineItem> {
+    // Front matter is YAML, where `# ...` is a comment rather than a heading.
+    let skip = split_frontmatter(text)
+        .map(|(_, body)| text[..text.len() - body.len()].matches('\n').count())
+        .unwrap_or(0);
+
+    let mut items = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (index, line) in text.lines().enumerate().skip(skip) {
+        let trimmed = line.trim_start();
+        // Headings may be indented by at most three spaces; more makes a code block.
+        if line.len() - trimmed.len() > 3 {
+            continue;
+        }
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            fence = Some("```");
+            continue;
+        }
+        if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|ch| *ch == '#').count();
+        if !(1..=6).contains(&level) {
+            continue;
+        }
+        let rest = &trimmed[level..];
+        if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+            continue;
+        }
+        // A closing run of `#` is decoration, not part of the title.
+        let title = rest.trim();
+        let title = match title.trim_end_matches('#') {
+            stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => {
+                stripped.trim_end()
+            }
+            _ => title,
+        };
+        items.push(OutlineItem {
+            level,
+            title: title.to_string(),
+            line: index + 1,
+        });
+    }
+    items
+}
+
+fn source_outline(text: &str, patterns: &[&str]) -> McpResult<Vec<OutlineItem>> {
+    let matchers = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|err| err.to_string()))
+        .collect::<McpResult<Vec<_>>>()?;
+
+    let mut items = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let Some(captures) = matchers.iter().find_map(|matcher| matcher.captures(line)) else {
+            continue;
+        };
+        let keyword = captures[1].split_whitespace().collect::<Vec<_>>().join(" ");
+        items.push(OutlineItem {
+            level: 1,
+            title: format!("{keyword} {}", captures[2].trim()),
+            line: index + 1,
+        });
+    }
+    Ok(items)
+}
+
+#[tauri::command]
+fn mcp_files_outline(path: String) -> McpResult<Vec<OutlineItem>> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    let bytes = read_file_bytes(&target)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    let extension = target
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    if matches!(
+        extension.as_deref(),
+        Some("md" | "markdown" | "mdx" | "mdown" | "mkd")
+    ) {
+        return Ok(markdown_outline(&text));
+    }
+
+    match outline_patterns(&target) {
+        Some(patterns) => source_outline(&text, patterns),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn is_blank_line(bytes: &[u8]) -> bool {
+    bytes.iter().all(|byte| byte.is_ascii_whitespace())
+}
+
+// Reads backwards from the end in blocks until `count` complete non-blank lines
+// are buffered (or `max_bytes` have been read), so a large log is never read
+// whole. Returns those lines with their byte offsets, oldest first.
+fn read_tail_lines(
+    file: &mut fs::File,
+    count: usize,
+    max_bytes: u64,
+) -> io::Result<(Vec<(u64, String)>, bool)> {
+    let length = file.metadata()?.len();
+    let mut start = length;
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut found = 0;
+
+    while start > 0 && found < count && length - start < max_bytes {
+        let step = TAIL_BLOCK_BYTES.min(start);
+        start -= step;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0_u8; step as usize];
+        file.read_exact(&mut chunk)?;
+
+        // The buffer's first piece may have been cut mid-line; together with
+        // the new chunk it now completes, as does every later piece of the chunk.
+        let head = buffer
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .unwrap_or(buffer.len());
+        let region = [chunk.as_slice(), &buffer[..head]].concat();
+        found += region
+            .split(|byte| *byte == b'\n')
+            .skip(1)
+            .filter(|piece| !is_blank_line(piece))
+            .count();
+
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+
+    let mut offset = start;
+    let mut lines = Vec::new();
+    let mut pieces = buffer.split(|byte| *byte == b'\n');
+    if start > 0 {
+        // Cut mid-way by where the read began.
+        if let Some(partial) = pieces.next() {
+            offset += partial.len() as u64 + 1;
+        }
+    }
+    for piece in pieces {
+        if !is_blank_line(piece) {
+            let text = String::from_utf8_lossy(piece);
+            lines.push((offset, text.trim_end_matches('\r').to_string()));
+        }
+        offset += piece.len() as u64 + 1;
+    }
+
+    let truncated = start > 0 && lines.len() < count;
+    if lines.len() > count {
+        lines.drain(..lines.len() - count);
+    }
+    Ok((lines, truncated))
+}
+
+#[tauri::command]
+fn mcp_files_merge_json(paths: Vec<String>) -> McpResult<serde_json::Value> {
+    if paths.is_empty() {
+        return Err("Indica al menos un archivo JSON.".into());
+    }
+    if paths.len() > MAX_MERGE_JSON_FILES {
+        return Err(format!(
+            "Demasiados archivos ({}); el máximo es {MAX_MERGE_JSON_FILES}.",
+            paths.len()
+        ));
+    }
+
+    let root = safe_root()?;
+    let mut merged: Option<serde_json::Value> = None;
+    for path in &paths {
+        let target = build_path(&root, Some(path.as_str()))?;
+        let text = read_text_file(&target).map_err(|err| format!("{path}: {err}"))?;
+        let value: serde_json::Value =
+            serde_json::from_str(text.strip_prefix('\u{feff}').unwrap_or(&text))
+                .map_err(|err| format!("{path}: JSON inválido: {err}"))?;
+        match merged.as_mut() {
+            Some(merged) => deep_merge_json(merged, value),
+            None => merged = Some(value),
+        }
+    }
+
+    Ok(merged.unwrap_or(serde_json::Value::Null))
+}
+
+#[tauri::command]
+fn mcp_files_tail_json(path: String, lines: Option<usize>) -> McpResult<TailJsonResponse> {
+    let count = lines
+        .unwrap_or(DEFAULT_TAIL_JSON_LINES)
+        .clamp(1, MAX_TAIL_JSON_LINES);
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let mut file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let (tail, truncated) =
+        read_tail_lines(&mut file, count, MAX_TAIL_JSON_BYTES).map_err(|err| err.to_string())?;
+
+    let mut entries = Vec::with_capacity(tail.len());
+    let mut malformed = Vec::new();
+    for (offset, text) in tail {
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(value) => entries.push(value),
+            Err(error) => malformed.push(MalformedJsonLine {
+                offset,
+                text,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    Ok(TailJsonResponse {
+        path: relative_from_root(&root, &target)?,
+        entries,
+        malformed,
+        truncated,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_quick_signature(path: String) -> McpResult<QuickSignatureResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let mut file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let metadata = file.metadata().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0_u8; 64 * 1024];
+    let mut crc = 0_u32;
+    let mut size = 0_u64;
+    loop {
+        let read = file.read(&mut buffer).map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        crc = crc32_update(crc, &buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok(QuickSignatureResponse {
+        path: relative_from_root(&root, &target)?,
+        size,
+        mtime_ms: metadata.modified().ok().and_then(system_time_to_millis),
+        crc32: crc,
+    })
+}
+
+// Vowel groups, with a trailing silent `e` dropped; English-centric but close
+// enough for a readability estimate.
+fn estimate_syllables(word: &str) -> usize {
+    let lower = word.to_lowercase();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for ch in lower.chars() {
+        let vowel = "aeiouyáéíóúàèìòùäëïöü".contains(ch);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if count > 1 && lower.ends_with('e') && !lower.ends_with("le") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+fn compute_text_stats(text: &str) -> (usize, usize, usize, usize) {
+    let mut words = 0;
+    let mut sentences = 0;
+    let mut syllables = 0;
+    let mut open_sentence = false;
+
+    for token in text.split_whitespace() {
+        let word: String = token.chars().filter(|ch| ch.is_alphanumeric()).collect();
+        if !word.is_empty() {
+            words += 1;
+            syllables += estimate_syllables(&word);
+            open_sentence = true;
+        }
+        // "end." / "end?)" / "end!\"" close a sentence; abbreviations are not special-cased.
+        let trimmed = token.trim_end_matches(['"', '\'', ')', ']', '»', '”', '’']);
+        if open_sentence && trimmed.ends_with(['.', '!', '?', '…']) {
+            sentences += 1;
+            open_sentence = false;
+        }
+    }
+    if open_sentence {
+        sentences += 1;
+    }
+
+    (words, sentences, syllables, count_paragraphs(text))
+}
+
+fn count_paragraphs(text: &str) -> usize {
+    let mut paragraphs = 0;
+    let mut in_paragraph = false;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            in_paragraph = false;
+        } else if !in_paragraph {
+            paragraphs += 1;
+            in_paragraph = true;
+        }
+    }
+    paragraphs
+}
+
+#[tauri::command]
+fn mcp_files_long_lines(path: String, max_length: usize) -> McpResult<LongLinesResponse> {
+    if max_length == 0 {
+        return Err("La longitud máxima debe ser positiva.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    // Read line by line so large files never sit in memory whole.
+    let file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    let mut lines = Vec::new();
+    let mut total = 0;
+    let mut number = 0;
+    loop {
+        buffer.clear();
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+
+        let content = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        // Cheap bound first: a line can't have more characters than bytes.
+        if content.len() <= max_length {
+            continue;
+        }
+        let length = String::from_utf8_lossy(content).chars().count();
+        if length <= max_length {
+            continue;
+        }
+        total += 1;
+        if lines.len() < MAX_LONG_LINES_REPORTED {
+            lines.push(LongLine {
+                line: number,
+                length,
+            });
+        }
+    }
+
+    Ok(LongLinesResponse {
+        path: relative_from_root(&root, &target)?,
+        max_length,
+        truncated: total > lines.len(),
+        lines,
+        total,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_text_stats(path: String, readability: Option<bool>) -> McpResult<TextStatsResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let size = fs::metadata(&target).map_err(|err| err.to_string())?.len();
+    if size > MAX_TEXT_STATS_BYTES {
+        return Err(format!(
+            "El archivo supera el máximo de {} MiB para calcular estadísticas.",
+            MAX_TEXT_STATS_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let text = read_text_file(&target)?;
+    let (words, sentences, syllables, paragraphs) = compute_text_stats(&text);
+    let avg_words_per_sentence = if sentences == 0 {
+        0.0
+    } else {
+        words as f64 / sentences as f64
+    };
+    // Flesch reading ease is calibrated for English prose.
+    let flesch_reading_ease = (readability.unwrap_or(false) && words > 0).then(|| {
+        206.835 - 1.015 * avg_words_per_sentence - 84.6 * (syllables as f64 / words as f64)
+    });
+
+    Ok(TextStatsResponse {
+        path: relative_from_root(&root, &target)?,
+        characters: text.chars().count(),
+        words,
+        sentences,
+        paragraphs,
+        avg_words_per_sentence,
+        flesch_reading_ease,
+    })
+}
+
+fn megabytes_per_second(size_mb: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        size_mb as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+// Writes `size_mb` MiB of random data (so compressing filesystems can't cheat)
+// and reads it back, returning both durations.
+fn run_benchmark(target: &Path, size_mb: u64) -> McpResult<(Duration, Duration)> {
+    let mut block = vec![0_u8; 1024 * 1024];
+    getrandom::getrandom(&mut block).map_err(|err| err.to_string())?;
+
+    let started = Instant::now();
+    let mut file = fs::File::create_new(target).map_err(|err| err.to_string())?;
+    for _ in 0..size_mb {
+        file.write_all(&block).map_err(|err| err.to_string())?;
+    }
+    // Without the sync we'd only be timing the page cache.
+    file.sync_all().map_err(|err| err.to_string())?;
+    drop(file);
+    let write_elapsed = started.elapsed();
+
+    // The read may still be served from the page cache on a local disk; on a
+    // network mount it normally goes back to the server.
+    let started = Instant::now();
+    let mut file = fs::File::open(target).map_err(|err| err.to_string())?;
+    let mut read_total: u64 = 0;
+    loop {
+        let read = file.read(&mut block).map_err(|err| err.to_string())?;
+        if read == 0 {
+            break;
+        }
+        read_total += read as u64;
+    }
+    let read_elapsed = started.elapsed();
+
+    if read_total != size_mb * 1024 * 1024 {
+        return Err("El archivo de prueba no se leyó completo.".into());
+    }
+    Ok((write_elapsed, read_elapsed))
+}
+
+#[tauri::command(async)]
+fn mcp_files_benchmark(size_mb: u64) -> McpResult<BenchmarkResponse> {
+    if size_mb == 0 || size_mb > MAX_BENCHMARK_SIZE_MB {
+        return Err(format!(
+            "El tamaño debe estar entre 1 y {MAX_BENCHMARK_SIZE_MB} MB."
+        ));
+    }
+
+    let root = safe_root()?;
+    let target = root.join(format!(".cerebro-benchmark-{}", current_timestamp_ms()));
+    let result = run_benchmark(&target, size_mb);
+    let _ = fs::remove_file(&target);
+    let (write_elapsed, read_elapsed) = result?;
+
+    Ok(BenchmarkResponse {
+        size_mb,
+        write_ms: write_elapsed.as_millis(),
+        read_ms: read_elapsed.as_millis(),
+        write_mb_per_sec: megabytes_per_second(size_mb, write_elapsed),
+        read_mb_per_sec: megabytes_per_second(size_mb, read_elapsed),
+    })
+}
+
+// Adds `path` (recursively, for directories) under its orbit-relative name.
+// Symlinks are stored as links, never followed.
+fn append_tar_tree<W: Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    path: &Path,
+    skip: &Path,
+    entries: &mut usize,
+) -> McpResult<()> {
+    // The archive may be written inside one of the directories being archived.
+    if path == skip {
+        return Ok(());
+    }
+    let metadata = fs::symlink_metadata(path).map_err(|err| err.to_string())?;
+    let relative = relative_from_root(root, path)?;
+
+    if metadata.is_dir() {
+        if relative != "." {
+            builder
+                .append_dir(&relative, path)
+                .map_err(|err| err.to_string())?;
+            *entries += 1;
+        }
+        let mut children: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|err| err.to_string())?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()
+            .map_err(|err| err.to_string())?;
+        children.sort();
+        for child in children {
+            append_tar_tree(builder, root, &child, skip, entries)?;
+        }
+        return Ok(());
+    }
+
+    // Sockets, fifos and devices have no place in a source archive.
+    if !metadata.is_file() && !metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    builder
+        .append_path_with_name(path, &relative)
+        .map_err(|err| err.to_string())?;
+    *entries += 1;
+    Ok(())
+}
+
+fn write_targz(root: &Path, sources: &[PathBuf], destination: &Path) -> McpResult<usize> {
+    let file = fs::File::create_new(destination).map_err(|err| err.to_string())?;
+    let encoder =
+        flate2::write::GzEncoder::new(io::BufWriter::new(file), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+
+    let mut entries = 0;
+    for source in sources {
+        append_tar_tree(&mut builder, root, source, destination, &mut entries)?;
+    }
+    let encoder = builder.into_inner().map_err(|err| err.to_string())?;
+    let mut writer = encoder.finish().map_err(|err| err.to_string())?;
+    writer.flush().map_err(|err| err.to_string())?;
+    Ok(entries)
+}
+
+#[tauri::command(async)]
+fn mcp_files_targz(sources: Vec<String>, destination: String) -> McpResult<TarGzResponse> {
+    if sources.is_empty() {
+        return Err("Indica al menos un origen.".into());
+    }
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(destination.as_str()))?;
+    let sources = sources
+        .iter()
+        .map(|source| {
+            let path = build_path(&root, Some(source.as_str()))?;
+            if !path.exists() {
+                return Err(format!("El origen '{source}' no existe."));
+            }
+            Ok(path)
+        })
+        .collect::<McpResult<Vec<PathBuf>>>()?;
+
+    if target.exists() {
+        return Err("El archivo de destino ya existe.".into());
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+
+    // Never leave a truncated archive behind.
+    let entries = write_targz(&root, &sources, &target).inspect_err(|_| {
+        let _ = fs::remove_file(&target);
+    })?;
+    let bytes = fs::metadata(&target).map_err(|err| err.to_string())?.len();
+
+    Ok(TarGzResponse {
+        path: relative_from_root(&root, &target)?,
+        entries,
+        bytes,
+    })
+}
+
+// Member names made only of normal components; anything absolute or with `..`
+// is refused outright rather than "fixed".
+fn safe_archive_member(path: &Path) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!safe.as_os_str().is_empty()).then_some(safe)
+}
+
+// Whether a relative symlink created in `parent` pointing to `link` stays inside
+// the extraction directory, following links extracted earlier.
+fn archive_link_stays_inside(parent: &Path, link: &Path, real_target: &Path) -> bool {
+    !link.is_absolute() && link_resolves_inside(parent, link, real_target)
+}
+
+#[tauri::command(async)]
+fn mcp_files_untargz(
+    source: String,
+    destination: String,
+    overwrite: Option<bool>,
+) -> McpResult<UntarGzResponse> {
+    let root = safe_root()?;
+    let archive_path = build_path(&root, Some(source.as_str()))?;
+    let target_dir = build_path(&root, Some(destination.as_str()))?;
+    let overwrite = overwrite.unwrap_or(false);
+
+    if !archive_path.is_file() {
+        return Err("El archivo indicado no existe.".into());
+    }
+    fs::create_dir_all(&target_dir).map_err(|err| err.to_string())?;
+    let (extracted, skipped) = extract_targz(
+        &root,
+        &archive_path,
+        &target_dir,
+        overwrite,
+        max_path_depth(),
+    )?;
+
+    Ok(UntarGzResponse {
+        destination: relative_from_root(&root, &target_dir)?,
+        extracted,
+        skipped,
+    })
+}
+
+fn extract_targz(
+    root: &Path,
+    archive_path: &Path,
+    target_dir: &Path,
+    overwrite: bool,
+    max_depth: usize,
+) -> McpResult<(Vec<String>, Vec<SkippedArchiveEntry>)> {
+    let real_target = target_dir.canonicalize().map_err(|err| err.to_string())?;
+    let file = fs::File::open(archive_path).map_err(|err| err.to_string())?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(BufReader::new(file)));
+    let mut extracted = Vec::new();
+    let mut skipped = Vec::new();
+    // Applied last, so a read-only directory doesn't block its own contents.
+    let mut directory_modes: Vec<(PathBuf, u32)> = Vec::new();
+
+    for entry in archive.entries().map_err(|err| err.to_string())? {
+        let mut entry = entry.map_err(|err| err.to_string())?;
+        let raw = entry.path().map_err(|err| err.to_string())?.into_owned();
+        let display = raw
+            .to_string_lossy()
+            .replace('\\', "/")
+            .trim_end_matches('/')
+            .to_string();
+        let mut skip = |reason| {
+            skipped.push(SkippedArchiveEntry {
+                path: display.clone(),
+                reason,
+            })
+        };
+
+        let Some(member) = safe_archive_member(&raw) else {
+            skip("unsafe-path");
+            continue;
+        };
+        let output = target_dir.join(&member);
+        if check_path_depth(root, &output, max_depth).is_err() {
+            skip("too-deep");
+            continue;
+        }
+        if let Some(parent) = output.parent() {
+            // Catches links already on disk (or extracted earlier) that lead out,
+            // before anything gets created through them.
+            if !canonicalize_lenient(parent).starts_with(&real_target) {
+                skip("unsafe-path");
+                continue;
+            }
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        // Setuid, setgid and sticky bits are never restored.
+        let mode = entry.header().mode().ok().map(|mode| mode & 0o777);
+        let kind = entry.header().entry_type();
+
+        if kind.is_dir() {
+            if output.exists() && !output.is_dir() {
+                skip("conflict");
+                continue;
+            }
+            fs::create_dir_all(&output).map_err(|err| err.to_string())?;
+            if let Some(mode) = mode {
+                directory_modes.push((output, mode));
+            }
+            extracted.push(display);
+            continue;
+        }
+
+        if !kind.is_file() && !kind.is_symlink() {
+            // Hard links, devices and fifos aren't worth the risk.
+            skip("unsupported-type");
+            continue;
+        }
+        if let Ok(existing) = fs::symlink_metadata(&output) {
+            if !overwrite {
+                skip("exists");
+                continue;
+            }
+            if existing.is_dir() {
+                skip("conflict");
+                continue;
+            }
+            // Remove rather than write through: the old file may be a symlink.
+            fs::remove_file(&output).map_err(|err| err.to_string())?;
+        }
+
+        if kind.is_symlink() {
+            let link = entry
+                .link_name()
+                .map_err(|err| err.to_string())?
+                .map(|link| link.into_owned());
+            let parent = output.parent().unwrap_or(target_dir);
+            let Some(link) =
+                link.filter(|link| archive_link_stays_inside(parent, link, &real_target))
+            else {
+                skip("unsafe-link");
+                continue;
+            };
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, &output).map_err(|err| err.to_string())?;
+            #[cfg(not(unix))]
+            {
+                let _ = link;
+                skip("unsupported-type");
+                continue;
+            }
+        } else {
+            let mut file = fs::File::create_new(&output).map_err(|err| err.to_string())?;
+            io::copy(&mut entry, &mut file).map_err(|err| err.to_string())?;
+            #[cfg(unix)]
+            if let Some(mode) = mode {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(fs::Permissions::from_mode(mode))
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        extracted.push(display);
+    }
+
+    #[cfg(unix)]
+    for (directory, mode) in directory_modes.into_iter().rev() {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&directory, fs::Permissions::from_mode(mode));
+    }
+    #[cfg(not(unix))]
+    drop(directory_modes);
+
+    Ok((extracted, skipped))
+}
+
+#[tauri::command]
+fn mcp_files_detect_indent(path: String) -> McpResult<IndentResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let sample = read_file_sample(&target, INDENT_SAMPLE_BYTES)?;
+    let mut text = String::from_utf8_lossy(&sample).to_string();
+    if sample.len() == INDENT_SAMPLE_BYTES {
+        // Drop the trailing partial line so it can't skew the counts.
+        if let Some(end) = text.rfind('\n') {
+            text.truncate(end);
+        }
+    }
+
+    let (style, size, confidence, sampled_lines) = detect_indent(&text);
+
+    Ok(IndentResponse {
+        path: relative_from_root(&root, &target)?,
+        style: style.to_string(),
+        size,
+        confidence,
+        sampled_lines,
+    })
+}
+
+fn scope_kind(stack: &ScopeStack) -> Option<&'static str> {
+    stack.as_slice().iter().rev().find_map(|scope| {
+        let name = scope.build_string();
+        SCOPE_KINDS
+            .iter()
+            .find(|(prefix, _)| {
+                name == *prefix
+                    || name
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(|(_, kind)| *kind)
+    })
+}
+
+fn push_highlight_token(
+    tokens: &mut Vec<HighlightToken>,
+    start: usize,
+    end: usize,
+    kind: Option<&'static str>,
+) {
+    let Some(kind) = kind else {
+        return;
+    };
+    if start == end {
+        return;
+    }
+    if let Some(last) = tokens.last_mut() {
+        if last.kind == kind && last.end == start {
+            last.end = end;
+            return;
+        }
+    }
+    tokens.push(HighlightToken { start, end, kind });
+}
+
+// Offsets are UTF-16 code units so they index straight into a JS string.
+// Plain text between tokens is left implicit.
+fn highlight_text(
+    text: &str,
+    syntaxes: &SyntaxSet,
+    syntax: &SyntaxReference,
+) -> McpResult<(Vec<HighlightToken>, bool)> {
+    let mut tokens = Vec::new();
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut position = 0_usize;
+
+    for line in LinesWithEndings::from(text) {
+        let ops = state
+            .parse_line(line, syntaxes)
+            .map_err(|err| err.to_string())?;
+        let mut kind = scope_kind(&stack);
+        let mut cursor = 0_usize;
+
+        for (index, op) in ops {
+            let start = position;
+            position += line[cursor..index].encode_utf16().count();
+            push_highlight_token(&mut tokens, start, position, kind);
+            cursor = index;
+            stack.apply(&op).map_err(|err| format!("{err:?}"))?;
+            kind = scope_kind(&stack);
+        }
+
+        let start = position;
+        position += line[cursor..].encode_utf16().count();
+        push_highlight_token(&mut tokens, start, position, kind);
+
+        if tokens.len() >= MAX_HIGHLIGHT_TOKENS {
+            tokens.truncate(MAX_HIGHLIGHT_TOKENS);
+            return Ok((tokens, true));
+        }
+    }
+
+    Ok((tokens, false))
+}
+
+#[tauri::command]
+fn mcp_files_highlight(
+    highlight: tauri::State<'_, HighlightState>,
+    path: String,
+) -> McpResult<HighlightResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if target.is_file() {
+        let size = fs::metadata(&target).map_err(|err| err.to_string())?.len();
+        if size > MAX_HIGHLIGHT_BYTES {
+            return Err(format!(
+                "El archivo supera el máximo de {MAX_HIGHLIGHT_BYTES} bytes para resaltar."
+            ));
+        }
+    }
+    let text = read_text_file(&target)?;
+
+    let syntaxes = highlight
+        .syntaxes
+        .get_or_init(SyntaxSet::load_defaults_newlines);
+    let by_extension = target
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| syntaxes.find_syntax_by_extension(ext));
+    let syntax = by_extension
+        .or_else(|| syntaxes.find_syntax_by_first_line(&text))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let (tokens, truncated) = highlight_text(&text, syntaxes, syntax)?;
+
+    Ok(HighlightResponse {
+        path: relative_from_root(&root, &target)?,
+        language: syntax.name.clone(),
+        tokens,
+        truncated,
+    })
+}
+
+#[tauri::command]
+fn mcp_files_read_typed(
+    path: String,
+    dtype: String,
+    offset: Option<u64>,
+    count: Option<usize>,
+    endianness: Option<String>,
+) -> McpResult<TypedReadResponse> {
+    let dtype = dtype.to_lowercase();
+    let size = dtype_size(&dtype).ok_or_else(|| {
+        "Tipo de dato no soportado. Usa u8, i8, u16, i16, u32, i32, u64, i64, f32 o f64."
+            .to_string()
+    })?;
+
+    let little_endian = match endianness.as_deref().map(|value| value.to_lowercase()) {
+        None => true,
+        Some(value) if value == "little" || value == "le" => true,
+        Some(value) if value == "big" || value == "be" => false,
+        Some(_) => return Err("Endianness inválido. Usa little o big.".into()),
+    };
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !target.exists() {
+        return Err("El archivo indicado no existe.".into());
+    }
+
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    let offset = offset.unwrap_or(0);
+    if !offset.is_multiple_of(size as u64) {
+        return Err(format!("El offset debe estar alineado a {} bytes.", size));
+    }
+
+    let mut file = fs::File::open(&target).map_err(|err| err.to_string())?;
+    let file_len = file.metadata().map_err(|err| err.to_string())?.len();
+    if offset > file_len {
+        return Err("El offset excede el tamaño del archivo.".into());
+    }
+
+    let available = ((file_len - offset) / size as u64) as usize;
+    let requested = count.unwrap_or(available);
+    let limit = requested.min(MAX_TYPED_VALUES);
+    let take = limit.min(available);
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|err| err.to_string())?;
+    let mut buffer = vec![0_u8; take * size];
+    file.read_exact(&mut buffer)
+        .map_err(|err| err.to_string())?;
+
+    let values = buffer
+        .chunks_exact(size)
+        .map(|chunk| decode_typed_value(&dtype, chunk, little_endian))
+        .collect();
+
+    Ok(TypedReadResponse {
+        path: relative_from_root(&root, &target)?,
+        dtype,
+        endianness: if little_endian { "little" } else { "big" }.to_string(),
+        offset,
+        count: take,
+        values,
+        truncated: requested > limit,
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn mcp_git_exec(
+    last_exec: tauri::State<'_, LastExecStore>,
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    _timeout_ms: Option<u64>,
+    merge_output: Option<bool>,
+    clean_env: Option<bool>,
+) -> McpResult<ExecResponse> {
+    if command != "git" && command != "git.exe" {
+        return Err("Solo se permite ejecutar el comando git desde este servidor.".into());
+    }
+
+    let root = safe_root()?;
+    let final_args = args.unwrap_or_default();
+    if final_args.is_empty() {
+        return Err("Debes especificar un subcomando de git.".into());
+    }
+
+    let subcommand = final_args[0].to_lowercase();
+    if BLOCKED_GIT_SUBCOMMANDS
+        .iter()
+        .any(|blocked| blocked.eq_ignore_ascii_case(subcommand.as_str()))
+    {
+        return Err("Operaciones remotas de git están deshabilitadas en modo offline.".into());
+    }
+
+    let working_dir = if let Some(ref dir) = cwd {
+        build_path(&root, Some(dir.as_str()))?
+    } else {
+        root.clone()
+    };
+
+    if !working_dir.exists() {
+        return Err("El directorio indicado para git no existe.".into());
+    }
+
+    if load_config().restrict_path_args {
+        validate_path_args(&root, &working_dir, &final_args)?;
+    }
+
+    let mut cmd = Command::new(command);
+    cmd.args(&final_args);
+    cmd.current_dir(&working_dir);
+    apply_command_env(&mut cmd, env, clean_env.unwrap_or(false));
+
+    let response = spawn_command(
+        cmd,
+        "git".to_string(),
+        final_args,
+        Some(working_dir),
+        ExecOptions {
+            merge_output: merge_output.unwrap_or(false),
+            ..ExecOptions::default()
+        },
+    )?;
+    last_exec.record(&response);
+    Ok(response)
+}
+
+#[tauri::command]
+fn mcp_git_info() -> McpResult<GitInfoResponse> {
+    let root = safe_root()?;
+    let mut cmd = Command::new("git");
+    cmd.arg("--version");
+    let version = cmd
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|out| out.trim().to_string());
+
+    Ok(GitInfoResponse {
+        version,
+        root: root.to_string_lossy().replace('\\', "/"),
+    })
+}
+
+// Accepts branch/tag names, hashes and suffixes like `HEAD~2` or `main^`, but
+// nothing git could read as an option or a range.
+fn is_safe_git_ref(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_GIT_REF_LENGTH
+        && !value.starts_with('-')
+        && !value.contains("..")
+        && value.chars().all(|ch| {
+            ch.is_ascii_alphanumeric() || matches!(ch, '/' | '.' | '_' | '-' | '~' | '^' | '@')
+        })
+}
+
+// `section.name` or `section.subsection.name`; section and name are what git
+// itself accepts, and nothing may start with `-`.
+fn is_valid_git_config_key(key: &str) -> bool {
+    let is_identifier = |part: &str| {
+        part.starts_with(|ch: char| ch.is_ascii_alphabetic())
+            && part
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+    };
+    let Some((section, rest)) = key.split_once('.') else {
+        return false;
+    };
+    let (subsection, name) = match rest.rsplit_once('.') {
+        Some((subsection, name)) => (Some(subsection), name),
+        None => (None, rest),
+    };
+    key.len() <= MAX_GIT_REF_LENGTH
+        && is_identifier(section)
+        && is_identifier(name)
+        && subsection.is_none_or(|subsection| {
+            !subsection.is_empty()
+                && subsection
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-' | '/'))
+        })
+}
+
+// Like `git_toplevel`, but refuses repositories whose root lies outside the orbit.
+fn orbit_git_repo(cwd: Option<&str>) -> McpResult<PathBuf> {
+    let root = safe_root()?;
+    let working_dir = build_path(&root, cwd)?;
+
+    if !working_dir.exists() {
+        return Err("El directorio indicado para git no existe.".into());
+    }
+
+    let repo_dir = git_toplevel(&working_dir)?;
+    relative_from_root(&root, &repo_dir)
+        .map_err(|_| "El repositorio está fuera de la órbita segura.".to_string())?;
+    Ok(repo_dir)
+}
+
+fn run_git(directory: &Path, args: &[&str]) -> McpResult<Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .map_err(|err| err.to_string())
+}
+
+fn verify_git_commit(repo_dir: &Path, value: &str) -> McpResult<()> {
+    let spec = format!("{value}^{{commit}}");
+    let output = run_git(repo_dir, &["rev-parse", "--verify", "--quiet", &spec])?;
+    if !output.status.success() {
+        return Err(format!(
+            "La referencia '{value}' no existe en el repositorio."
+        ));
+    }
+    Ok(())
+}
+
+fn parse_numstat(output: &str) -> Vec<DiffStat> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let added = parts.next()?;
+            let deleted = parts.next()?;
+            let path = parts.next()?;
+            let binary = added == "-" && deleted == "-";
+            Some(DiffStat {
+                path: path.to_string(),
+                added: added.parse().ok(),
+                deleted: deleted.parse().ok(),
+                binary,
+            })
+        })
+        .collect()
+}
+
+// Each `--line-porcelain` entry opens with `<hash> <orig> <final> [<count>]`,
+// repeats the commit headers and ends with the content line, prefixed by a tab.
+fn parse_blame_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            lines.extend(current.take());
+            continue;
+        }
+        match current.as_mut() {
+            None => {
+                let mut parts = line.split(' ');
+                let (Some(hash), Some(_), Some(final_line)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                let Ok(line_number) = final_line.parse() else {
+                    continue;
+                };
+                current = Some(BlameLine {
+                    line_number,
+                    commit_hash: hash.to_string(),
+                    author: String::new(),
+                    timestamp: 0,
+                    summary: String::new(),
+                    uncommitted: hash.bytes().all(|byte| byte == b'0'),
+                });
+            }
+            Some(entry) => {
+                if let Some(author) = line.strip_prefix("author ") {
+                    entry.author = author.to_string();
+                } else if let Some(time) = line.strip_prefix("author-time ") {
+                    entry.timestamp = time.trim().parse().unwrap_or(0);
+                } else if let Some(summary) = line.strip_prefix("summary ") {
+                    entry.summary = summary.to_string();
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[tauri::command(async)]
+fn mcp_git_blame(
+    cwd: Option<String>,
+    path: String,
+    timeout_ms: Option<u64>,
+) -> McpResult<Vec<BlameLine>> {
+    let root = safe_root()?;
+    let repo_dir = orbit_git_repo(cwd.as_deref())?;
+    let target = build_path(&root, Some(path.as_str()))?;
+    if !target.is_file() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+    let real_target = target.canonicalize().map_err(|err| err.to_string())?;
+    let real_repo = repo_dir.canonicalize().map_err(|err| err.to_string())?;
+    let Ok(in_repo) = real_target.strip_prefix(&real_repo) else {
+        return Err("El archivo no pertenece al repositorio indicado.".into());
+    };
+
+    let timeout = timeout_ms
+        .filter(|timeout| *timeout > 0)
+        .unwrap_or(DEFAULT_GIT_BLAME_TIMEOUT_MS);
+    let mut cmd = Command::new("git");
+    cmd.arg("blame")
+        .arg("--line-porcelain")
+        .arg("--")
+        .arg(in_repo)
+        .current_dir(&real_repo);
+    let output = run_with_timeout(cmd, Some(Duration::from_millis(timeout)), None, false)?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(parse_blame_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+#[tauri::command(async)]
+fn mcp_git_fsck(cwd: Option<String>, timeout_ms: Option<u64>) -> McpResult<GitFsckResponse> {
+    let repo_dir = orbit_git_repo(cwd.as_deref())?;
+    let timeout = timeout_ms
+        .unwrap_or(DEFAULT_GIT_FSCK_TIMEOUT_MS)
+        .clamp(1, MAX_GIT_FSCK_TIMEOUT_MS);
+
+    // Dangling objects are routine leftovers from rebases and resets, not corruption.
+    let mut cmd = Command::new("git");
+    cmd.args(["fsck", "--no-progress", "--no-dangling"]);
+    cmd.current_dir(&repo_dir);
+    let output = run_with_timeout(cmd, Some(Duration::from_millis(timeout)), None, true)?;
+
+    let issues: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Ok(GitFsckResponse {
+        ok: output.status.success() && issues.is_empty(),
+        issues,
+    })
+}
+
+#[tauri::command]
+fn mcp_git_config_get(cwd: Option<String>, key: String) -> McpResult<Option<String>> {
+    if !is_valid_git_config_key(&key) {
+        return Err(format!("La clave de configuración '{key}' no es válida."));
+    }
+
+    let repo_dir = orbit_git_repo(cwd.as_deref())?;
+    let output = run_git(&repo_dir, &["config", "--get", "--", &key])?;
+    // Exit code 1 means the key isn't set anywhere.
+    match output.status.code() {
+        Some(0) => Ok(Some(
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end_matches(['\n', '\r'])
+                .to_string(),
+        )),
+        Some(1) => Ok(None),
+        _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+// Writes to the repository's own config only (`--local`).
+#[tauri::command]
+fn mcp_git_config_set(cwd: Option<String>, key: String, value: String) -> McpResult<()> {
+    if !GIT_CONFIG_SETTABLE_KEYS
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&key))
+    {
+        return Err(format!(
+            "La clave '{key}' no se puede modificar. Permitidas: {}.",
+            GIT_CONFIG_SETTABLE_KEYS.join(", ")
+        ));
+    }
+    if value.contains(['\n', '\r', '\0']) {
+        return Err("El valor no puede contener saltos de línea.".into());
+    }
+
+    let repo_dir = orbit_git_repo(cwd.as_deref())?;
+    let output = run_git(&repo_dir, &["config", "--local", "--", &key, &value])?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn mcp_git_diff_refs(
+    cwd: Option<String>,
+    from: String,
+    to: String,
+) -> McpResult<GitDiffRefsResponse> {
+    for value in [&from, &to] {
+        if !is_safe_git_ref(value) {
+            return Err(format!("La referencia '{value}' no es válida."));
+        }
+    }
+
+    let repo_dir = orbit_git_repo(cwd.as_deref())?;
+    for value in [&from, &to] {
+        verify_git_commit(&repo_dir, value)?;
+    }
+
+    let range = format!("{from}..{to}");
+    let numstat = run_git(&repo_dir, &["diff", "--numstat", "--no-renames", &range])?;
+    if !numstat.status.success() {
+        return Err(String::from_utf8_lossy(&numstat.stderr).trim().to_string());
+    }
+    let stats = parse_numstat(&String::from_utf8_lossy(&numstat.stdout));
+
+    let unified = run_git(&repo_dir, &["diff", "--no-color", "--no-renames", &range])?;
+    if !unified.status.success() {
+        return Err(String::from_utf8_lossy(&unified.stderr).trim().to_string());
+    }
+    let truncated = unified.stdout.len() > MAX_GIT_DIFF_BYTES;
+    let bytes = &unified.stdout[..unified.stdout.len().min(MAX_GIT_DIFF_BYTES)];
+    let diff = String::from_utf8_lossy(bytes).to_string();
+
+    Ok(GitDiffRefsResponse {
+        from,
+        to,
+        stats,
+        diff,
+        truncated,
+    })
+}
+
+#[tauri::command]
+fn mcp_git_show_file(
+    cwd: Option<String>,
+    rev: String,
+    path: String,
+    encoding: Option<String>,
+) -> McpResult<GitShowFileResponse> {
+    if !is_safe_git_ref(&rev) {
+        return Err(format!("La referencia '{rev}' no es válida."));
+    }
+
+    let root = safe_root()?;
+    let working_dir = build_path(&root, cwd.as_deref())?;
+    let target = build_path(&root, Some(path.as_str()))?;
+
+    if !working_dir.exists() {
+        return Err("El directorio indicado para git no existe.".into());
+    }
+
+    let repo_dir = git_toplevel(&working_dir)?;
+    let repo_relative = relative_from_root(&root, &repo_dir)
+        .map_err(|_| "El repositorio está fuera de la órbita segura.".to_string())?;
+    let repo_in_orbit = build_path(&root, Some(repo_relative.as_str()))?;
+    let path_in_repo = target
+        .strip_prefix(&repo_in_orbit)
+        .map_err(|_| "La ruta indicada no pertenece al repositorio.".to_string())?
+        .to_string_lossy()
+        .replace('\\', "/");
+    if path_in_repo.is_empty() {
+        return Err("La ruta indicada no es un archivo.".into());
+    }
+
+    verify_git_commit(&repo_dir, &rev)?;
+    let object = format!("{rev}:{path_in_repo}");
+    let kind = run_git(&repo_dir, &["cat-file", "-t", &object])?;
+    if !kind.status.success() {
+        return Err(format!("El archivo no existía en la revisión '{rev}'."));
+    }
+    if String::from_utf8_lossy(&kind.stdout).trim() != "blob" {
+        return Err("La ruta indicada no es un archivo en esa revisión.".into());
+    }
+
+    let output = run_git(&repo_dir, &["show", &object])?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    // Binary blobs fall back to base64 unless the caller asked for it anyway.
+    let wants_base64 = encoding
+        .as_deref()
+        .is_some_and(|value| value.eq_ignore_ascii_case("base64"));
+    let (encoding, content) = match String::from_utf8(output.stdout) {
+        Ok(text) if !wants_base64 => ("utf8", text),
+        Ok(text) => ("base64", encode_base64(text.as_bytes())),
+        Err(err) => ("base64", encode_base64(err.as_bytes())),
+    };
+
+    Ok(GitShowFileResponse {
+        rev,
+        path: relative_from_root(&root, &target)?,
+        encoding: encoding.to_string(),
+        content,
+    })
+}
+
+fn git_toplevel(directory: &Path) -> McpResult<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(directory)
+        .output()
+        .map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err("El directorio indicado no es un repositorio git.".into());
+    }
+
+    let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(PathBuf::from(toplevel))
+}
+
+fn parse_branch_header(header: &str, snapshot: &mut GitStatusSnapshot) {
+    let (names, tracking) = match header.split_once(" [") {
+        Some((names, tracking)) => (names, Some(tracking.trim_end_matches(']'))),
+        None => (header, None),
+    };
+
+    let names = names
+        .strip_prefix("No commits yet on ")
+        .or_else(|| names.strip_prefix("Initial commit on "))
+        .unwrap_or(names);
+    match names.split_once("...") {
+        Some((branch, upstream)) => {
+            snapshot.branch = Some(branch.to_string());
+            snapshot.upstream = Some(upstream.to_string());
+        }
+        None if names.starts_with("HEAD (no branch)") => {}
+        None => snapshot.branch = Some(names.to_string()),
+    }
+
+    for part in tracking.unwrap_or_default().split(", ") {
+        if let Some(count) = part.strip_prefix("ahead ") {
+            snapshot.ahead = count.parse().unwrap_or(0);
+        } else if let Some(count) = part.strip_prefix("behind ") {
+            snapshot.behind = count.parse().unwrap_or(0);
+        }
+    }
+}
+
+fn read_git_status(directory: &Path, limit: Option<Duration>) -> McpResult<GitStatusSnapshot> {
+    let mut cmd = Command::new("git");
+    cmd.args(["status", "--porcelain=v1", "--branch", "-z"])
+        .current_dir(directory);
+    let output = run_with_timeout(cmd, limit, None, false)?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut snapshot = GitStatusSnapshot {
+        branch: None,
+        upstream: None,
+        ahead: 0,
+        behind: 0,
+        clean: true,
+        entries: Vec::new(),
+    };
+
+    let mut records = stdout.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("## ") {
+            parse_branch_header(header, &mut snapshot);
+            continue;
+        }
+        if record.len() < 4 {
+            continue;
+        }
+
+        let index = record[..1].to_string();
+        let worktree = record[1..2].to_string();
+        // With -z, renames and copies carry the original path as the next record.
+        let original_path = if index == "R" || index == "C" {
+            records.next().map(|value| value.to_string())
+        } else {
+            None
+        };
+
+        snapshot.entries.push(GitStatusEntry {
+            path: record[3..].to_string(),
+            original_path,
+            index,
+            worktree,
+        });
+    }
+
+    snapshot.clean = snapshot.entries.is_empty();
+    Ok(snapshot)
+}
+
+fn debounce_git_status(
+    app: tauri::AppHandle,
+    repo_dir: PathBuf,
+    key: String,
+    events: mpsc::Receiver<()>,
+) {
+    let quiet = Duration::from_millis(GIT_WATCH_DEBOUNCE_MS);
+
+    while events.recv().is_ok() {
+        loop {
+            match events.recv_timeout(quiet) {
+                Ok(()) => continue,
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        match read_git_status(&repo_dir, None) {
+            Ok(status) => {
+                let event = GitStatusEvent {
+                    cwd: key.clone(),
+                    status,
+                };
+                if let Err(err) = app.emit("git://status", event) {
+                    eprintln!("[git] failed to emit status: {err}");
+                }
+            }
+            Err(err) => eprintln!("[git] failed to read status for {}: {}", key, err),
+        }
+    }
+}
+
+#[tauri::command]
+fn mcp_git_watch(
+    app: tauri::AppHandle,
+    watches: tauri::State<'_, GitWatchState>,
+    cwd: Option<String>,
+) -> McpResult<GitStatusEvent> {
+    let root = safe_root()?;
+    let working_dir = build_path(&root, cwd.as_deref())?;
+
+    if !working_dir.exists() {
+        return Err("El directorio indicado para git no existe.".into());
+    }
+
+    let repo_dir = git_toplevel(&working_dir)?;
+    let key = relative_from_root(&root, &repo_dir)
+        .map_err(|_| "El repositorio está fuera de la órbita segura.".to_string())?;
+    let status = read_git_status(&repo_dir, None)?;
+
+    let mut watchers = watches.watchers.lock().map_err(|err| err.to_string())?;
+    if !watchers.contains_key(&key) {
+        let git_dir = repo_dir.join(".git");
+        let head = git_dir.join("HEAD");
+        let index = git_dir.join("index");
+        let (sender, receiver) = mpsc::channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+                if event.kind.is_access() {
+                    return;
+                }
+                // Inside .git only HEAD and index matter; the rest is object churn.
+                let relevant = event
+                    .paths
+                    .iter()
+                    .any(|path| !path.starts_with(&git_dir) || *path == head || *path == index);
+                if relevant {
+                    let _ = sender.send(());
+                }
+            })
+            .map_err(|err| err.to_string())?;
+        watcher
+            .watch(&repo_dir, RecursiveMode::Recursive)
+            .map_err(|err| err.to_string())?;
+
+        let worker_dir = repo_dir.clone();
+        let worker_key = key.clone();
+        thread::spawn(move || debounce_git_status(app, worker_dir, worker_key, receiver));
+        watchers.insert(key.clone(), watcher);
+    }
+
+    Ok(GitStatusEvent { cwd: key, status })
+}
+
+#[tauri::command]
+fn mcp_git_unwatch(
+    watches: tauri::State<'_, GitWatchState>,
+    cwd: Option<String>,
+) -> McpResult<bool> {
+    let root = safe_root()?;
+    let working_dir = build_path(&root, cwd.as_deref())?;
+    let key = match git_toplevel(&working_dir) {
+        Ok(repo_dir) => relative_from_root(&root, &repo_dir)?,
+        Err(_) => relative_from_root(&root, &working_dir)?,
+    };
+
+    // Dropping the watcher closes its channel, which ends the debounce thread.
+    let removed = watches
+        .watchers
+        .lock()
+        .map_err(|err| err.to_string())?
+        .remove(&key);
+    Ok(removed.is_some())
+}
+
+fn is_shell_command_allowed(command: &str) -> bool {
+    ALLOWED_SHELL_COMMANDS
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(command))
+}
+
+fn has_disallowed_tokens(values: &[String]) -> bool {
+    values
+        .iter()
+        .any(|value| value.contains('&') || value.contains('|') || value.contains(';'))
+}
+
+// Expands a configured alias into its command and prepended args. Only one level
+// is expanded, and the result still goes through the allowlist in
+// `prepare_shell_exec`, so an alias can never reach a command that isn't allowed.
+fn resolve_command_alias(
+    config: &CerebroConfig,
+    command: &str,
+    args: Vec<String>,
+) -> (String, Vec<String>) {
+    let expansion = config
+        .command_aliases
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(command))
+        .map(|(_, expansion)| expansion);
+    match expansion.and_then(|expansion| expansion.split_first()) {
+        Some((target, prefix)) => {
+            let mut resolved = prefix.to_vec();
+            resolved.extend(args);
+            (target.clone(), resolved)
+        }
+        None => (command.to_string(), args),
+    }
+}
+
+// Shared policy checks for anything that runs an allowlisted command. Returns
+// the orbit root, the resolved working directory and the loaded config.
+fn prepare_shell_exec(
+    command: &str,
+    args: &[String],
+    cwd: Option<&str>,
+) -> McpResult<(PathBuf, PathBuf, CerebroConfig)> {
+    if !is_shell_command_allowed(command) {
+        return Err("Comando no permitido por la política de seguridad.".into());
+    }
+
+    if has_disallowed_tokens(args) {
+        return Err("El comando contiene operadores no permitidos.".into());
+    }
+
+    let root = safe_root()?;
+    let working_dir = build_path(&root, cwd)?;
+
+    if !working_dir.exists() {
+        return Err("El directorio indicado no existe.".into());
+    }
+
+    let config = load_config();
+    if config.restrict_path_args {
+        validate_path_args(&root, &working_dir, args)?;
+    }
+
+    Ok((root, working_dir, config))
+}
+
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+fn mcp_shell_exec(
+    app: tauri::AppHandle,
+    jobs: tauri::State<'_, JobRegistry>,
+    last_exec: tauri::State<'_, LastExecStore>,
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    timeout_ms: Option<u64>,
+    run_id: Option<String>,
+    merge_output: Option<bool>,
+    clean_env: Option<bool>,
+    detach: Option<bool>,
+    restart: Option<bool>,
+    max_restarts: Option<u32>,
+    expect_exit: Option<i32>,
+) -> Result<ShellExecResponse, ShellExecError> {
+    let detach = detach.unwrap_or(false);
+    let restart = restart.unwrap_or(false);
+    if restart && !detach {
+        return Err("El reinicio automático solo está disponible con detach.".into());
+    }
+    if expect_exit.is_some() && detach {
+        return Err("expect_exit no está disponible con detach.".into());
+    }
+    let (command, final_args) =
+        resolve_command_alias(&load_config(), &command, args.unwrap_or_default());
+    let (root, working_dir, config) = prepare_shell_exec(&command, &final_args, cwd.as_deref())?;
+
+    // Detached jobs may need to respawn, so the command is built on demand.
+    let build_command = {
+        let command = command.clone();
+        let final_args = final_args.clone();
+        let working_dir = working_dir.clone();
+        let clean_env = clean_env.unwrap_or(false);
+        move || {
+            let mut cmd = Command::new(&command);
+            cmd.args(&final_args);
+            cmd.current_dir(&working_dir);
+            apply_command_env(&mut cmd, env.clone(), clean_env);
+            cmd
+        }
+    };
+
+    if detach {
+        let run_id = match run_id {
+            Some(run_id) => run_id,
+            None => format!("detached-{}", uuid_v4()?),
+        };
+        let max_restarts = restart.then(|| {
+            max_restarts
+                .unwrap_or(DEFAULT_DETACHED_RESTARTS)
+                .min(MAX_DETACHED_RESTARTS)
+        });
+        let pid = spawn_detached(
+            app,
+            jobs.inner(),
+            Box::new(build_command),
+            run_id.clone(),
+            command.clone(),
+            max_restarts,
+        )?;
+        return Ok(ShellExecResponse::Detached(DetachedExecResponse {
+            run_id,
+            command,
+            args: final_args,
+            cwd: relative_from_root(&root, &working_dir).ok(),
+            pid,
+            detached: true,
+        }));
+    }
+
+    let timeout = resolve_shell_timeout_ms(&config, &command, timeout_ms);
+    let response = spawn_command(
+        build_command(),
+        command,
+        final_args,
+        Some(working_dir),
+        ExecOptions {
+            timeout: Some(Duration::from_millis(timeout)),
+            job: run_id.map(|run_id| JobTracker {
+                registry: jobs.inner(),
+                run_id,
+            }),
+            merge_output: merge_output.unwrap_or(false),
+        },
+    )?;
+    last_exec.record(&response);
+    if let Some(expected) = expect_exit.filter(|expected| *expected != response.exit_code) {
+        return Err(unexpected_exit_error(response, expected));
+    }
+    Ok(ShellExecResponse::Completed(response))
+}
+
+fn unexpected_exit_error(response: ExecResponse, expected: i32) -> ShellExecError {
+    ShellExecError::UnexpectedExit(UnexpectedExitError {
+        kind: "unexpectedExit",
+        message: format!(
+            "El comando '{}' terminó con código {} (se esperaba {expected}).",
+            response.command, response.exit_code
+        ),
+        command: response.command,
+        expected,
+        actual: response.exit_code,
+        stdout: response.stdout,
+        stderr: response.stderr,
+    })
+}
+
+// Runs the steps one after another. Every step is checked against the policy
+// before the first one starts, so a rejected step never leaves the workflow half done.
+#[tauri::command(async)]
+fn mcp_shell_pipeline(
+    last_exec: tauri::State<'_, LastExecStore>,
+    steps: Vec<ExecStep>,
+    stop_on_error: Option<bool>,
+) -> McpResult<Vec<PipelineStepResponse>> {
+    if steps.is_empty() {
+        return Err("El pipeline no contiene pasos.".into());
+    }
+    let stop_on_error = stop_on_error.unwrap_or(true);
+    let config = load_config();
+
+    let mut prepared = Vec::with_capacity(steps.len());
+    for (index, step) in steps.into_iter().enumerate() {
+        let (command, args) = resolve_command_alias(&config, &step.command, step.args.clone());
+        let (_, working_dir, config) = prepare_shell_exec(&command, &args, step.cwd.as_deref())
+            .map_err(|err| format!("Paso {}: {err}", index + 1))?;
+        let timeout = resolve_shell_timeout_ms(&config, &command, step.timeout_ms);
+        prepared.push((step, command, args, working_dir, timeout));
+    }
+
+    let mut results = Vec::with_capacity(prepared.len());
+    let mut failed = false;
+    for (index, (step, command, args, working_dir, timeout)) in prepared.into_iter().enumerate() {
+        if failed && stop_on_error {
+            results.push(PipelineStepResponse {
+                index,
+                status: "skipped",
+                result: None,
+                error: None,
+            });
+            continue;
+        }
+
+        let mut cmd = Command::new(&command);
+        cmd.args(&args);
+        apply_command_env(&mut cmd, step.env, step.clean_env);
+        let outcome = spawn_command(
+            cmd,
+            command,
+            args,
+            Some(working_dir),
+            ExecOptions {
+                timeout: Some(Duration::from_millis(timeout)),
+                job: None,
+                merge_output: step.merge_output,
+            },
+        );
+        let step_response = match outcome {
+            Ok(response) => {
+                last_exec.record(&response);
+                let succeeded = response.exit_code == 0;
+                failed |= !succeeded;
+                PipelineStepResponse {
+                    index,
+                    status: if succeeded { "completed" } else { "failed" },
+                    result: Some(response),
+                    error: None,
+                }
+            }
+            Err(err) => {
+                failed = true;
+                PipelineStepResponse {
+                    index,
+                    status: "failed",
+                    result: None,
+                    error: Some(err),
+                }
+            }
+        };
+        results.push(step_response);
+    }
+
+    Ok(results)
+}
+
+// Reports a running job, including how often a detached job has been restarted.
+#[tauri::command]
+fn mcp_shell_stats(jobs: tauri::State<'_, JobRegistry>, run_id: String) -> McpResult<ActiveJob> {
+    jobs.stats(&run_id)
+}
+
+#[tauri::command]
+fn mcp_shell_kill(jobs: tauri::State<'_, JobRegistry>, run_id: String) -> McpResult<bool> {
+    jobs.request_kill(&run_id)
+}
+
+#[derive(Clone, Copy)]
+enum DiagnosticFormat {
+    Cargo,
+    Eslint,
+    Tsc,
+    Generic,
+}
+
+impl DiagnosticFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticFormat::Cargo => "cargo",
+            DiagnosticFormat::Eslint => "eslint",
+            DiagnosticFormat::Tsc => "tsc",
+            DiagnosticFormat::Generic => "generic",
+        }
+    }
+}
+
+fn first_positional(args: &[String]) -> Option<&str> {
+    args.iter()
+        .map(|arg| arg.as_str())
+        .find(|arg| !arg.starts_with('-') && !arg.starts_with('+'))
+}
+
+// Picks a parser for the command and adds the flags that make its output
+// machine-readable, unless the caller already chose a format.
+fn prepare_diagnostic_args(command: &str, args: &[String]) -> (DiagnosticFormat, Vec<String>) {
+    let mut final_args = args.to_vec();
+    let has_flag = |flags: &[&str]| {
+        args.iter().any(|arg| {
+            flags
+                .iter()
+                .any(|flag| arg == flag || arg.starts_with(&format!("{flag}=")))
+        })
+    };
+
+    if command.eq_ignore_ascii_case("cargo") {
+        let json_capable = matches!(
+            first_positional(args),
+            Some(
+                "build"
+                    | "b"
+                    | "check"
+                    | "c"
+                    | "clippy"
+                    | "test"
+                    | "t"
+                    | "bench"
+                    | "run"
+                    | "r"
+                    | "rustc"
+                    | "doc"
+            )
+        );
+        if json_capable && !has_flag(&["--message-format"]) {
+            // Anything after `--` belongs to the test binary or rustc, not cargo.
+            let insert_at = final_args
+                .iter()
+                .position(|arg| arg == "--")
+                .unwrap_or(final_args.len());
+            final_args.insert(insert_at, "--message-format=json".to_string());
+        }
+        return (DiagnosticFormat::Cargo, final_args);
+    }
+
+    if command.eq_ignore_ascii_case("npx") {
+        match first_positional(args) {
+            Some("eslint") => {
+                if !has_flag(&["--format", "-f"]) {
+                    final_args.extend(["--format".to_string(), "json".to_string()]);
+                }
+                return (DiagnosticFormat::Eslint, final_args);
+            }
+            Some("tsc") => {
+                if !has_flag(&["--pretty"]) {
+                    final_args.extend(["--pretty".to_string(), "false".to_string()]);
+                }
+                return (DiagnosticFormat::Tsc, final_args);
+            }
+            _ => {}
+        }
+    }
+
+    (DiagnosticFormat::Generic, final_args)
+}
+
+fn diagnostic_path(root: &Path, base: &Path, file: &str) -> String {
+    let candidate = Path::new(file);
+    let mut resolved = if candidate.is_absolute() {
+        PathBuf::new()
+    } else {
+        base.to_path_buf()
+    };
+    for component in candidate.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other.as_os_str()),
+        }
+    }
+    relative_from_root(root, &resolved).unwrap_or_else(|_| file.replace('\\', "/"))
+}
+
+fn parse_cargo_diagnostics(output: &str, root: &Path, base: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &value["message"];
+        let Some(severity) = message["level"].as_str() else {
+            continue;
+        };
+        let primary = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true));
+        // Span-less messages are summaries like "aborting due to 2 previous errors".
+        let Some(span) = primary else {
+            continue;
+        };
+        let diagnostic = Diagnostic {
+            file: diagnostic_path(root, base, span["file_name"].as_str().unwrap_or_default()),
+            line: span["line_start"].as_u64().unwrap_or(0),
+            column: span["column_start"].as_u64().unwrap_or(0),
+            severity: severity.to_string(),
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            code: message["code"]["code"]
+                .as_str()
+                .map(|code| code.to_string()),
+        };
+        // Cargo repeats diagnostics when a file is compiled for several targets.
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+fn parse_eslint_diagnostics(output: &str, root: &Path, base: &Path) -> Vec<Diagnostic> {
+    let Ok(serde_json::Value::Array(results)) = serde_json::from_str(output.trim()) else {
+        return Vec::new();
+    };
+    results
+        .iter()
+        .flat_map(|result| {
+            let file = diagnostic_path(root, base, result["filePath"].as_str().unwrap_or_default());
+            result["messages"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |message| Diagnostic {
+                    file: file.clone(),
+                    line: message["line"].as_u64().unwrap_or(0),
+                    column: message["column"].as_u64().unwrap_or(0),
+                    severity: match message["severity"].as_u64() {
+                        Some(2) => "error".to_string(),
+                        _ => "warning".to_string(),
+                    },
+                    message: message["message"].as_str().unwrap_or_default().to_string(),
+                    code: message["ruleId"].as_str().map(|rule| rule.to_string()),
+                })
+        })
+        .collect()
+}
+
+// `src/app.ts(12,5): error TS2322: Type 'x' is not assignable ...`
+fn parse_tsc_line(line: &str, root: &Path, base: &Path) -> Option<Diagnostic> {
+    let (location, rest) = line.split_once("): ")?;
+    let (file, position) = location.rsplit_once('(')?;
+    let (line_number, column) = position.split_once(',')?;
+    let (head, message) = rest.split_once(": ")?;
+    let (severity, code) = head.split_once(' ')?;
+    Some(Diagnostic {
+        file: diagnostic_path(root, base, file.trim()),
+        line: line_number.parse().ok()?,
+        column: column.parse().ok()?,
+        severity: severity.to_string(),
+        message: message.to_string(),
+        code: Some(code.to_string()),
+    })
+}
+
+// `path:line[:column]: error|warning|note: message`, as printed by gcc, go vet
+// and many linters.
+fn parse_generic_line(line: &str, root: &Path, base: &Path) -> Option<Diagnostic> {
+    let mut parts = line.splitn(3, ':');
+    let file = parts.next()?.trim();
+    let line_number: u64 = parts.next()?.trim().parse().ok()?;
+    let remainder = parts.next()?;
+    let (column, rest) = match remainder.split_once(':') {
+        Some((column, rest)) if column.trim().parse::<u64>().is_ok() => {
+            (column.trim().parse().ok()?, rest)
+        }
+        _ => (0, remainder),
+    };
+    let (severity, message) = rest.trim().split_once(':')?;
+    let severity = severity.trim().to_lowercase();
+    if !matches!(severity.as_str(), "error" | "warning" | "note") || file.is_empty() {
+        return None;
+    }
+    Some(Diagnostic {
+        file: diagnostic_path(root, base, file),
+        line: line_number,
+        column,
+        severity,
+        message: message.trim().to_string(),
+        code: None,
+    })
+}
+
+fn parse_diagnostics(
+    format: DiagnosticFormat,
+    response: &ExecResponse,
+    root: &Path,
+    base: &Path,
+) -> Vec<Diagnostic> {
+    match format {
+        DiagnosticFormat::Cargo => parse_cargo_diagnostics(&response.stdout, root, base),
+        DiagnosticFormat::Eslint => parse_eslint_diagnostics(&response.stdout, root, base),
+        DiagnosticFormat::Tsc => response
+            .stdout
+            .lines()
+            .chain(response.stderr.lines())
+            .filter_map(|line| parse_tsc_line(line, root, base))
+            .collect(),
+        DiagnosticFormat::Generic => response
+            .stdout
+            .lines()
+            .chain(response.stderr.lines())
+            .filter_map(|line| parse_generic_line(line, root, base))
+            .collect(),
+    }
+}
+
+#[tauri::command(async)]
+fn mcp_shell_exec_diagnostics(
+    last_exec: tauri::State<'_, LastExecStore>,
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    timeout_ms: Option<u64>,
+) -> McpResult<DiagnosticsResponse> {
+    let (command, args) = resolve_command_alias(&load_config(), &command, args.unwrap_or_default());
+    let (format, final_args) = prepare_diagnostic_args(&command, &args);
+    let (root, working_dir, config) = prepare_shell_exec(&command, &final_args, cwd.as_deref())?;
+
+    let mut cmd = Command::new(&command);
+    cmd.args(&final_args);
+
+    let timeout = resolve_shell_timeout_ms(&config, &command, timeout_ms);
+    let response = spawn_command(
+        cmd,
+        command,
+        final_args,
+        Some(working_dir.clone()),
+        ExecOptions {
+            timeout: Some(Duration::from_millis(timeout)),
+            ..ExecOptions::default()
+        },
+    )?;
+    last_exec.record(&response);
+
+    let diagnostics = if response.exit_code == 0 {
+        Vec::new()
+    } else {
+        parse_diagnostics(format, &response, &root, &working_dir)
+    };
+
+    Ok(DiagnosticsResponse {
+        format: format.as_str().to_string(),
+        diagnostics,
+        result: response,
+    })
+}
+
+#[tauri::command]
+fn mcp_exec_last(
+    last_exec: tauri::State<'_, LastExecStore>,
+    command_name: String,
+) -> McpResult<Option<ExecResponse>> {
+    let results = last_exec.results.lock().map_err(|err| err.to_string())?;
+    Ok(results.get(&command_name.to_lowercase()).cloned())
+}
+
+// Kills processes recorded by sessions that are no longer running, as long as
+// the pid still belongs to the command that was recorded for it.
+#[cfg(unix)]
+#[tauri::command]
+fn mcp_exec_reap_orphans(jobs: tauri::State<'_, JobRegistry>) -> McpResult<ReapOrphansResponse> {
+    let _jobs = jobs.jobs.lock().map_err(|err| err.to_string())?;
+    let path = pids_file_path()?;
+    let current = std::process::id();
+
+    // Entries of dead sessions are settled by this pass either way, so only
+    // those of live sessions stay in the file.
+    let (live, stale): (Vec<PidRecord>, Vec<PidRecord>) =
+        read_pid_records(&path).into_iter().partition(|record| {
+            record.owner_pid == current || process_arguments(record.owner_pid).is_some()
+        });
+
+    let mut reaped = Vec::new();
+    for record in stale {
+        let Some(arguments) = process_arguments(record.pid) else {
+            continue;
+        };
+        // A reused pid now belongs to something else; leave it alone.
+        if process_matches_command(&arguments, &record.command) && terminate_process(record.pid) {
+            reaped.push(record.pid);
+        }
+    }
+    store_pid_records(&path, &live)?;
+
+    Ok(ReapOrphansResponse {
+        reaped: reaped.len(),
+        pids: reaped,
+    })
+}
+
+#[cfg(not(unix))]
+#[tauri::command]
+fn mcp_exec_reap_orphans(_jobs: tauri::State<'_, JobRegistry>) -> McpResult<ReapOrphansResponse> {
+    Err("La limpieza de procesos huérfanos solo está disponible en sistemas Unix.".into())
+}
+
+// Only executions started with a `run_id` are registered.
+#[tauri::command]
+fn mcp_exec_active(jobs: tauri::State<'_, JobRegistry>) -> McpResult<Vec<ActiveJob>> {
+    jobs.active()
+}
+
+#[tauri::command]
+fn mcp_shell_open_files(
+    jobs: tauri::State<'_, JobRegistry>,
+    run_id: String,
+) -> McpResult<OpenFilesResponse> {
+    if !cfg!(target_os = "linux") {
+        return Err("La inspección de archivos abiertos solo está disponible en Linux.".into());
+    }
+
+    let (pid, command) = jobs.lookup(&run_id)?;
+    let fd_dir = PathBuf::from(format!("/proc/{pid}/fd"));
+    let entries = fs::read_dir(&fd_dir).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => "El proceso ya no está en ejecución.".to_string(),
+        io::ErrorKind::PermissionDenied => {
+            "Sin permisos para inspeccionar los descriptores del proceso.".to_string()
+        }
+        _ => err.to_string(),
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        // Descriptors can close between listing and resolving; skip those quietly.
+        let Ok(target) = fs::read_link(entry.path()) else {
+            continue;
+        };
+        // Pipes, sockets and anon inodes resolve to pseudo paths like `pipe:[123]`.
+        if !target.is_absolute() || !target.is_file() {
+            continue;
+        }
+        let display = target.to_string_lossy().to_string();
+        if !files.contains(&display) {
+            files.push(display);
+        }
+    }
+    files.sort();
+
+    Ok(OpenFilesResponse {
+        run_id,
+        pid,
+        command,
+        files,
+    })
+}
+
+fn project_script(name: &str, source: &str, command: String, runner: &str) -> ProjectScript {
+    let run_args = match source {
+        "npm" => vec!["run".to_string(), name.to_string()],
+        _ => vec![name.to_string()],
+    };
+    ProjectScript {
+        name: name.to_string(),
+        source: source.to_string(),
+        command,
+        runner: runner.to_string(),
+        run_args,
+    }
+}
+
+fn npm_scripts(dir: &Path) -> Vec<ProjectScript> {
+    let Ok(contents) = fs::read_to_string(dir.join("package.json")) else {
+        return Vec::new();
+    };
+    let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&contents) else {
+        return Vec::new();
+    };
+    let runner = if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else {
+        "npm"
+    };
+
+    manifest
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+        .map(|scripts| {
+            scripts
+                .iter()
+                .filter_map(|(name, body)| {
+                    let body = body.as_str()?;
+                    Some(project_script(name, "npm", body.to_string(), runner))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn cargo_aliases(dir: &Path) -> Vec<ProjectScript> {
+    let config_dir = dir.join(".cargo");
+    let Some(contents) = ["config.toml", "config"]
+        .iter()
+        .find_map(|name| fs::read_to_string(config_dir.join(name)).ok())
+    else {
+        return Vec::new();
+    };
+    let Ok(config) = contents.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+
+    config
+        .get("alias")
+        .and_then(|aliases| aliases.as_table())
+        .map(|aliases| {
+            aliases
+                .iter()
+                .filter_map(|(name, value)| {
+                    let command = match value {
+                        toml::Value::String(text) => text.clone(),
+                        toml::Value::Array(parts) => parts
+                            .iter()
+                            .filter_map(|part| part.as_str())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        _ => return None,
+                    };
+                    Some(project_script(name, "cargo", command, "cargo"))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Collects the indented lines that follow a rule header, stopping at the first
+// non-blank line that isn't indented.
+fn indented_body(lines: &[&str], header: usize) -> String {
+    lines[header + 1..]
+        .iter()
+        .take_while(|line| line.trim().is_empty() || line.starts_with([' ', '\t']))
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn makefile_targets(dir: &Path) -> Vec<ProjectScript> {
+    let Some(contents) = ["GNUmakefile", "makefile", "Makefile"]
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+    else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut scripts: Vec<ProjectScript> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with([' ', '\t', '#']) {
+            continue;
+        }
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `:=` / `::=` assignments and `VAR = a:b` lines aren't rules.
+        if rest.starts_with('=') || rest.starts_with(":=") || targets.contains('=') {
+            continue;
+        }
+        let body = indented_body(&lines, index);
+        for target in targets.split_whitespace() {
+            // Skip special (`.PHONY`), pattern (`%.o`) and computed (`$(X)`) targets.
+            if target.starts_with('.') || target.contains(['%', '$']) {
+                continue;
+            }
+            if scripts.iter().any(|script| script.name == target) {
+                continue;
+            }
+            scripts.push(project_script(target, "make", body.clone(), "make"));
         }
     }
+    scripts
+}
 
-    if mem_total == 0 {
-        return None;
+fn just_recipes(dir: &Path) -> Vec<ProjectScript> {
+    let Some(contents) = ["justfile", "Justfile", ".justfile"]
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+    else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut scripts = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with([' ', '\t', '#', '[']) {
+            continue;
+        }
+        let header = line.trim_start_matches('@');
+        let name_end = header
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'))
+            .unwrap_or(header.len());
+        let name = &header[..name_end];
+        if name.is_empty() || matches!(name, "set" | "alias" | "export" | "import" | "mod") {
+            continue;
+        }
+        // Parameters may sit between the name and the colon; `:=` is an assignment.
+        let Some(colon) = header.find(':') else {
+            continue;
+        };
+        if header[colon + 1..].starts_with('=') {
+            continue;
+        }
+        scripts.push(project_script(
+            name,
+            "just",
+            indented_body(&lines, index),
+            "just",
+        ));
     }
+    scripts
+}
 
-    Some((mem_total, mem_free, mem_available, swap_total, swap_free))
+fn collect_project_scripts(dir: &Path) -> Vec<ProjectScript> {
+    let mut scripts = npm_scripts(dir);
+    scripts.extend(cargo_aliases(dir));
+    scripts.extend(makefile_targets(dir));
+    scripts.extend(just_recipes(dir));
+    scripts
 }
 
-fn read_linux_uptime() -> Option<u64> {
-    if !cfg!(target_os = "linux") {
-        return None;
+fn push_dependency(
+    output: &mut Vec<Dependency>,
+    manifest: &str,
+    name: &str,
+    version: String,
+    kind: &str,
+) {
+    output.push(Dependency {
+        name: name.to_string(),
+        version,
+        kind: kind.to_string(),
+        manifest: manifest.to_string(),
+    });
+}
+
+fn npm_dependencies(manifest: &serde_json::Value, relative: &str, output: &mut Vec<Dependency>) {
+    for (field, kind) in [
+        ("dependencies", "runtime"),
+        ("devDependencies", "dev"),
+        ("peerDependencies", "peer"),
+        ("optionalDependencies", "optional"),
+    ] {
+        let Some(entries) = manifest.get(field).and_then(|value| value.as_object()) else {
+            continue;
+        };
+        for (name, version) in entries {
+            let version = version.as_str().unwrap_or("*").to_string();
+            push_dependency(output, relative, name, version, kind);
+        }
     }
-    let contents = fs::read_to_string("/proc/uptime").ok()?;
-    let first = contents.split_whitespace().next()?;
-    let seconds = first.split('.').next()?.parse::<u64>().ok()?;
-    Some(seconds)
 }
 
-fn count_linux_processes() -> Option<usize> {
-    if !cfg!(target_os = "linux") {
+// Cargo accepts `"1.0"`, `{ version = "1.0" }`, `{ path = ".." }`, `{ workspace = true }`...
+fn cargo_dependency_version(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(version) => version.clone(),
+        toml::Value::Table(table) => {
+            if let Some(version) = table.get("version").and_then(|value| value.as_str()) {
+                version.to_string()
+            } else if table.get("workspace").and_then(|value| value.as_bool()) == Some(true) {
+                "workspace".to_string()
+            } else if let Some(path) = table.get("path").and_then(|value| value.as_str()) {
+                format!("path:{path}")
+            } else if let Some(git) = table.get("git").and_then(|value| value.as_str()) {
+                format!("git:{git}")
+            } else {
+                "*".to_string()
+            }
+        }
+        _ => "*".to_string(),
+    }
+}
+
+fn cargo_dependency_tables(table: &toml::Table, relative: &str, output: &mut Vec<Dependency>) {
+    for (field, kind) in [
+        ("dependencies", "runtime"),
+        ("dev-dependencies", "dev"),
+        ("build-dependencies", "build"),
+    ] {
+        let Some(entries) = table.get(field).and_then(|value| value.as_table()) else {
+            continue;
+        };
+        for (name, value) in entries {
+            push_dependency(
+                output,
+                relative,
+                name,
+                cargo_dependency_version(value),
+                kind,
+            );
+        }
+    }
+}
+
+fn cargo_dependencies(manifest: &toml::Table, relative: &str, output: &mut Vec<Dependency>) {
+    cargo_dependency_tables(manifest, relative, output);
+    // `[target.'cfg(...)'.dependencies]` tables follow the same layout.
+    if let Some(targets) = manifest.get("target").and_then(|value| value.as_table()) {
+        for target in targets.values().filter_map(|value| value.as_table()) {
+            cargo_dependency_tables(target, relative, output);
+        }
+    }
+    if let Some(shared) = manifest
+        .get("workspace")
+        .and_then(|value| value.get("dependencies"))
+        .and_then(|value| value.as_table())
+    {
+        for (name, value) in shared {
+            push_dependency(
+                output,
+                relative,
+                name,
+                cargo_dependency_version(value),
+                "runtime",
+            );
+        }
+    }
+}
+
+// Splits a PEP 508 requirement such as `requests[socks]>=2.31; python_version>"3.8"`.
+fn parse_pep508(requirement: &str) -> Option<(String, String)> {
+    let requirement = requirement.split(';').next()?.trim();
+    let name_end = requirement
+        .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..name_end];
+    if name.is_empty() {
         return None;
     }
-    let entries = fs::read_dir("/proc").ok()?;
-    let mut count = 0_usize;
-    for entry in entries {
-        if let Ok(dir) = entry {
-            if let Some(name) = dir.file_name().to_str() {
-                if name.chars().all(|c| c.is_ascii_digit()) {
-                    count += 1;
-                }
+    let mut rest = requirement[name_end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map(|(_, tail)| tail).unwrap_or("");
+    }
+    let version = rest
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim();
+    let version = if version.is_empty() { "*" } else { version };
+    Some((name.to_string(), version.to_string()))
+}
+
+fn pyproject_dependencies(manifest: &toml::Table, relative: &str, output: &mut Vec<Dependency>) {
+    let project = manifest.get("project");
+    let mut pep508_lists = Vec::new();
+    if let Some(list) = project
+        .and_then(|project| project.get("dependencies"))
+        .and_then(|value| value.as_array())
+    {
+        pep508_lists.push((list, "runtime"));
+    }
+    if let Some(groups) = project
+        .and_then(|project| project.get("optional-dependencies"))
+        .and_then(|value| value.as_table())
+    {
+        pep508_lists.extend(
+            groups
+                .values()
+                .filter_map(|value| value.as_array())
+                .map(|list| (list, "optional")),
+        );
+    }
+    for (list, kind) in pep508_lists {
+        for requirement in list.iter().filter_map(|value| value.as_str()) {
+            if let Some((name, version)) = parse_pep508(requirement) {
+                push_dependency(output, relative, &name, version, kind);
             }
         }
     }
-    Some(count)
+
+    // Poetry keeps its own tables, with `python` pinning the interpreter rather than a package.
+    let Some(poetry) = manifest
+        .get("tool")
+        .and_then(|tool| tool.get("poetry"))
+        .and_then(|value| value.as_table())
+    else {
+        return;
+    };
+    let mut tables = vec![(poetry.get("dependencies"), "runtime")];
+    tables.push((poetry.get("dev-dependencies"), "dev"));
+    if let Some(groups) = poetry.get("group").and_then(|value| value.as_table()) {
+        for group in groups.values() {
+            tables.push((group.get("dependencies"), "dev"));
+        }
+    }
+    for (table, kind) in tables {
+        let Some(table) = table.and_then(|value| value.as_table()) else {
+            continue;
+        };
+        for (name, value) in table {
+            if name.eq_ignore_ascii_case("python") {
+                continue;
+            }
+            push_dependency(
+                output,
+                relative,
+                name,
+                cargo_dependency_version(value),
+                kind,
+            );
+        }
+    }
 }
 
-fn metrics_log_path() -> McpResult<PathBuf> {
-    let home = resolve_home_dir()?;
-    let directory = home.join(".cerebro").join("logs");
-    if !directory.exists() {
-        fs::create_dir_all(&directory).map_err(|err| err.to_string())?;
+// Resolves workspace member patterns like `crates/*` or `packages/app`; only the
+// last segment may contain glob characters.
+fn expand_workspace_members(dir: &Path, patterns: &[&str]) -> Vec<PathBuf> {
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.starts_with('!') || pattern.contains("..") {
+            continue;
+        }
+        let (parent, leaf) = match pattern.rsplit_once('/') {
+            Some((parent, leaf)) => (dir.join(parent), leaf),
+            None => (dir.to_path_buf(), pattern),
+        };
+        if !leaf.contains(['*', '?', '[']) {
+            members.push(parent.join(leaf));
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&parent) else {
+            continue;
+        };
+        let mut matched: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter(|entry| glob_match(leaf, &entry.file_name().to_string_lossy()))
+            .map(|entry| entry.path())
+            .collect();
+        matched.sort();
+        members.extend(matched);
     }
-    Ok(directory.join("metrics.jsonl"))
+    members.retain(|member| member.is_dir() && member != dir);
+    members.dedup();
+    members
 }
 
-fn rotate_metrics_log_if_needed(path: &Path) -> McpResult<()> {
-    if !path.exists() {
-        return Ok(());
+fn manifest_dependencies(
+    root: &Path,
+    dir: &Path,
+    output: &mut Vec<Dependency>,
+    include_members: bool,
+) -> McpResult<()> {
+    let mut members = Vec::new();
+
+    let package_json = dir.join("package.json");
+    if let Ok(contents) = fs::read_to_string(&package_json) {
+        if let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&contents) {
+            npm_dependencies(&manifest, &relative_from_root(root, &package_json)?, output);
+            // `workspaces` is either an array or `{ packages: [...] }` (yarn).
+            let workspaces = manifest.get("workspaces");
+            let patterns: Vec<&str> = workspaces
+                .and_then(|value| value.as_array())
+                .or_else(|| {
+                    workspaces
+                        .and_then(|value| value.get("packages"))
+                        .and_then(|value| value.as_array())
+                })
+                .map(|list| list.iter().filter_map(|value| value.as_str()).collect())
+                .unwrap_or_default();
+            members.extend(expand_workspace_members(dir, &patterns));
+        }
     }
 
-    let metadata = fs::metadata(path).map_err(|err| err.to_string())?;
-    let mut should_rotate = metadata.len() > MAX_METRICS_FILE_SIZE;
+    let cargo_toml = dir.join("Cargo.toml");
+    if let Ok(contents) = fs::read_to_string(&cargo_toml) {
+        if let Ok(manifest) = contents.parse::<toml::Table>() {
+            cargo_dependencies(&manifest, &relative_from_root(root, &cargo_toml)?, output);
+            let patterns: Vec<&str> = manifest
+                .get("workspace")
+                .and_then(|value| value.get("members"))
+                .and_then(|value| value.as_array())
+                .map(|list| list.iter().filter_map(|value| value.as_str()).collect())
+                .unwrap_or_default();
+            members.extend(expand_workspace_members(dir, &patterns));
+        }
+    }
 
-    if let Ok(modified) = metadata.modified() {
-        if let Ok(age) = SystemTime::now().duration_since(modified) {
-            if age.as_secs() > MAX_METRICS_FILE_AGE_SECS {
-                should_rotate = true;
+    let pyproject = dir.join("pyproject.toml");
+    if let Ok(contents) = fs::read_to_string(&pyproject) {
+        if let Ok(manifest) = contents.parse::<toml::Table>() {
+            pyproject_dependencies(&manifest, &relative_from_root(root, &pyproject)?, output);
+        }
+    }
+
+    if include_members && !members.is_empty() {
+        members.sort();
+        members.dedup();
+        // Members could point outside through symlinks; stay in the orbit.
+        let real_root = root.canonicalize().map_err(|err| err.to_string())?;
+        for member in members {
+            let inside = member
+                .canonicalize()
+                .is_ok_and(|real| real.starts_with(&real_root));
+            if inside {
+                manifest_dependencies(root, &member, output, false)?;
             }
         }
     }
+    Ok(())
+}
 
-    if !should_rotate {
-        return Ok(());
+fn project_dir(path: Option<&str>) -> McpResult<(PathBuf, PathBuf)> {
+    let root = safe_root()?;
+    let dir = build_path(&root, path)?;
+
+    if !dir.exists() {
+        return Err("La ruta indicada no existe.".into());
     }
 
-    let directory = path
-        .parent()
-        .ok_or_else(|| "No se pudo resolver el directorio de métricas.".to_string())?;
+    if !dir.is_dir() {
+        return Err("La ruta indicada no es un directorio.".into());
+    }
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_secs())
-        .unwrap_or(0);
+    Ok((root, dir))
+}
 
-    let mut candidate = directory.join(format!("metrics-{}.jsonl", timestamp));
-    let mut index = 1_u32;
+fn project_kind(dir: &Path) -> &'static str {
+    const MARKERS: &[(&str, &str)] = &[
+        ("Cargo.toml", "rust"),
+        ("package.json", "node"),
+        ("deno.json", "deno"),
+        ("pyproject.toml", "python"),
+        ("requirements.txt", "python"),
+        ("setup.py", "python"),
+        ("go.mod", "go"),
+        ("Gemfile", "ruby"),
+        ("pom.xml", "java"),
+        ("build.gradle", "java"),
+        ("build.gradle.kts", "java"),
+        ("Makefile", "make"),
+        ("justfile", "just"),
+    ];
+    MARKERS
+        .iter()
+        .find(|(marker, _)| dir.join(marker).is_file())
+        .map_or("unknown", |(_, kind)| kind)
+}
 
-    while candidate.exists() {
-        candidate = directory.join(format!("metrics-{}-{}.jsonl", timestamp, index));
-        index += 1;
-    }
+fn summarize_project(root: &Path, dir: &Path) -> McpResult<ProjectSummary> {
+    let last_modified = fs::metadata(dir)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis() as u64);
+    // Only a repo rooted here counts; a folder inside an orbit-wide repo is not its own project.
+    let is_git_repo = dir.join(".git").exists();
+    let status = is_git_repo
+        .then(|| {
+            read_git_status(
+                dir,
+                Some(Duration::from_millis(ORBIT_PROJECT_GIT_TIMEOUT_MS)),
+            )
+            .ok()
+        })
+        .flatten();
+
+    Ok(ProjectSummary {
+        name: dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: relative_from_root(root, dir)?,
+        kind: project_kind(dir),
+        is_git_repo,
+        is_dirty: status.as_ref().map(|status| !status.clean),
+        branch: status.and_then(|status| status.branch),
+        last_modified,
+    })
+}
+
+#[tauri::command(async)]
+fn mcp_orbit_projects() -> McpResult<Vec<ProjectSummary>> {
+    let root = safe_root()?;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&root)
+        .map_err(|err| err.to_string())?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            !entry.file_name().to_string_lossy().starts_with('.')
+                && entry.file_type().is_ok_and(|kind| kind.is_dir())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+
+    // Each git probe has its own timeout and they run side by side, so one slow
+    // repo delays the list by at most that timeout.
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<ProjectSummary>>> =
+        Mutex::new((0..dirs.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..ORBIT_PROJECT_PARALLELISM.min(dirs.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(dir) = dirs.get(index) else {
+                    break;
+                };
+                let summary = summarize_project(&root, dir).ok();
+                if let Ok(mut results) = results.lock() {
+                    results[index] = summary;
+                }
+            });
+        }
+    });
 
-    fs::rename(path, &candidate).map_err(|err| err.to_string())
+    Ok(results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect())
 }
 
-fn disk_info_for_path(system: &System, target: &Path) -> Option<DiskInfo> {
-    let mut selected_index: Option<usize> = None;
-    let mut best_depth = 0_usize;
+#[tauri::command]
+fn mcp_project_scripts(path: Option<String>) -> McpResult<ProjectScriptsResponse> {
+    let (root, dir) = project_dir(path.as_deref())?;
+    Ok(ProjectScriptsResponse {
+        path: relative_from_root(&root, &dir)?,
+        scripts: collect_project_scripts(&dir),
+    })
+}
 
-    for (index, disk) in system.disks().iter().enumerate() {
-        let mount_point = disk.mount_point();
-        if target.starts_with(mount_point) {
-            let depth = mount_point.components().count();
-            if depth > best_depth {
-                best_depth = depth;
-                selected_index = Some(index);
+#[tauri::command]
+fn mcp_project_dependencies(path: Option<String>) -> McpResult<ProjectDependenciesResponse> {
+    let (root, dir) = project_dir(path.as_deref())?;
+    let mut dependencies = Vec::new();
+    manifest_dependencies(&root, &dir, &mut dependencies, true)?;
+    Ok(ProjectDependenciesResponse {
+        path: relative_from_root(&root, &dir)?,
+        dependencies,
+    })
+}
+
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_STYLE_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+};
+const HASH_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+};
+const MARKUP_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+};
+
+fn loc_language(path: &Path) -> Option<(&'static str, CommentSyntax)> {
+    let name = path.file_name()?.to_string_lossy();
+    match name.as_ref() {
+        "Makefile" | "makefile" | "GNUmakefile" => return Some(("Makefile", HASH_COMMENTS)),
+        "Dockerfile" => return Some(("Dockerfile", HASH_COMMENTS)),
+        _ => {}
+    }
+
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => ("Rust", C_STYLE_COMMENTS),
+        "ts" | "tsx" | "mts" | "cts" => ("TypeScript", C_STYLE_COMMENTS),
+        "js" | "jsx" | "mjs" | "cjs" => ("JavaScript", C_STYLE_COMMENTS),
+        "go" => ("Go", C_STYLE_COMMENTS),
+        "c" | "h" => ("C", C_STYLE_COMMENTS),
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => ("C++", C_STYLE_COMMENTS),
+        "java" => ("Java", C_STYLE_COMMENTS),
+        "kt" | "kts" => ("Kotlin", C_STYLE_COMMENTS),
+        "swift" => ("Swift", C_STYLE_COMMENTS),
+        "cs" => ("C#", C_STYLE_COMMENTS),
+        "scss" | "less" => ("SCSS", C_STYLE_COMMENTS),
+        "css" => (
+            "CSS",
+            CommentSyntax {
+                line: &[],
+                block: Some(("/*", "*/")),
+            },
+        ),
+        "py" | "pyi" => ("Python", HASH_COMMENTS),
+        "rb" => ("Ruby", HASH_COMMENTS),
+        "sh" | "bash" | "zsh" => ("Shell", HASH_COMMENTS),
+        "toml" => ("TOML", HASH_COMMENTS),
+        "yaml" | "yml" => ("YAML", HASH_COMMENTS),
+        "html" | "htm" => ("HTML", MARKUP_COMMENTS),
+        "vue" => ("Vue", MARKUP_COMMENTS),
+        "svelte" => ("Svelte", MARKUP_COMMENTS),
+        "xml" | "svg" => ("XML", MARKUP_COMMENTS),
+        "md" | "markdown" => ("Markdown", MARKUP_COMMENTS),
+        "sql" => (
+            "SQL",
+            CommentSyntax {
+                line: &["--"],
+                block: Some(("/*", "*/")),
+            },
+        ),
+        "lua" => (
+            "Lua",
+            CommentSyntax {
+                line: &["--"],
+                block: None,
+            },
+        ),
+        "json" => (
+            "JSON",
+            CommentSyntax {
+                line: &[],
+                block: None,
+            },
+        ),
+        _ => return None,
+    };
+    Some(language)
+}
+
+// Block comments only count when they open at the start of a line; a `/*`
+// after code is far more often inside a string than a real comment.
+fn count_loc(text: &str, syntax: &CommentSyntax, stats: &mut LangStats) {
+    let mut in_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if in_block {
+            stats.comment_lines += 1;
+            if let Some((_, close)) = syntax.block {
+                in_block = !trimmed.contains(close);
             }
+            continue;
+        }
+        if trimmed.is_empty() {
+            stats.blank_lines += 1;
+        } else if syntax.line.iter().any(|marker| trimmed.starts_with(marker)) {
+            stats.comment_lines += 1;
+        } else if let Some((rest, close)) = syntax
+            .block
+            .and_then(|(open, close)| trimmed.strip_prefix(open).map(|rest| (rest, close)))
+        {
+            stats.comment_lines += 1;
+            in_block = !rest.contains(close);
+        } else {
+            stats.code_lines += 1;
         }
     }
+}
 
-    selected_index.map(|index| {
-        let disk = &system.disks()[index];
-        let total = disk.total_space();
-        let free = disk.available_space();
-        let usage_percent = if total > 0 {
-            Some(((total.saturating_sub(free)) as f64 / total as f64 * 100.0) as f32)
-        } else {
-            None
+fn collect_loc(
+    root: &Path,
+    dir: &Path,
+    filter: &mut IgnoreFilter,
+    visited: &mut VisitedDirs,
+    languages: &mut BTreeMap<&'static str, LangStats>,
+    counted: &mut usize,
+) -> McpResult<bool> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .map_err(|err| err.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string())?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let file_type = entry.file_type().map_err(|err| err.to_string())?;
+        let path = entry.path();
+        let relative = relative_from_root(root, &path)?;
+        if file_type.is_symlink()
+            || entry.file_name() == ".git"
+            || filter.is_ignored(&relative, file_type.is_dir())
+        {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !visited.enter(root, &path) {
+                continue;
+            }
+            let mark = filter.enter_dir(root, &path);
+            let truncated = collect_loc(root, &path, filter, visited, languages, counted);
+            filter.leave_dir(mark);
+            if truncated? {
+                return Ok(true);
+            }
+            continue;
+        }
+
+        let Some((language, syntax)) = loc_language(&path) else {
+            continue;
         };
-        DiskInfo {
-            mount: disk.mount_point().to_string_lossy().replace('\\', "/"),
-            total,
-            free,
-            usage_percent,
+        let too_large = entry
+            .metadata()
+            .ok()
+            .is_none_or(|meta| meta.len() > MAX_LOC_FILE_BYTES);
+        if too_large {
+            continue;
+        }
+        if *counted >= MAX_LOC_FILES {
+            return Ok(true);
+        }
+        let Ok(bytes) = fs::read(&path) else {
+            continue;
+        };
+        *counted += 1;
+
+        let stats = languages.entry(language).or_insert_with(|| LangStats {
+            language,
+            ..LangStats::default()
+        });
+        stats.files += 1;
+        count_loc(&String::from_utf8_lossy(&bytes), &syntax, stats);
+    }
+    Ok(false)
+}
+
+#[tauri::command(async)]
+fn mcp_project_loc(path: Option<String>) -> McpResult<ProjectLocResponse> {
+    let (root, dir) = project_dir(path.as_deref())?;
+    let mut filter = IgnoreFilter::new(&root, &dir, true);
+    let mut visited = VisitedDirs::default();
+    visited.enter(&root, &dir);
+    let mut languages = BTreeMap::new();
+    let mut counted = 0;
+    let truncated = collect_loc(
+        &root,
+        &dir,
+        &mut filter,
+        &mut visited,
+        &mut languages,
+        &mut counted,
+    )?;
+
+    let mut languages: Vec<LangStats> = languages.into_values().collect();
+    languages.sort_by_key(|language| std::cmp::Reverse(language.code_lines));
+    Ok(ProjectLocResponse {
+        path: relative_from_root(&root, &dir)?,
+        languages,
+        truncated,
+        cycles: visited.cycles,
+    })
+}
+
+#[tauri::command(async)]
+fn mcp_project_run_script(
+    last_exec: tauri::State<'_, LastExecStore>,
+    path: Option<String>,
+    name: String,
+    source: Option<String>,
+    timeout_ms: Option<u64>,
+) -> McpResult<ExecResponse> {
+    if name.starts_with('-') || has_disallowed_tokens(std::slice::from_ref(&name)) {
+        return Err("Nombre de script inválido.".into());
+    }
+
+    let (_, dir) = project_dir(path.as_deref())?;
+    let mut matches: Vec<ProjectScript> = collect_project_scripts(&dir)
+        .into_iter()
+        .filter(|script| script.name == name)
+        .filter(|script| {
+            source
+                .as_deref()
+                .is_none_or(|source| script.source.eq_ignore_ascii_case(source))
+        })
+        .collect();
+
+    let script = match matches.len() {
+        0 => return Err("El script indicado no existe en el proyecto.".into()),
+        1 => matches.remove(0),
+        _ => {
+            return Err(
+                "El script existe en varias fuentes; indica source (npm, cargo, make o just)."
+                    .into(),
+            )
+        }
+    };
+
+    if !is_shell_command_allowed(&script.runner) {
+        return Err("Comando no permitido por la política de seguridad.".into());
+    }
+
+    let mut cmd = Command::new(&script.runner);
+    cmd.args(&script.run_args);
+
+    let timeout = resolve_shell_timeout_ms(&load_config(), &script.runner, timeout_ms);
+    let response = spawn_command(
+        cmd,
+        script.runner,
+        script.run_args,
+        Some(dir),
+        ExecOptions {
+            timeout: Some(Duration::from_millis(timeout)),
+            ..ExecOptions::default()
+        },
+    )?;
+    last_exec.record(&response);
+    Ok(response)
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    wait_for_child(child, Some(timeout), None)
+}
+
+// Returns `None` once `limit` passes or `cancel` is raised; the child is left running.
+fn wait_for_child(
+    child: &mut Child,
+    limit: Option<Duration>,
+    cancel: Option<&AtomicBool>,
+) -> io::Result<Option<ExitStatus>> {
+    if limit.is_none() && cancel.is_none() {
+        return child.wait().map(Some);
+    }
+    let deadline = limit.map(|limit| Instant::now() + limit);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            || cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+        {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn version_args(command: &str) -> &'static [&'static str] {
+    match command {
+        "go" => &["version"],
+        _ => &["--version"],
+    }
+}
+
+fn first_non_empty_line(bytes: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string())
+}
+
+fn probe_command_version(command: &str, timeout: Duration) -> Option<String> {
+    let mut child = Command::new(command)
+        .args(version_args(command))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    match wait_with_timeout(&mut child, timeout) {
+        Ok(Some(status)) if status.success() => {}
+        Ok(Some(_)) => return None,
+        _ => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
         }
-    })
+    }
+
+    let output = child.wait_with_output().ok()?;
+    // Some tools (older python) print their version on stderr.
+    first_non_empty_line(&output.stdout).or_else(|| first_non_empty_line(&output.stderr))
 }
 
-const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+fn probe_versions(commands: &[&str]) -> BTreeMap<String, Option<String>> {
+    let timeout = Duration::from_millis(VERSION_PROBE_TIMEOUT_MS);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(BTreeMap::new());
+
+    thread::scope(|scope| {
+        for _ in 0..VERSION_PROBE_PARALLELISM.min(commands.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(command) = commands.get(index) else {
+                    break;
+                };
+                let version = probe_command_version(command, timeout);
+                if let Ok(mut map) = results.lock() {
+                    map.insert(command.to_string(), version);
+                }
+            });
+        }
+    });
 
-fn encode_base64(data: &[u8]) -> String {
-    if data.is_empty() {
-        return String::new();
+    results.into_inner().unwrap_or_default()
+}
+
+#[tauri::command(async)]
+fn mcp_shell_versions() -> McpResult<BTreeMap<String, Option<String>>> {
+    Ok(probe_versions(ALLOWED_SHELL_COMMANDS))
+}
+
+fn command_category(command: &str) -> &'static str {
+    match command {
+        "npm" | "pnpm" | "yarn" | "npx" | "pip" | "pip3" => "package-manager",
+        "node" | "deno" | "python" => "runtime",
+        "cargo" | "go" | "just" | "make" => "build",
+        "rg" => "search",
+        _ => "utility",
     }
+}
 
-    let mut encoded = String::with_capacity(((data.len() + 2) / 3) * 4);
+// The executable `Command::new(command)` would run, searched the way the OS does.
+fn resolve_in_path(command: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    let extensions: Vec<String> = if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".into())
+            .split(';')
+            .filter(|extension| !extension.is_empty())
+            .map(|extension| extension.to_string())
+            .collect()
+    } else {
+        vec![String::new()]
+    };
 
-    for chunk in data.chunks(3) {
-        let b0 = chunk[0] as u32;
-        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
-        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+    env::split_paths(&paths).find_map(|directory| {
+        extensions.iter().find_map(|extension| {
+            let candidate = directory.join(format!("{command}{extension}"));
+            is_executable_file(&candidate).then_some(candidate)
+        })
+    })
+}
 
-        let triple = (b0 << 16) | (b1 << 8) | b2;
-        let idx0 = ((triple >> 18) & 0x3F) as usize;
-        let idx1 = ((triple >> 12) & 0x3F) as usize;
-        let idx2 = ((triple >> 6) & 0x3F) as usize;
-        let idx3 = (triple & 0x3F) as usize;
+#[cfg(unix)]
+fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
 
-        encoded.push(BASE64_TABLE[idx0] as char);
-        encoded.push(BASE64_TABLE[idx1] as char);
-        if chunk.len() > 1 {
-            encoded.push(BASE64_TABLE[idx2] as char);
-        } else {
-            encoded.push('=');
-        }
-        if chunk.len() > 2 {
-            encoded.push(BASE64_TABLE[idx3] as char);
-        } else {
-            encoded.push('=');
-        }
-    }
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
 
-    encoded
+#[cfg(not(unix))]
+fn is_executable_file(path: &Path) -> bool {
+    path.is_file()
 }
 
-fn decode_base64(input: &str) -> McpResult<Vec<u8>> {
-    let cleaned: String = input.chars().filter(|ch| !ch.is_whitespace()).collect();
+// Versions are only probed for installed commands, in parallel, so the
+// catalog costs at most a few probe timeouts.
+#[tauri::command(async)]
+fn mcp_shell_catalog(
+    category: Option<String>,
+    installed_only: Option<bool>,
+) -> McpResult<Vec<CatalogEntry>> {
+    let commands: Vec<(&str, Option<PathBuf>)> = ALLOWED_SHELL_COMMANDS
+        .iter()
+        .filter(|command| {
+            category
+                .as_deref()
+                .is_none_or(|wanted| wanted.eq_ignore_ascii_case(command_category(command)))
+        })
+        .map(|command| (*command, resolve_in_path(command)))
+        .filter(|(_, path)| path.is_some() || !installed_only.unwrap_or(false))
+        .collect();
 
-    if cleaned.len() % 4 != 0 {
-        return Err("Cadena base64 inválida.".into());
-    }
+    let installed: Vec<&str> = commands
+        .iter()
+        .filter(|(_, path)| path.is_some())
+        .map(|(command, _)| *command)
+        .collect();
+    let mut versions = probe_versions(&installed);
+
+    Ok(commands
+        .into_iter()
+        .map(|(command, path)| CatalogEntry {
+            command: command.to_string(),
+            category: command_category(command),
+            installed: path.is_some(),
+            path: path.map(|path| path.to_string_lossy().to_string()),
+            version: versions.remove(command).flatten(),
+        })
+        .collect())
+}
 
-    let mut output = Vec::with_capacity((cleaned.len() / 4) * 3);
+fn canonical_config_key(key: &str) -> Option<&'static str> {
+    match key {
+        "commandTimeouts" | "command_timeouts" => Some("commandTimeouts"),
+        "restrictPathArgs" | "restrict_path_args" => Some("restrictPathArgs"),
+        "commandAliases" | "command_aliases" => Some("commandAliases"),
+        "maxPathDepth" | "max_path_depth" => Some("maxPathDepth"),
+        _ => None,
+    }
+}
 
-    for chunk in cleaned.as_bytes().chunks(4) {
-        let mut buffer = [0_u32; 4];
-        let mut padding = 0;
+// RFC 7396 merge patch: objects merge recursively and `null` removes a key.
+fn merge_json_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    let serde_json::Value::Object(entries) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let Some(object) = target.as_object_mut() else {
+        return;
+    };
+    for (key, value) in entries {
+        if value.is_null() {
+            object.remove(&key);
+        } else {
+            merge_json_patch(object.entry(key).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
 
-        for (index, ch) in chunk.iter().enumerate() {
-            if *ch == b'=' {
-                buffer[index] = 0;
-                padding += 1;
-            } else {
-                buffer[index] =
-                    decode_base64_char(*ch).ok_or_else(|| "Cadena base64 inválida.".to_string())?;
+// Layered-config merge: objects merge recursively, anything else (arrays and
+// `null` included) replaces the earlier value. Unlike `merge_json_patch`,
+// `null` is kept as a value rather than deleting the key.
+fn deep_merge_json(target: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (target, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(entries)) => {
+            for (key, value) in entries {
+                match base.get_mut(&key) {
+                    Some(existing) => deep_merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
             }
         }
+        (target, overlay) => *target = overlay,
+    }
+}
 
-        let triple = (buffer[0] << 18) | (buffer[1] << 12) | (buffer[2] << 6) | buffer[3];
-        output.push(((triple >> 16) & 0xFF) as u8);
-        if padding < 2 {
-            output.push(((triple >> 8) & 0xFF) as u8);
+fn validate_config(config: &CerebroConfig) -> McpResult<()> {
+    for (command, timeout) in &config.command_timeouts {
+        if command.is_empty()
+            || !command
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "'{command}' no es un nombre de comando válido para commandTimeouts."
+            ));
         }
-        if padding < 1 {
-            output.push((triple & 0xFF) as u8);
+        if *timeout == 0 {
+            return Err(format!("El timeout de '{command}' debe ser positivo."));
         }
     }
-
-    Ok(output)
+    for (alias, expansion) in &config.command_aliases {
+        let Some(target) = expansion.first() else {
+            return Err(format!("El alias '{alias}' no indica ningún comando."));
+        };
+        if !is_shell_command_allowed(target) {
+            return Err(format!(
+                "El alias '{alias}' apunta a '{target}', que no está permitido."
+            ));
+        }
+        if has_disallowed_tokens(expansion) {
+            return Err(format!(
+                "El alias '{alias}' contiene operadores no permitidos."
+            ));
+        }
+    }
+    if config.max_path_depth == Some(0) {
+        return Err("maxPathDepth debe ser al menos 1.".into());
+    }
+    Ok(())
 }
 
-fn decode_base64_char(byte: u8) -> Option<u32> {
-    match byte {
-        b'A'..=b'Z' => Some((byte - b'A') as u32),
-        b'a'..=b'z' => Some((byte - b'a' + 26) as u32),
-        b'0'..=b'9' => Some((byte - b'0' + 52) as u32),
-        b'+' => Some(62),
-        b'/' => Some(63),
-        _ => None,
+#[tauri::command]
+fn mcp_config_set(patch: serde_json::Value) -> McpResult<EffectiveConfigResponse> {
+    let serde_json::Value::Object(entries) = patch else {
+        return Err("El parche de configuración debe ser un objeto JSON.".into());
+    };
+
+    let path = config_path()?;
+    let mut current = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<serde_json::Value>(&contents).map_err(|err| {
+            format!("El archivo de configuración actual no es JSON válido: {err}")
+        })?,
+        Err(_) => serde_json::Value::Object(serde_json::Map::new()),
+    };
+    // Fold aliases into one spelling so the merged file never holds both.
+    if let Some(object) = current.as_object_mut() {
+        let keys: Vec<String> = object.keys().cloned().collect();
+        for key in keys {
+            if let Some(canonical) = canonical_config_key(&key).filter(|name| *name != key) {
+                if let Some(value) = object.remove(&key) {
+                    object.entry(canonical).or_insert(value);
+                }
+            }
+        }
     }
-}
 
-fn spawn_command(
-    mut cmd: Command,
-    command_name: String,
-    args: Vec<String>,
-    cwd: Option<PathBuf>,
-) -> McpResult<ExecResponse> {
-    if let Some(ref directory) = cwd {
-        cmd.current_dir(directory);
+    let mut normalized = serde_json::Map::new();
+    for (key, value) in entries {
+        let canonical = canonical_config_key(&key).ok_or_else(|| {
+            format!("La clave '{key}' no existe o no se puede modificar en tiempo de ejecución.")
+        })?;
+        normalized.insert(canonical.to_string(), value);
     }
+    merge_json_patch(&mut current, serde_json::Value::Object(normalized));
 
-    let start = Instant::now();
-    let output = cmd.output().map_err(|err| err.to_string())?;
-    let duration = start.elapsed().as_millis();
-    let exit_code = output.status.code().unwrap_or(-1);
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    // Validate the merged result before touching disk, so a bad patch changes nothing.
+    let config: CerebroConfig = serde_json::from_value(current.clone())
+        .map_err(|err| format!("Configuración inválida: {err}"))?;
+    validate_config(&config)?;
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+    }
+    let mut payload = serde_json::to_vec_pretty(&current).map_err(|err| err.to_string())?;
+    payload.push(b'\n');
+    write_file_atomic(&path, &payload)?;
+    // A rewrite within the filesystem's timestamp granularity would look unchanged.
+    *MAX_PATH_DEPTH_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+
+    // Every command re-reads the file through `load_config`, so the change is already live.
+    mcp_config_get()
+}
 
+#[tauri::command]
+fn mcp_config_get() -> McpResult<EffectiveConfigResponse> {
+    let path = config_path()?;
     let root = safe_root()?;
-    let cwd_relative = cwd.and_then(|dir| relative_from_root(&root, &dir).ok());
+    let config = load_config();
 
-    Ok(ExecResponse {
-        command: command_name,
-        args,
-        cwd: cwd_relative,
-        exit_code,
-        stdout,
-        stderr,
-        duration_ms: duration,
+    // `load_config` falls back to defaults silently, so re-read the raw file to
+    // tell which keys actually came from it.
+    let raw = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+        .filter(|raw| serde_json::from_value::<CerebroConfig>(raw.clone()).is_ok());
+    let from_file = |keys: &[&str]| {
+        let present = raw
+            .as_ref()
+            .is_some_and(|raw| keys.iter().any(|key| raw.get(key).is_some()));
+        if present {
+            "file"
+        } else {
+            "default"
+        }
+    };
+
+    let ignore_path = root.join(IGNORE_FILE_NAME);
+    Ok(EffectiveConfigResponse {
+        config_path: path.to_string_lossy().replace('\\', "/"),
+        config_loaded: raw.is_some(),
+        orbit_path: ConfigValue {
+            value: root.to_string_lossy().replace('\\', "/"),
+            source: "default",
+        },
+        allowed_commands: ConfigValue {
+            value: ALLOWED_SHELL_COMMANDS
+                .iter()
+                .map(|value| value.to_string())
+                .collect(),
+            source: "default",
+        },
+        default_timeout_ms: ConfigValue {
+            value: DEFAULT_SHELL_TIMEOUT_MS,
+            source: "default",
+        },
+        command_timeouts: ConfigValue {
+            value: config.command_timeouts.into_iter().collect(),
+            source: from_file(&["commandTimeouts", "command_timeouts"]),
+        },
+        restrict_path_args: ConfigValue {
+            value: config.restrict_path_args,
+            source: from_file(&["restrictPathArgs", "restrict_path_args"]),
+        },
+        command_aliases: ConfigValue {
+            value: config.command_aliases.into_iter().collect(),
+            source: from_file(&["commandAliases", "command_aliases"]),
+        },
+        ignore_file: ConfigValue {
+            value: ignore_path.is_file().then(|| IGNORE_FILE_NAME.to_string()),
+            source: "default",
+        },
+        read_only_roots: ConfigValue {
+            value: config.read_only_roots,
+            source: from_file(&["readOnlyRoots", "read_only_roots"]),
+        },
+        max_path_depth: ConfigValue {
+            value: config.max_path_depth.unwrap_or(DEFAULT_MAX_PATH_DEPTH),
+            source: from_file(&["maxPathDepth", "max_path_depth"]),
+        },
     })
 }
 
 #[tauri::command]
-fn mcp_files_list(path: Option<String>) -> McpResult<ListResponse> {
+fn mcp_security_policy() -> McpResult<SecurityPolicyResponse> {
     let root = safe_root()?;
-    let target = build_path(&root, path.as_deref())?;
+    let config = load_config();
+    Ok(SecurityPolicyResponse {
+        orbit_path: root.to_string_lossy().replace('\\', "/"),
+        allowed_commands: ALLOWED_SHELL_COMMANDS
+            .iter()
+            .map(|value| value.to_string())
+            .collect(),
+        blocked_git_subcommands: BLOCKED_GIT_SUBCOMMANDS
+            .iter()
+            .map(|value| value.to_string())
+            .collect(),
+        restrict_path_args: config.restrict_path_args,
+        read_only_roots: config
+            .read_only_roots
+            .iter()
+            .map(|configured| ReadOnlyRootInfo {
+                configured: configured.clone(),
+                resolved: resolve_read_only_root(configured)
+                    .map(|path| path.to_string_lossy().replace('\\', "/")),
+            })
+            .collect(),
+    })
+}
 
-    if !target.exists() {
-        return Err("La ruta indicada no existe.".into());
-    }
+#[tauri::command]
+fn mcp_shell_capabilities() -> McpResult<ShellCapabilities> {
+    let config = load_config();
+    Ok(ShellCapabilities {
+        allowed_commands: ALLOWED_SHELL_COMMANDS
+            .iter()
+            .map(|value| value.to_string())
+            .collect(),
+        default_timeout_ms: DEFAULT_SHELL_TIMEOUT_MS,
+        command_timeouts: ALLOWED_SHELL_COMMANDS
+            .iter()
+            .map(|command| {
+                (
+                    command.to_string(),
+                    resolve_shell_timeout_ms(&config, command, None),
+                )
+            })
+            .collect(),
+        restrict_path_args: config.restrict_path_args,
+        command_aliases: config.command_aliases.into_iter().collect(),
+    })
+}
 
-    if !target.is_dir() {
-        return Err("La ruta indicada no es un directorio.".into());
+// Drains `pending` up to the last complete UTF-8 sequence, holding back a
+// trailing partial one until the next read completes it.
+fn take_complete_utf8(pending: &mut Vec<u8>) -> Option<String> {
+    let ready = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    if ready == 0 {
+        return None;
     }
+    let chunk: Vec<u8> = pending.drain(..ready).collect();
+    Some(String::from_utf8_lossy(&chunk).to_string())
+}
 
-    let mut entries = Vec::new();
+fn stream_job_output<R: Read>(
+    app: &tauri::AppHandle,
+    run_id: &str,
+    stream: &'static str,
+    mut reader: R,
+) {
+    let mut buffer = [0_u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+        let Some(data) = take_complete_utf8(&mut pending) else {
+            continue;
+        };
 
-    for entry in fs::read_dir(&target).map_err(|err| err.to_string())? {
-        let entry = entry.map_err(|err| err.to_string())?;
-        let metadata = entry.metadata().map_err(|err| err.to_string())?;
-        let entry_path = entry.path();
-        let entry_relative = relative_from_root(&root, &entry_path)?;
-        let modified = metadata.modified().ok().and_then(system_time_to_millis);
-
-        entries.push(FileEntry {
-            name: entry.file_name().to_string_lossy().to_string(),
-            path: entry_relative,
-            entry_type: if metadata.is_dir() {
-                "directory".to_string()
-            } else {
-                "file".to_string()
-            },
-            size: if metadata.is_file() {
-                metadata.len()
-            } else {
-                0
-            },
-            modified_at: modified,
-        });
+        let event = ShellOutputEvent {
+            run_id: run_id.to_string(),
+            stream,
+            data,
+        };
+        if let Err(err) = app.emit("shell://output", event) {
+            eprintln!("[shell] failed to emit output: {err}");
+        }
     }
+}
 
-    entries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+// Spawns `cmd` with piped output and starts forwarding it as `shell://output`.
+fn start_detached_child(
+    app: &tauri::AppHandle,
+    run_id: &str,
+    mut cmd: Command,
+) -> io::Result<(Child, Vec<thread::JoinHandle<()>>)> {
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    // Its own process group, so a kill reaches whatever the command spawned.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = cmd.spawn()?;
+
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|pipe| ("stdout", Box::new(pipe) as Box<dyn Read + Send>)),
+        child
+            .stderr
+            .take()
+            .map(|pipe| ("stderr", Box::new(pipe) as Box<dyn Read + Send>)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(stream, pipe)| {
+        let app = app.clone();
+        let run_id = run_id.to_string();
+        thread::spawn(move || stream_job_output(&app, &run_id, stream, pipe))
+    })
+    .collect();
 
-    Ok(ListResponse { entries })
+    Ok((child, readers))
 }
 
-#[tauri::command]
-fn mcp_files_read(path: String, encoding: Option<String>) -> McpResult<ReadResponse> {
-    let root = safe_root()?;
-    let target = build_path(&root, Some(path.as_str()))?;
-
-    if !target.exists() {
-        return Err("El archivo indicado no existe.".into());
+// Waits for the output forwarders. Something left in the group (a dev server
+// started by `npm run dev`) can hold the pipes open after the child exits; a
+// kill request meanwhile takes the rest of the group down so they close.
+fn join_job_readers(
+    readers: &mut Vec<thread::JoinHandle<()>>,
+    pgid: u32,
+    kill: Option<&AtomicBool>,
+) {
+    let mut group_killed = false;
+    for reader in readers.drain(..) {
+        while !reader.is_finished() {
+            if !group_killed && kill.is_some_and(|kill| kill.load(Ordering::SeqCst)) {
+                kill_process_group(pgid);
+                group_killed = true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = reader.join();
     }
+}
 
-    if !target.is_file() {
-        return Err("La ruta indicada no es un archivo.".into());
+// Sleeps between restarts, returning early (with `false`) if the job is killed meanwhile.
+fn wait_before_restart(kill: Option<&AtomicBool>) -> bool {
+    let deadline = Instant::now() + Duration::from_millis(DETACHED_RESTART_DELAY_MS);
+    while Instant::now() < deadline {
+        if kill.is_some_and(|kill| kill.load(Ordering::SeqCst)) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
     }
+    true
+}
 
-    let data = fs::read(&target).map_err(|err| err.to_string())?;
-    let encoding_pref = encoding.unwrap_or_else(|| "utf8".to_string());
-    let content = if encoding_pref.eq_ignore_ascii_case("base64") {
-        encode_base64(&data)
-    } else {
-        String::from_utf8(data).map_err(|_| {
-            "El archivo no está codificado como UTF-8. Usa encoding base64.".to_string()
-        })?
+// Starts the command built by `build` without a timeout and returns once it's
+// running. Output is emitted as `shell://output`, and `shell://exit` follows when
+// the process ends. With `max_restarts`, a child that exits unsuccessfully (and
+// wasn't killed) is respawned up to that many times, emitting `shell://restart`.
+fn spawn_detached(
+    app: tauri::AppHandle,
+    jobs: &JobRegistry,
+    build: Box<dyn Fn() -> Command + Send>,
+    run_id: String,
+    command_name: String,
+    max_restarts: Option<u32>,
+) -> McpResult<u32> {
+    jobs.reserve(&run_id, &command_name)?;
+    let (mut child, mut readers) = match start_detached_child(&app, &run_id, build()) {
+        Ok(started) => started,
+        Err(err) => {
+            jobs.finish(&run_id);
+            return Err(err.to_string());
+        }
     };
+    let pid = child.id();
+    jobs.attach_pid(&run_id, pid);
+    let kill = jobs.kill_flag(&run_id);
+
+    thread::spawn(move || {
+        let registry = app.state::<JobRegistry>();
+        let status = loop {
+            let status = match wait_for_child(&mut child, None, kill.as_deref()) {
+                Ok(Some(status)) => Some(status),
+                Ok(None) => {
+                    kill_child_tree(&mut child);
+                    child.wait().ok()
+                }
+                Err(_) => None,
+            };
+            join_job_readers(&mut readers, child.id(), kill.as_deref());
+
+            let killed = kill
+                .as_deref()
+                .is_some_and(|kill| kill.load(Ordering::SeqCst));
+            let failed = !status.is_some_and(|status| status.success());
+            let can_restart = max_restarts.is_some_and(|max| registry.restarts(&run_id) < max);
+            if killed || !failed || !can_restart || !wait_before_restart(kill.as_deref()) {
+                break status;
+            }
 
-    let relative = relative_from_root(&root, &target)?;
+            match start_detached_child(&app, &run_id, build()) {
+                Ok((next, next_readers)) => {
+                    child = next;
+                    readers = next_readers;
+                    let event = ShellRestartEvent {
+                        run_id: run_id.clone(),
+                        restarts: registry.record_restart(&run_id, child.id()),
+                        exit_code: status.and_then(|status| status.code()),
+                        pid: child.id(),
+                    };
+                    if let Err(err) = app.emit("shell://restart", event) {
+                        eprintln!("[shell] failed to emit restart: {err}");
+                    }
+                }
+                Err(err) => {
+                    eprintln!("[shell] failed to restart {run_id}: {err}");
+                    break status;
+                }
+            }
+        };
+        registry.finish(&run_id);
 
-    Ok(ReadResponse {
-        path: relative,
-        encoding: if encoding_pref.eq_ignore_ascii_case("base64") {
-            "base64".to_string()
-        } else {
-            "utf8".to_string()
+        let event = ShellExitEvent {
+            run_id,
+            exit_code: status.and_then(|status| status.code()),
+            killed: kill.is_some_and(|kill| kill.load(Ordering::SeqCst)),
+        };
+        if let Err(err) = app.emit("shell://exit", event) {
+            eprintln!("[shell] failed to emit exit: {err}");
+        }
+    });
+
+    Ok(pid)
+}
+
+fn stream_pty_output(app: tauri::AppHandle, session_id: String, mut reader: Box<dyn Read + Send>) {
+    let mut buffer = [0_u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+        let Some(data) = take_complete_utf8(&mut pending) else {
+            continue;
+        };
+
+        let event = PtyDataEvent {
+            session_id: session_id.clone(),
+            data,
+        };
+        if let Err(err) = app.emit("pty://data", event) {
+            eprintln!("[pty] failed to emit output: {err}");
+        }
+    }
+
+    let finished = app
+        .state::<PtyState>()
+        .sessions
+        .lock()
+        .ok()
+        .and_then(|mut sessions| sessions.remove(&session_id));
+    let exit_code = finished
+        .and_then(|mut session| session.child.wait().ok())
+        .map(|status| status.exit_code());
+
+    if let Err(err) = app.emit(
+        "pty://exit",
+        PtyExitEvent {
+            session_id,
+            exit_code,
         },
-        content,
-    })
+    ) {
+        eprintln!("[pty] failed to emit exit: {err}");
+    }
 }
 
 #[tauri::command]
-fn mcp_files_write(
-    path: String,
-    content: String,
-    encoding: Option<String>,
-    overwrite: Option<bool>,
-) -> McpResult<WriteResponse> {
-    let root = safe_root()?;
-    let target = build_path(&root, Some(path.as_str()))?;
+fn mcp_pty_spawn(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PtyState>,
+    command: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> McpResult<PtySpawnResponse> {
+    let (command, final_args) =
+        resolve_command_alias(&load_config(), &command, args.unwrap_or_default());
+    let (root, working_dir, _) = prepare_shell_exec(&command, &final_args, cwd.as_deref())?;
+
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: rows.unwrap_or(DEFAULT_PTY_ROWS),
+            cols: cols.unwrap_or(DEFAULT_PTY_COLS),
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|err| err.to_string())?;
 
-    if let Some(parent) = target.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
-        }
-    }
+    let mut cmd = CommandBuilder::new(&command);
+    cmd.args(&final_args);
+    cmd.cwd(&working_dir);
+    cmd.env("TERM", "xterm-256color");
 
-    let existed = target.exists();
-    if existed && !overwrite.unwrap_or(true) {
-        return Err("El archivo ya existe y overwrite=false.".into());
-    }
+    let child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|err| err.to_string())?;
+    // The child keeps its own handle; dropping ours lets the reader see EOF on exit.
+    drop(pair.slave);
 
-    let encoding_pref = encoding.unwrap_or_else(|| "utf8".to_string());
-    let bytes = if encoding_pref.eq_ignore_ascii_case("base64") {
-        let payload = decode_base64(&content)?;
-        fs::write(&target, &payload).map_err(|err| err.to_string())?;
-        payload.len()
-    } else {
-        fs::write(&target, content.as_bytes()).map_err(|err| err.to_string())?;
-        content.as_bytes().len()
-    };
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|err| err.to_string())?;
+    let writer = pair.master.take_writer().map_err(|err| err.to_string())?;
+
+    let session_id = format!("pty-{}", state.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+    let pid = child.process_id();
+
+    state
+        .sessions
+        .lock()
+        .map_err(|err| err.to_string())?
+        .insert(
+            session_id.clone(),
+            PtySession {
+                master: pair.master,
+                writer,
+                child,
+            },
+        );
 
-    let relative = relative_from_root(&root, &target)?;
+    let stream_id = session_id.clone();
+    thread::spawn(move || stream_pty_output(app, stream_id, reader));
 
-    Ok(WriteResponse {
-        path: relative,
-        bytes,
-        created: !existed,
+    Ok(PtySpawnResponse {
+        session_id,
+        command,
+        args: final_args,
+        cwd: relative_from_root(&root, &working_dir).ok(),
+        pid,
     })
 }
 
 #[tauri::command]
-fn mcp_files_info() -> McpResult<FilesInfoResponse> {
-    let root = safe_root()?;
-    Ok(FilesInfoResponse {
-        root: root.to_string_lossy().replace('\\', "/"),
-        exists: true,
-    })
+fn mcp_pty_write(
+    state: tauri::State<'_, PtyState>,
+    session_id: String,
+    data: String,
+) -> McpResult<()> {
+    let mut sessions = state.sessions.lock().map_err(|err| err.to_string())?;
+    let session = sessions
+        .get_mut(&session_id)
+        .ok_or_else(|| "No se encontró la sesión de terminal indicada.".to_string())?;
+
+    session
+        .writer
+        .write_all(data.as_bytes())
+        .map_err(|err| err.to_string())?;
+    session.writer.flush().map_err(|err| err.to_string())
 }
 
 #[tauri::command]
-fn mcp_git_exec(
-    command: String,
-    args: Option<Vec<String>>,
-    cwd: Option<String>,
-    env: Option<HashMap<String, String>>,
-    _timeout_ms: Option<u64>,
-) -> McpResult<ExecResponse> {
-    if command != "git" && command != "git.exe" {
-        return Err("Solo se permite ejecutar el comando git desde este servidor.".into());
-    }
-
-    let root = safe_root()?;
-    let final_args = args.unwrap_or_default();
-    if final_args.is_empty() {
-        return Err("Debes especificar un subcomando de git.".into());
-    }
-
-    let subcommand = final_args[0].to_lowercase();
-    if BLOCKED_GIT_SUBCOMMANDS
-        .iter()
-        .any(|blocked| blocked.eq_ignore_ascii_case(subcommand.as_str()))
-    {
-        return Err("Operaciones remotas de git están deshabilitadas en modo offline.".into());
+fn mcp_pty_resize(
+    state: tauri::State<'_, PtyState>,
+    session_id: String,
+    cols: u16,
+    rows: u16,
+) -> McpResult<()> {
+    if cols == 0 || rows == 0 {
+        return Err("El tamaño de la terminal debe ser mayor que cero.".into());
     }
 
-    let working_dir = if let Some(ref dir) = cwd {
-        build_path(&root, Some(dir.as_str()))?
-    } else {
-        root.clone()
-    };
+    let sessions = state.sessions.lock().map_err(|err| err.to_string())?;
+    let session = sessions
+        .get(&session_id)
+        .ok_or_else(|| "No se encontró la sesión de terminal indicada.".to_string())?;
+
+    session
+        .master
+        .resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|err| err.to_string())
+}
 
-    if !working_dir.exists() {
-        return Err("El directorio indicado para git no existe.".into());
+#[tauri::command]
+fn mcp_pty_kill(state: tauri::State<'_, PtyState>, session_id: String) -> McpResult<()> {
+    let mut session = state
+        .sessions
+        .lock()
+        .map_err(|err| err.to_string())?
+        .remove(&session_id)
+        .ok_or_else(|| "No se encontró la sesión de terminal indicada.".to_string())?;
+
+    if let Err(err) = session.child.kill() {
+        eprintln!("[pty] failed to kill session {}: {}", session_id, err);
     }
+    let _ = session.child.wait();
+    Ok(())
+}
 
-    let mut cmd = Command::new(command);
-    cmd.args(&final_args);
-    cmd.current_dir(&working_dir);
-
-    if let Some(env_vars) = env {
-        for (key, value) in env_vars {
-            cmd.env(key, value);
+// /proc/mounts escapes space, tab, newline and backslash as `\ooo` octal.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 4)
+            .filter(|_| bytes[index] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        if let Some(value) = escaped {
+            output.push(value);
+            index += 4;
+            continue;
         }
+        output.push(bytes[index]);
+        index += 1;
     }
+    String::from_utf8_lossy(&output).into_owned()
+}
 
-    spawn_command(cmd, "git".to_string(), final_args, Some(working_dir))
+fn parse_proc_mounts(contents: &str, include_pseudo: bool) -> Vec<MountInfo> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            let options = fields.next().unwrap_or_default();
+            if !include_pseudo && PSEUDO_FS_TYPES.contains(&fs_type) {
+                return None;
+            }
+            Some(MountInfo {
+                device: unescape_mount_field(device),
+                mount_point: unescape_mount_field(mount_point),
+                fs_type: fs_type.to_string(),
+                options: options.split(',').map(str::to_string).collect(),
+            })
+        })
+        .collect()
 }
 
 #[tauri::command]
-fn mcp_git_info() -> McpResult<GitInfoResponse> {
-    let root = safe_root()?;
-    let mut cmd = Command::new("git");
-    cmd.arg("--version");
-    let version = cmd
-        .output()
-        .ok()
-        .and_then(|output| String::from_utf8(output.stdout).ok())
-        .map(|out| out.trim().to_string());
+fn mcp_system_mounts(include_pseudo: Option<bool>) -> McpResult<Vec<MountInfo>> {
+    if !cfg!(target_os = "linux") {
+        return Ok(Vec::new());
+    }
 
-    Ok(GitInfoResponse {
-        version,
-        root: root.to_string_lossy().replace('\\', "/"),
-    })
+    let contents = fs::read_to_string("/proc/mounts").map_err(|err| err.to_string())?;
+    Ok(parse_proc_mounts(
+        &contents,
+        include_pseudo.unwrap_or(false),
+    ))
 }
 
-fn is_shell_command_allowed(command: &str) -> bool {
-    ALLOWED_SHELL_COMMANDS
-        .iter()
-        .any(|allowed| allowed.eq_ignore_ascii_case(command))
+#[tauri::command]
+fn mcp_app_info() -> AppInfoResponse {
+    // Both values are injected by build.rs; an empty commit means git wasn't available.
+    let commit = env!("CEREBRO_GIT_COMMIT");
+    AppInfoResponse {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        build_timestamp: env!("CEREBRO_BUILD_TIMESTAMP").parse().ok(),
+        git_commit: (!commit.is_empty()).then(|| commit.to_string()),
+    }
 }
 
-fn has_disallowed_tokens(values: &[String]) -> bool {
-    values
-        .iter()
-        .any(|value| value.contains('&') || value.contains('|') || value.contains(';'))
+#[tauri::command]
+fn mcp_system_info() -> McpResult<SystemInfoResponse> {
+    collect_system_info()
 }
 
 #[tauri::command]
-fn mcp_shell_exec(
-    command: String,
-    args: Option<Vec<String>>,
-    cwd: Option<String>,
-    env: Option<HashMap<String, String>>,
-    _timeout_ms: Option<u64>,
-) -> McpResult<ExecResponse> {
-    if !is_shell_command_allowed(&command) {
-        return Err("Comando no permitido por la política de seguridad.".into());
+fn mcp_system_info_export(destination: String) -> McpResult<WriteResponse> {
+    let root = safe_root()?;
+    let target = build_path(&root, Some(destination.as_str()))?;
+    if target.is_dir() {
+        return Err("La ruta de destino es un directorio.".into());
     }
 
-    let final_args = args.unwrap_or_default();
-    if has_disallowed_tokens(&final_args) {
-        return Err("El comando contiene operadores no permitidos.".into());
+    let snapshot = collect_system_info()?;
+    let mut payload = serde_json::to_vec_pretty(&snapshot).map_err(|err| err.to_string())?;
+    payload.push(b'\n');
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
     }
 
+    let existed = target.exists();
+    fs::write(&target, &payload).map_err(|err| err.to_string())?;
+
+    Ok(WriteResponse {
+        path: relative_from_root(&root, &target)?,
+        bytes: payload.len(),
+        created: !existed,
+        backup: None,
+        sha256: None,
+        mode: None,
+    })
+}
+
+#[tauri::command]
+fn mcp_support_report(app: tauri::AppHandle, destination: String) -> McpResult<String> {
     let root = safe_root()?;
-    let working_dir = if let Some(ref dir) = cwd {
-        build_path(&root, Some(dir.as_str()))?
-    } else {
-        root.clone()
+    let target = build_path(&root, Some(destination.as_str()))?;
+    if target.is_dir() {
+        return Err("La ruta de destino es un directorio.".into());
+    }
+
+    let report = SupportReport {
+        generated_at_ms: current_timestamp_ms(),
+        app: mcp_app_info(),
+        system: collect_system_info()?,
+        security_policy: mcp_security_policy()?,
+        recent_logs: mcp_app_logs(app, Some(SUPPORT_REPORT_LOG_LINES))?,
+        metrics: summarize_metrics(&mcp_metrics_tail(Some(SUPPORT_REPORT_METRICS))?),
     };
+    let hostname = report.system.hostname.clone();
+    let mut value = serde_json::to_value(&report).map_err(|err| err.to_string())?;
+    let redactions = support_report_redactions(hostname);
+    let assignments = secret_assignment_pattern()?;
+    redact_report_value(&mut value, &redactions, &assignments);
 
-    if !working_dir.exists() {
-        return Err("El directorio indicado no existe.".into());
+    let mut payload = serde_json::to_vec_pretty(&value).map_err(|err| err.to_string())?;
+    payload.push(b'\n');
+
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
     }
+    write_file_atomic(&target, &payload)?;
 
-    let mut cmd = Command::new(&command);
-    cmd.args(&final_args);
-    cmd.current_dir(&working_dir);
+    relative_from_root(&root, &target)
+}
 
-    if let Some(env_vars) = env {
-        for (key, value) in env_vars {
-            cmd.env(key, value);
+fn summarize_metrics(entries: &[MetricsEntry]) -> MetricsSummary {
+    let mut summary = MetricsSummary {
+        entries: entries.len(),
+        failures: 0,
+        first_ts: entries.iter().map(|entry| entry.ts).min(),
+        last_ts: entries.iter().map(|entry| entry.ts).max(),
+        avg_latency_ms: None,
+        prompt_tokens: 0,
+        output_tokens: 0,
+        by_provider: BTreeMap::new(),
+        by_mode: BTreeMap::new(),
+    };
+    let mut latency_total = 0_u64;
+    let mut latency_count = 0_u64;
+    for entry in entries {
+        if !entry.success {
+            summary.failures += 1;
+        }
+        if let Some(latency) = entry.latency_ms {
+            latency_total = latency_total.saturating_add(latency);
+            latency_count += 1;
         }
+        summary.prompt_tokens += u64::from(entry.prompt_tokens.unwrap_or(0));
+        summary.output_tokens += u64::from(entry.output_tokens.unwrap_or(0));
+        *summary
+            .by_provider
+            .entry(entry.provider.clone())
+            .or_default() += 1;
+        *summary.by_mode.entry(entry.mode.clone()).or_default() += 1;
     }
+    summary.avg_latency_ms = latency_total.checked_div(latency_count);
+    summary
+}
 
-    spawn_command(cmd, command, final_args, Some(working_dir))
+// Identifying strings paired with their placeholder, longest first so the home
+// directory is replaced before the bare username inside it.
+fn support_report_redactions(hostname: Option<String>) -> Vec<(String, &'static str)> {
+    let mut redactions: Vec<(String, &'static str)> = Vec::new();
+    if let Ok(home) = resolve_home_dir() {
+        let home = home.to_string_lossy().to_string();
+        redactions.push((home.replace('\\', "/"), "~"));
+        redactions.push((home, "~"));
+    }
+    for key in ["USER", "USERNAME", "LOGNAME"] {
+        if let Ok(user) = env::var(key) {
+            redactions.push((user, "<user>"));
+        }
+    }
+    if let Some(hostname) = hostname {
+        redactions.push((hostname, "<host>"));
+    }
+    // Very short values would match inside unrelated words.
+    redactions.retain(|(needle, _)| needle.trim().len() >= 2);
+    redactions.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+    redactions.dedup_by(|a, b| a.0 == b.0);
+    redactions
 }
 
-#[tauri::command]
-fn mcp_shell_capabilities() -> McpResult<ShellCapabilities> {
-    Ok(ShellCapabilities {
-        allowed_commands: ALLOWED_SHELL_COMMANDS
-            .iter()
-            .map(|value| value.to_string())
-            .collect(),
-        default_timeout_ms: DEFAULT_SHELL_TIMEOUT_MS,
-    })
+// Matches `API_TOKEN=abc` or `password: "abc"` inside free text such as log messages.
+fn secret_assignment_pattern() -> McpResult<Regex> {
+    let markers = SECRET_KEY_MARKERS
+        .iter()
+        .map(|marker| regex::escape(marker))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&format!(
+        r#"(?i)\b([a-z0-9_.-]*(?:{markers})[a-z0-9_.-]*)(\s*[=:]\s*)("[^"]*"|'[^']*'|[^\s,;]+)"#
+    ))
+    .map_err(|err| err.to_string())
 }
 
-#[tauri::command]
-fn mcp_system_info() -> McpResult<SystemInfoResponse> {
+fn redact_report_value(
+    value: &mut serde_json::Value,
+    redactions: &[(String, &'static str)],
+    assignments: &Regex,
+) {
+    match value {
+        serde_json::Value::String(text) => {
+            if assignments.is_match(text) {
+                *text = assignments
+                    .replace_all(text, format!("${{1}}${{2}}{REDACTED_VALUE}").as_str())
+                    .into_owned();
+            }
+            for (needle, placeholder) in redactions {
+                if text.contains(needle.as_str()) {
+                    *text = text.replace(needle.as_str(), placeholder);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_report_value(item, redactions, assignments);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                // Only string values can carry a secret; counters like `promptTokens` stay.
+                if is_secret_env_key(key) && item.is_string() {
+                    *item = serde_json::Value::String(REDACTED_VALUE.to_string());
+                } else {
+                    redact_report_value(item, redactions, assignments);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_system_info() -> McpResult<SystemInfoResponse> {
     let timestamp_ms = current_timestamp_ms();
     let hostname = read_hostname();
 
@@ -930,6 +10797,92 @@ fn mcp_system_info() -> McpResult<SystemInfoResponse> {
     })
 }
 
+// Expects the CPU to have been refreshed once before, so usage covers the time
+// since that refresh.
+fn sample_resources(system: &mut System) -> ResourceSample {
+    system.refresh_cpu();
+    system.refresh_memory();
+    system.refresh_processes();
+
+    let cpus = system.cpus();
+    let cpu_usage = (!cpus.is_empty())
+        .then(|| cpus.iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() / cpus.len() as f32);
+
+    let mut memory_total = system.total_memory().saturating_mul(1024);
+    let mut memory_available = system.available_memory().saturating_mul(1024);
+    let mut swap_used = system.used_swap().saturating_mul(1024);
+    if memory_total == 0 {
+        if let Some((total, free, available, swap_total, swap_free)) = read_linux_meminfo() {
+            memory_total = total;
+            memory_available = if available > 0 { available } else { free };
+            swap_used = swap_total.saturating_sub(swap_free);
+        }
+    }
+
+    let process_count = match system.processes().len() {
+        0 => count_linux_processes(),
+        count => Some(count),
+    };
+
+    ResourceSample {
+        timestamp_ms: current_timestamp_ms(),
+        memory_used: memory_total.saturating_sub(memory_available),
+        memory_available,
+        swap_used,
+        cpu_usage,
+        process_count,
+    }
+}
+
+fn signed_delta(start: u64, end: u64) -> i64 {
+    (i128::from(end) - i128::from(start)).clamp(i64::MIN.into(), i64::MAX.into()) as i64
+}
+
+#[tauri::command(async)]
+fn mcp_system_delta(interval_ms: Option<u64>) -> McpResult<SystemDeltaResponse> {
+    let interval_ms = interval_ms
+        .unwrap_or(1_000)
+        .clamp(MIN_SYSTEM_DELTA_MS, MAX_SYSTEM_DELTA_MS);
+
+    // Sampling runs on its own thread so a slow refresh can't stretch the call
+    // much past the requested interval.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut system = System::new();
+        system.refresh_cpu();
+        thread::sleep(Duration::from_millis(MIN_SYSTEM_DELTA_MS));
+        let start = sample_resources(&mut system);
+        thread::sleep(Duration::from_millis(interval_ms));
+        let end = sample_resources(&mut system);
+        let _ = sender.send((start, end));
+    });
+    let wait = Duration::from_millis(MIN_SYSTEM_DELTA_MS + interval_ms + SYSTEM_DELTA_SLACK_MS);
+    let (start, end) = receiver
+        .recv_timeout(wait)
+        .map_err(|_| "No se pudieron muestrear los recursos del sistema a tiempo.".to_string())?;
+
+    let delta = ResourceDelta {
+        memory_used: signed_delta(start.memory_used, end.memory_used),
+        memory_available: signed_delta(start.memory_available, end.memory_available),
+        swap_used: signed_delta(start.swap_used, end.swap_used),
+        cpu_usage: start
+            .cpu_usage
+            .zip(end.cpu_usage)
+            .map(|(start, end)| end - start),
+        process_count: start
+            .process_count
+            .zip(end.process_count)
+            .map(|(start, end)| signed_delta(start as u64, end as u64)),
+    };
+
+    Ok(SystemDeltaResponse {
+        interval_ms,
+        start,
+        end,
+        delta,
+    })
+}
+
 #[tauri::command]
 fn mcp_system_paths() -> McpResult<SystemPathsResponse> {
     let home = resolve_home_dir()?;
@@ -941,9 +10894,24 @@ fn mcp_system_paths() -> McpResult<SystemPathsResponse> {
 }
 
 #[tauri::command]
-fn mcp_metrics_append(entry: MetricsEntry) -> McpResult<()> {
+fn mcp_metrics_append(
+    app: tauri::AppHandle,
+    subscribers: tauri::State<'_, MetricsSubscribers>,
+    entry: MetricsEntry,
+) -> McpResult<()> {
     let path = metrics_log_path()?;
-    rotate_metrics_log_if_needed(&path)?;
+    if let Some(archive) = rotate_metrics_log_if_needed(&path)? {
+        let rotated = MetricsRotatedEvent {
+            file: archive
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: fs::metadata(&archive).map(|meta| meta.len()).unwrap_or(0),
+        };
+        if let Err(error) = app.emit("metrics://rotated", rotated) {
+            eprintln!("[metrics] failed to emit rotation: {error}");
+        }
+    }
     let line = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
     let mut file = fs::OpenOptions::new()
         .create(true)
@@ -953,9 +10921,124 @@ fn mcp_metrics_append(entry: MetricsEntry) -> McpResult<()> {
     file.write_all(line.as_bytes())
         .map_err(|err| err.to_string())?;
     file.write_all(b"\n").map_err(|err| err.to_string())?;
+
+    if subscribers.count.load(Ordering::SeqCst) > 0 {
+        if let Err(error) = app.emit("metrics://entry", entry) {
+            eprintln!("[metrics] failed to emit entry: {error}");
+        }
+    }
     Ok(())
 }
 
+#[tauri::command]
+fn mcp_metrics_subscribe(subscribers: tauri::State<'_, MetricsSubscribers>) -> McpResult<usize> {
+    Ok(subscribers.count.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+#[tauri::command]
+fn mcp_metrics_unsubscribe(subscribers: tauri::State<'_, MetricsSubscribers>) -> McpResult<usize> {
+    let previous = subscribers
+        .count
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+            Some(count.saturating_sub(1))
+        })
+        .unwrap_or(0);
+    Ok(previous.saturating_sub(1))
+}
+
+fn newest_log_file(directory: &Path) -> Option<PathBuf> {
+    fs::read_dir(directory)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            entry.path().extension().is_some_and(|ext| ext == "log")
+                && entry.file_type().is_ok_and(|kind| kind.is_file())
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|meta| meta.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+// Parses the `[date][time][target][LEVEL] message` layout used by
+// tauri-plugin-log.
+fn parse_app_log_line(line: &str) -> Option<AppLogEntry> {
+    let mut fields = Vec::with_capacity(4);
+    let mut rest = line;
+    while fields.len() < 4 {
+        let inner = rest.strip_prefix('[')?;
+        let (field, after) = inner.split_once(']')?;
+        fields.push(field);
+        rest = after;
+    }
+    Some(AppLogEntry {
+        timestamp: Some(format!("{} {}", fields[0], fields[1])),
+        target: Some(fields[2].to_string()),
+        level: Some(fields[3].to_string()),
+        message: rest.trim_start().to_string(),
+    })
+}
+
+#[tauri::command]
+fn mcp_app_logs(app: tauri::AppHandle, limit: Option<usize>) -> McpResult<AppLogsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_APP_LOG_LINES)
+        .clamp(1, MAX_APP_LOG_LINES);
+    // Fixed to the app's own log directory; nothing from the caller reaches the path.
+    let directory = app.path().app_log_dir().map_err(|err| err.to_string())?;
+    let Some(file_path) = newest_log_file(&directory) else {
+        return Ok(AppLogsResponse {
+            file: None,
+            entries: Vec::new(),
+            truncated: false,
+        });
+    };
+
+    let mut file = fs::File::open(&file_path).map_err(|err| err.to_string())?;
+    let length = file.metadata().map_err(|err| err.to_string())?.len();
+    let start = length.saturating_sub(MAX_APP_LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))
+        .map_err(|err| err.to_string())?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let text = String::from_utf8_lossy(&buffer);
+    let mut lines: Vec<&str> = text.lines().collect();
+    if start > 0 && !lines.is_empty() {
+        // The first line is probably cut mid-way.
+        lines.remove(0);
+    }
+
+    let mut entries: Vec<AppLogEntry> = Vec::new();
+    for line in lines {
+        match parse_app_log_line(line) {
+            Some(entry) => entries.push(entry),
+            // Unstructured lines are continuations of a multi-line message.
+            None => match entries.last_mut() {
+                Some(previous) => {
+                    previous.message.push('\n');
+                    previous.message.push_str(line);
+                }
+                None => entries.push(AppLogEntry {
+                    timestamp: None,
+                    level: None,
+                    target: None,
+                    message: line.to_string(),
+                }),
+            },
+        }
+    }
+
+    let truncated = start > 0 || entries.len() > limit;
+    if entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+
+    Ok(AppLogsResponse {
+        file: Some(file_path.to_string_lossy().replace('\\', "/")),
+        entries,
+        truncated,
+    })
+}
+
 #[tauri::command]
 fn mcp_metrics_tail(limit: Option<usize>) -> McpResult<Vec<MetricsEntry>> {
     let path = metrics_log_path()?;
@@ -991,6 +11074,181 @@ fn mcp_metrics_tail(limit: Option<usize>) -> McpResult<Vec<MetricsEntry>> {
     Ok(entries)
 }
 
+#[tauri::command]
+fn mcp_metrics_range(start_ms: u64, end_ms: u64) -> McpResult<Vec<MetricsEntry>> {
+    if start_ms > end_ms {
+        return Err("El inicio del intervalo no puede ser posterior al final.".into());
+    }
+
+    let path = metrics_log_path()?;
+    let directory = path
+        .parent()
+        .ok_or_else(|| "No se pudo resolver el directorio de métricas.".to_string())?;
+
+    // An archive holds the entries written between the previous rotation and its own.
+    let mut sources = Vec::new();
+    let mut previous_rotation_ms = 0_u64;
+    for (seconds, _, archive) in rotated_metrics_logs(directory) {
+        let rotated_ms = seconds.saturating_mul(1000);
+        if rotated_ms >= start_ms && previous_rotation_ms <= end_ms {
+            sources.push(archive);
+        }
+        previous_rotation_ms = rotated_ms;
+    }
+    if path.exists() && previous_rotation_ms <= end_ms {
+        sources.push(path);
+    }
+
+    let mut entries = Vec::new();
+    'files: for source in sources {
+        let file = fs::File::open(&source).map_err(|err| err.to_string())?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| err.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<MetricsEntry>(&line) {
+                Ok(entry) if entry.ts >= start_ms && entry.ts <= end_ms => {
+                    if entries.len() >= MAX_METRICS_RANGE_ENTRIES {
+                        eprintln!("[metrics] range capped at {MAX_METRICS_RANGE_ENTRIES} entries");
+                        break 'files;
+                    }
+                    entries.push(entry);
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("[metrics] failed to parse entry: {error}");
+                }
+            }
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.ts);
+    Ok(entries)
+}
+
+// Timestamps of the first and last parseable entries; only the head and tail
+// of the file are read.
+fn metrics_time_range(path: &Path) -> (Option<u64>, Option<u64>) {
+    let parse = |line: &str| serde_json::from_str::<MetricsEntry>(line).ok();
+    let Ok(file) = fs::File::open(path) else {
+        return (None, None);
+    };
+    let first = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| parse(&line))
+        .map(|entry| entry.ts);
+
+    let last = fs::File::open(path)
+        .and_then(|mut file| read_tail_lines(&mut file, 16, METRICS_RANGE_TAIL_BYTES))
+        .ok()
+        .and_then(|(lines, _)| lines.iter().rev().find_map(|(_, line)| parse(line)))
+        .map(|entry| entry.ts);
+    (first, last)
+}
+
+#[tauri::command]
+fn mcp_metrics_files() -> McpResult<Vec<MetricsFileInfo>> {
+    let path = metrics_log_path()?;
+    let mut sources: Vec<(PathBuf, Option<u64>)> = match path.parent() {
+        Some(directory) => rotated_metrics_logs(directory)
+            .into_iter()
+            .map(|(seconds, _, archive)| (archive, Some(seconds.saturating_mul(1000))))
+            .collect(),
+        None => Vec::new(),
+    };
+    if path.is_file() {
+        sources.push((path.clone(), None));
+    }
+
+    let mut files = Vec::with_capacity(sources.len());
+    for (source, rotated_at_ms) in sources {
+        let Ok(metadata) = fs::metadata(&source) else {
+            continue;
+        };
+        let (first_ts, last_ts) = metrics_time_range(&source);
+        files.push(MetricsFileInfo {
+            name: source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            current: source == path,
+            size: metadata.len(),
+            rotated_at_ms,
+            first_ts,
+            last_ts,
+        });
+    }
+    Ok(files)
+}
+
+#[tauri::command]
+fn mcp_metrics_repair(keep_invalid: Option<bool>) -> McpResult<MetricsRepairResponse> {
+    let path = metrics_log_path()?;
+    if !path.exists() {
+        return Ok(MetricsRepairResponse {
+            valid: 0,
+            invalid: 0,
+            rewritten: false,
+            sidecar: None,
+        });
+    }
+
+    let contents = fs::read(&path).map_err(|err| err.to_string())?;
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    // Split on raw bytes: a line cut mid-write may not even be valid UTF-8.
+    for line in contents.split(|byte| *byte == b'\n') {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if serde_json::from_slice::<MetricsEntry>(line).is_ok() {
+            valid.push(line);
+        } else {
+            invalid.push(line);
+        }
+    }
+
+    if invalid.is_empty() {
+        return Ok(MetricsRepairResponse {
+            valid: valid.len(),
+            invalid: 0,
+            rewritten: false,
+            sidecar: None,
+        });
+    }
+
+    let mut sidecar = None;
+    if keep_invalid.unwrap_or(true) {
+        let corrupt_path = path.with_extension("jsonl.corrupt");
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&corrupt_path)
+            .map_err(|err| err.to_string())?;
+        for line in &invalid {
+            file.write_all(line).map_err(|err| err.to_string())?;
+            file.write_all(b"\n").map_err(|err| err.to_string())?;
+        }
+        sidecar = Some(corrupt_path.to_string_lossy().replace('\\', "/"));
+    }
+
+    let mut payload = Vec::with_capacity(contents.len());
+    for line in &valid {
+        payload.extend_from_slice(line);
+        payload.push(b'\n');
+    }
+    write_file_atomic(&path, &payload)?;
+
+    Ok(MetricsRepairResponse {
+        valid: valid.len(),
+        invalid: invalid.len(),
+        rewritten: true,
+        sidecar,
+    })
+}
+
 #[tauri::command]
 fn mcp_metrics_clear() -> McpResult<()> {
     let path = metrics_log_path()?;
@@ -1024,9 +11282,229 @@ fn mcp_metrics_clear() -> McpResult<()> {
     Ok(())
 }
 
+fn random_bytes<const N: usize>() -> McpResult<[u8; N]> {
+    let mut bytes = [0_u8; N];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| format!("No se pudo obtener aleatoriedad del sistema: {err}"))?;
+    Ok(bytes)
+}
+
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn uuid_v4() -> McpResult<String> {
+    let mut bytes = random_bytes::<16>()?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Ok(format_uuid(&bytes))
+}
+
+fn uuid_v7(state: &IdState) -> McpResult<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let (millis, counter) = {
+        let mut last = state.last_v7.lock().map_err(|err| err.to_string())?;
+        let next = if now > last.0 {
+            (now, 0)
+        } else if last.1 < 0x0fff {
+            (last.0, last.1 + 1)
+        } else {
+            // Counter exhausted: borrow the next millisecond.
+            (last.0 + 1, 0)
+        };
+        *last = next;
+        next
+    };
+
+    let mut bytes = random_bytes::<16>()?;
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6] = 0x70 | ((counter >> 8) as u8 & 0x0f);
+    bytes[7] = counter as u8;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Ok(format_uuid(&bytes))
+}
+
+#[tauri::command]
+fn mcp_util_uuid(state: tauri::State<'_, IdState>, version: Option<u8>) -> McpResult<String> {
+    match version.unwrap_or(4) {
+        4 => uuid_v4(),
+        7 => uuid_v7(state.inner()),
+        _ => Err("Versión de UUID no soportada. Usa 4 o 7.".into()),
+    }
+}
+
+#[tauri::command]
+fn mcp_util_nanoid(size: Option<usize>) -> McpResult<String> {
+    let size = size.unwrap_or(DEFAULT_NANOID_SIZE);
+    if size == 0 || size > MAX_NANOID_SIZE {
+        return Err(format!(
+            "El tamaño debe estar entre 1 y {MAX_NANOID_SIZE} caracteres."
+        ));
+    }
+
+    let mut bytes = vec![0_u8; size];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| format!("No se pudo obtener aleatoriedad del sistema: {err}"))?;
+    // 64-symbol alphabet, so masking to 6 bits keeps the distribution uniform.
+    Ok(bytes
+        .iter()
+        .map(|byte| NANOID_ALPHABET[(byte & 0x3f) as usize] as char)
+        .collect())
+}
+
+#[tauri::command]
+fn mcp_util_hex_encode(data_base64: String) -> McpResult<String> {
+    let bytes = decode_base64(&data_base64)?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+// Returns the bytes as base64 so arbitrary binary survives the IPC boundary.
+// Whitespace is ignored, so `xxd -p` style wrapped output decodes as is.
+#[tauri::command]
+fn mcp_util_hex_decode(hex: String) -> McpResult<String> {
+    let digits: Vec<u8> = hex
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("La cadena hexadecimal debe tener un número par de dígitos.".into());
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for (index, pair) in digits.chunks(2).enumerate() {
+        let text = std::str::from_utf8(pair).unwrap_or_default();
+        let byte = u8::from_str_radix(text, 16)
+            .ok()
+            .filter(|_| pair.iter().all(u8::is_ascii_hexdigit))
+            .ok_or_else(|| {
+                format!(
+                    "Dígito hexadecimal inválido en la posición {}.",
+                    index * 2 + 1
+                )
+            })?;
+        bytes.push(byte);
+    }
+    Ok(encode_base64(&bytes))
+}
+
+fn render_qr_png(code: &qrcode::QrCode) -> McpResult<(Vec<u8>, usize)> {
+    let modules = code.width();
+    let side = (modules + QR_QUIET_ZONE * 2) * QR_MODULE_PIXELS;
+    let colors = code.to_colors();
+    let mut pixels = vec![0xff_u8; side * side];
+    for (index, color) in colors.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let x0 = (index % modules + QR_QUIET_ZONE) * QR_MODULE_PIXELS;
+        let y0 = (index / modules + QR_QUIET_ZONE) * QR_MODULE_PIXELS;
+        for y in y0..y0 + QR_MODULE_PIXELS {
+            pixels[y * side + x0..y * side + x0 + QR_MODULE_PIXELS].fill(0);
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok((output, side))
+}
+
 #[tauri::command]
+fn mcp_util_qrcode(data: String, destination: Option<String>) -> McpResult<QrCodeResponse> {
+    if data.is_empty() {
+        return Err("No hay datos que codificar.".into());
+    }
+
+    let code = qrcode::QrCode::new(data.as_bytes()).map_err(|err| match err {
+        qrcode::types::QrError::DataTooLong => format!(
+            "Los datos ({} bytes) superan la capacidad máxima de un código QR.",
+            data.len()
+        ),
+        other => format!("No se pudo generar el código QR: {other}"),
+    })?;
+    let (png_bytes, width) = render_qr_png(&code)?;
+
+    let Some(destination) = destination else {
+        return Ok(QrCodeResponse {
+            modules: code.width(),
+            width,
+            bytes: png_bytes.len(),
+            data_url: Some(format!(
+                "data:image/png;base64,{}",
+                encode_base64(&png_bytes)
+            )),
+            path: None,
+        });
+    };
+
+    let root = safe_root()?;
+    let target = build_path(&root, Some(destination.as_str()))?;
+    if target.is_dir() {
+        return Err("La ruta de destino es un directorio.".into());
+    }
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+    fs::write(&target, &png_bytes).map_err(|err| err.to_string())?;
+
+    Ok(QrCodeResponse {
+        modules: code.width(),
+        width,
+        bytes: png_bytes.len(),
+        data_url: None,
+        path: Some(relative_from_root(&root, &target)?),
+    })
+}
+
+// Percent-encodes everything except RFC 3986 unreserved characters.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn project_window_label(name: &str) -> McpResult<String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_'));
+    if !valid {
+        return Err("La etiqueta de la ventana solo admite letras, números, - y _.".into());
+    }
+    Ok(format!("{PROJECT_WINDOW_PREFIX}{name}"))
+}
+
+// Runs off the main thread: building a window from a sync command deadlocks on Windows.
+#[tauri::command(async)]
 fn mcp_tauri_exec(
     app: tauri::AppHandle,
+    zoom: tauri::State<'_, ZoomState>,
+    windows: tauri::State<'_, WindowRegistry>,
     command: String,
     args: Option<Vec<String>>,
 ) -> McpResult<ExecResponse> {
@@ -1091,6 +11569,165 @@ fn mcp_tauri_exec(
                 Err("No se encontró la ventana principal.".into())
             }
         }
+        "set-title" => {
+            let title = collected_args.join(" ");
+            if title.trim().is_empty() {
+                return Err("El título de la ventana no puede estar vacío.".into());
+            }
+            if let Some(window) = app.get_webview_window("main") {
+                window.set_title(&title).map_err(|err| err.to_string())?;
+                Ok(ExecResponse {
+                    command,
+                    args: collected_args,
+                    cwd: None,
+                    exit_code: 0,
+                    stdout: title,
+                    stderr: String::new(),
+                    duration_ms: start.elapsed().as_millis(),
+                })
+            } else {
+                Err("No se encontró la ventana principal.".into())
+            }
+        }
+        "get-title" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let title = window.title().map_err(|err| err.to_string())?;
+                Ok(ExecResponse {
+                    command,
+                    args: collected_args,
+                    cwd: None,
+                    exit_code: 0,
+                    stdout: title,
+                    stderr: String::new(),
+                    duration_ms: start.elapsed().as_millis(),
+                })
+            } else {
+                Err("No se encontró la ventana principal.".into())
+            }
+        }
+        "get-zoom" => {
+            if app.get_webview_window("main").is_some() {
+                let factor = *zoom.factor.lock().map_err(|err| err.to_string())?;
+                Ok(ExecResponse {
+                    command,
+                    args: collected_args,
+                    cwd: None,
+                    exit_code: 0,
+                    stdout: factor.to_string(),
+                    stderr: String::new(),
+                    duration_ms: start.elapsed().as_millis(),
+                })
+            } else {
+                Err("No se encontró la ventana principal.".into())
+            }
+        }
+        "set-zoom" => {
+            let requested = collected_args
+                .first()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| "Debes indicar un factor de zoom numérico.".to_string())?;
+            let factor = requested.clamp(MIN_ZOOM, MAX_ZOOM);
+            if let Some(window) = app.get_webview_window("main") {
+                window.set_zoom(factor).map_err(|err| {
+                    format!("El zoom no está disponible en esta plataforma: {err}")
+                })?;
+                *zoom.factor.lock().map_err(|err| err.to_string())? = factor;
+                Ok(ExecResponse {
+                    command,
+                    args: collected_args,
+                    cwd: None,
+                    exit_code: 0,
+                    stdout: factor.to_string(),
+                    stderr: String::new(),
+                    duration_ms: start.elapsed().as_millis(),
+                })
+            } else {
+                Err("No se encontró la ventana principal.".into())
+            }
+        }
+        "open-window" => {
+            let name = collected_args
+                .first()
+                .ok_or_else(|| "Debes indicar la etiqueta de la ventana.".to_string())?;
+            let label = project_window_label(name)?;
+            let root = safe_root()?;
+            let orbit = build_path(&root, collected_args.get(1).map(String::as_str))?;
+            if !orbit.is_dir() {
+                return Err("La ruta de la órbita no es un directorio.".into());
+            }
+            let orbit = relative_from_root(&root, &orbit)?;
+
+            // A second open of the same label just brings the window forward.
+            let stdout = if let Some(window) = app.get_webview_window(&label) {
+                window.show().map_err(|err| err.to_string())?;
+                window.set_focus().map_err(|err| err.to_string())?;
+                format!("{label} enfocada")
+            } else {
+                let url = format!("index.html?orbit={}", percent_encode(&orbit));
+                tauri::WebviewWindowBuilder::new(
+                    &app,
+                    label.clone(),
+                    tauri::WebviewUrl::App(url.into()),
+                )
+                .title(format!("Cerebro — {orbit}"))
+                .inner_size(1200.0, 800.0)
+                .build()
+                .map_err(|err| err.to_string())?;
+                format!("{label} abierta")
+            };
+            windows
+                .labels
+                .lock()
+                .map_err(|err| err.to_string())?
+                .insert(label);
+            Ok(ExecResponse {
+                command,
+                args: collected_args,
+                cwd: None,
+                exit_code: 0,
+                stdout,
+                stderr: String::new(),
+                duration_ms: start.elapsed().as_millis(),
+            })
+        }
+        "close-window" => {
+            let name = collected_args
+                .first()
+                .ok_or_else(|| "Debes indicar la etiqueta de la ventana.".to_string())?;
+            let label = project_window_label(name)?;
+            let window = app
+                .get_webview_window(&label)
+                .ok_or_else(|| "No existe ninguna ventana con esa etiqueta.".to_string())?;
+            window.close().map_err(|err| err.to_string())?;
+            windows
+                .labels
+                .lock()
+                .map_err(|err| err.to_string())?
+                .remove(&label);
+            Ok(ExecResponse {
+                command,
+                args: collected_args,
+                cwd: None,
+                exit_code: 0,
+                stdout: format!("{label} cerrada"),
+                stderr: String::new(),
+                duration_ms: start.elapsed().as_millis(),
+            })
+        }
+        "list-windows" => {
+            let mut labels = windows.labels.lock().map_err(|err| err.to_string())?;
+            labels.retain(|label| app.get_webview_window(label).is_some());
+            Ok(ExecResponse {
+                command,
+                args: collected_args,
+                cwd: None,
+                exit_code: 0,
+                stdout: labels.iter().cloned().collect::<Vec<_>>().join("\n"),
+                stderr: String::new(),
+                duration_ms: start.elapsed().as_millis(),
+            })
+        }
         _ => Err("Comando Tauri no soportado.".into()),
     }
 }
@@ -1100,7 +11737,18 @@ fn mcp_tauri_capabilities() -> McpResult<HashMap<&'static str, Vec<&'static str>
     let mut map = HashMap::new();
     map.insert(
         "commands",
-        vec!["show-main-window", "toggle-devtools", "set-always-on-top"],
+        vec![
+            "show-main-window",
+            "toggle-devtools",
+            "set-always-on-top",
+            "set-title",
+            "get-title",
+            "get-zoom",
+            "set-zoom",
+            "open-window",
+            "close-window",
+            "list-windows",
+        ],
     );
     Ok(map)
 }
@@ -1109,23 +11757,482 @@ fn mcp_tauri_capabilities() -> McpResult<HashMap<&'static str, Vec<&'static str>
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .manage(PtyState::default())
+        .manage(StreamRegistry::default())
+        .manage(GitWatchState::default())
+        .manage(JobRegistry::default())
+        .manage(IdState::default())
+        .manage(MetricsSubscribers::default())
+        .manage(LastExecStore::default())
+        .manage(HighlightState::default())
+        .manage(ZoomState::default())
+        .manage(CaseSensitivityState::default())
+        .manage(WindowRegistry::default())
         .invoke_handler(tauri::generate_handler![
             mcp_files_list,
+            mcp_files_rename_batch,
+            mcp_files_find_up,
+            mcp_files_to_relative,
+            mcp_files_normalize_dir,
+            mcp_files_case_sensitivity,
+            mcp_files_diff_listing,
+            mcp_files_tree,
+            mcp_files_tree_markdown,
+            mcp_files_stat_batch,
+            mcp_files_owner,
+            mcp_files_set_mtime,
+            mcp_files_wait_for,
+            mcp_files_read_env,
+            mcp_files_list_stream,
+            mcp_files_list_stream_stop,
+            mcp_files_read_chunks,
+            mcp_files_read_chunks_stop,
+            mcp_files_read_progress,
+            mcp_files_search_stream,
+            mcp_files_search_stop,
+            mcp_files_manifest_full,
             mcp_files_read,
             mcp_files_write,
+            mcp_files_delete,
+            mcp_files_move,
+            mcp_files_copy,
+            mcp_files_backups_list,
+            mcp_files_restore_backup,
+            mcp_files_diff_backup,
             mcp_files_info,
+            mcp_files_word_diff,
+            mcp_files_detect_charset,
+            mcp_files_detect_indent,
+            mcp_files_text_stats,
+            mcp_files_long_lines,
+            mcp_files_benchmark,
+            mcp_files_targz,
+            mcp_files_untargz,
+            mcp_files_quick_signature,
+            mcp_files_line_index,
+            mcp_files_read_around,
+            mcp_files_read_frontmatter,
+            mcp_files_outline,
+            mcp_files_tail_json,
+            mcp_files_merge_json,
+            mcp_files_highlight,
+            mcp_files_read_typed,
             mcp_git_exec,
             mcp_git_info,
+            mcp_git_diff_refs,
+            mcp_git_fsck,
+            mcp_git_config_get,
+            mcp_git_config_set,
+            mcp_git_blame,
+            mcp_git_show_file,
+            mcp_git_watch,
+            mcp_git_unwatch,
             mcp_shell_exec,
+            mcp_shell_pipeline,
+            mcp_shell_open_files,
+            mcp_shell_kill,
+            mcp_shell_stats,
+            mcp_exec_last,
+            mcp_exec_active,
+            mcp_exec_reap_orphans,
+            mcp_shell_exec_diagnostics,
             mcp_shell_capabilities,
+            mcp_security_policy,
+            mcp_shell_catalog,
+            mcp_config_get,
+            mcp_config_set,
+            mcp_shell_versions,
+            mcp_orbit_projects,
+            mcp_project_scripts,
+            mcp_project_dependencies,
+            mcp_project_loc,
+            mcp_project_run_script,
+            mcp_pty_spawn,
+            mcp_pty_write,
+            mcp_pty_resize,
+            mcp_pty_kill,
+            mcp_app_info,
             mcp_system_info,
+            mcp_system_mounts,
+            mcp_system_delta,
+            mcp_system_info_export,
+            mcp_support_report,
             mcp_system_paths,
             mcp_metrics_append,
+            mcp_metrics_subscribe,
+            mcp_metrics_unsubscribe,
             mcp_metrics_tail,
+            mcp_metrics_range,
+            mcp_metrics_files,
+            mcp_metrics_repair,
             mcp_metrics_clear,
+            mcp_app_logs,
+            mcp_util_uuid,
+            mcp_util_nanoid,
+            mcp_util_hex_encode,
+            mcp_util_hex_decode,
+            mcp_util_qrcode,
             mcp_tauri_exec,
             mcp_tauri_capabilities
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                app.state::<JobRegistry>().forget_pids();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir; removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or_default();
+            let path = env::temp_dir().join(format!(
+                "cerebro-test-{name}-{}-{nanos}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path.canonicalize().unwrap())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn untargz_rejects_chained_links_that_escape() {
+        let dir = TempDir::new("untargz-chain");
+        let archive_path = dir.0.join("chain.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive_path).unwrap(),
+            flate2::Compression::default(),
+        ));
+        for (member, link) in [("a/b/c/s", "../../.."), ("a/b/c/x", "s/../../..")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, member, link).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let target = dir.0.join("out");
+        fs::create_dir_all(&target).unwrap();
+        let (extracted, skipped) = extract_targz(
+            &dir.0,
+            &archive_path,
+            &target,
+            false,
+            DEFAULT_MAX_PATH_DEPTH,
+        )
+        .unwrap();
+
+        assert_eq!(extracted, vec!["a/b/c/s".to_string()]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, "a/b/c/x");
+        assert_eq!(skipped[0].reason, "unsafe-link");
+        assert!(fs::symlink_metadata(target.join("a/b/c/x")).is_err());
+    }
+
+    #[cfg(unix)]
+    fn orbit_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_args_inside_the_orbit_pass() {
+        let root = Path::new("/home/user/cerebro");
+        let project = root.join("project");
+        let args = orbit_args(&[
+            "src/main.rs",
+            "./README.md",
+            "../shared",
+            "--verbose",
+            "build",
+        ]);
+        assert!(validate_path_args(root, &project, &args).is_ok());
+        assert!(validate_path_args(root, &project, &orbit_args(&["--out=../dist"])).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_args_escaping_the_orbit_are_rejected() {
+        let root = Path::new("/home/user/cerebro");
+        let project = root.join("project");
+        for arg in ["../..", "../../.ssh/id_rsa", "/etc", "/etc/passwd"] {
+            assert!(
+                validate_path_args(root, &project, &orbit_args(&[arg])).is_err(),
+                "{arg} should be rejected"
+            );
+        }
+        assert!(validate_path_args(root, root, &orbit_args(&["--flag=../x"])).is_err());
+        assert!(validate_path_args(root, &project, &orbit_args(&["--flag=../../x"])).is_err());
+    }
+
+    fn ignore_rules(lines: &[&str]) -> IgnoreRules {
+        IgnoreRules {
+            rules: lines
+                .iter()
+                .filter_map(|line| IgnorePattern::parse(line))
+                .map(|pattern| IgnoreRule {
+                    base: String::new(),
+                    pattern,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn ignore_comments_and_blanks_are_skipped() {
+        assert!(IgnorePattern::parse("# build output").is_none());
+        assert!(IgnorePattern::parse("   ").is_none());
+        assert!(IgnorePattern::parse("\\#literal").is_some());
+    }
+
+    #[test]
+    fn ignore_negation_reincludes_a_file() {
+        let rules = ignore_rules(&["*.log", "!keep.log"]);
+        assert!(rules.is_ignored("debug.log", false));
+        assert!(rules.is_ignored("logs/debug.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+    }
+
+    #[test]
+    fn ignore_dir_only_patterns_skip_files() {
+        let rules = ignore_rules(&["build/"]);
+        assert!(rules.is_ignored("build", true));
+        assert!(rules.is_ignored("src/build", true));
+        assert!(rules.is_ignored("build/out.js", false));
+        assert!(!rules.is_ignored("build", false));
+        assert!(!rules.is_ignored("src/build", false));
+    }
+
+    #[test]
+    fn ignore_anchored_patterns_match_from_the_base() {
+        let rules = ignore_rules(&["/target", "docs/*.md"]);
+        assert!(rules.is_ignored("target", true));
+        assert!(!rules.is_ignored("crates/target", true));
+        assert!(rules.is_ignored("docs/intro.md", false));
+        assert!(!rules.is_ignored("src/docs/intro.md", false));
+        assert!(!rules.is_ignored("docs/guide/intro.md", false));
+    }
+
+    #[test]
+    fn ignore_double_star_spans_directories() {
+        let rules = ignore_rules(&["**/cache", "a/**/b", "logs/**"]);
+        assert!(rules.is_ignored("cache", true));
+        assert!(rules.is_ignored("x/y/cache", true));
+        assert!(rules.is_ignored("a/b", false));
+        assert!(rules.is_ignored("a/x/y/b", false));
+        assert!(rules.is_ignored("logs/2024/app.txt", false));
+        assert!(!rules.is_ignored("other/b", false));
+
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/a/b/lib.rs"));
+        assert!(!glob_match("src/*.rs", "src/a/lib.rs"));
+    }
+
+    // Walks `dir` following symlinks, guarded only by `visited`.
+    #[cfg(unix)]
+    fn walk_following_links(
+        root: &Path,
+        dir: &Path,
+        visited: &mut VisitedDirs,
+        entered: &mut Vec<String>,
+    ) {
+        if !visited.enter(root, dir) {
+            return;
+        }
+        entered.push(relative_from_root(root, dir).unwrap());
+        let mut children: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect();
+        children.sort();
+        for child in children {
+            walk_following_links(root, &child, visited, entered);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn visited_dirs_stop_a_symlink_loop() {
+        let dir = TempDir::new("visited-loop");
+        fs::create_dir_all(dir.0.join("a/b")).unwrap();
+        std::os::unix::fs::symlink("..", dir.0.join("a/loop")).unwrap();
+
+        let mut visited = VisitedDirs::default();
+        let mut entered = Vec::new();
+        walk_following_links(&dir.0, &dir.0, &mut visited, &mut entered);
+
+        assert_eq!(entered, vec![".", "a", "a/b"]);
+        assert_eq!(visited.cycles, vec!["a/loop".to_string()]);
+
+        // The tree walk never follows the link at all, and still finishes.
+        let mut filter = IgnoreFilter::new(&dir.0, &dir.0, false);
+        let mut visited = VisitedDirs::default();
+        let (mut nodes, mut truncated) = (0, false);
+        let tree = build_tree_node(
+            &dir.0,
+            &dir.0,
+            0,
+            MAX_TREE_DEPTH,
+            &mut filter,
+            &mut visited,
+            &mut nodes,
+            &mut truncated,
+        )
+        .unwrap();
+        assert_eq!(nodes, 3);
+        assert!(!truncated);
+        assert_eq!(tree.children.unwrap()[0].path, "a");
+    }
+
+    #[test]
+    fn path_depth_limit_rejects_deep_paths() {
+        let root = Path::new("orbit");
+        assert!(check_path_depth(root, &root.join("a/b"), 2).is_ok());
+        assert!(check_path_depth(root, &root.join("a/b/c"), 2).is_err());
+
+        let shallow = Path::new("project/src/main.rs");
+        assert_eq!(
+            sanitize_path_with_depth(root, shallow, 3).unwrap(),
+            root.join("project/src/main.rs")
+        );
+        assert!(sanitize_path_with_depth(root, shallow, 2).is_err());
+        // `..` is resolved before the depth is measured.
+        let folded = Path::new("a/b/../../project/src/main.rs");
+        assert!(sanitize_path_with_depth(root, folded, 3).is_ok());
+
+        let deep: PathBuf = (0..DEFAULT_MAX_PATH_DEPTH + 1)
+            .map(|level| format!("d{level}"))
+            .collect();
+        assert!(sanitize_path_with_depth(root, &deep, DEFAULT_MAX_PATH_DEPTH).is_err());
+        assert!(
+            sanitize_path_with_depth(root, deep.parent().unwrap(), DEFAULT_MAX_PATH_DEPTH).is_ok()
+        );
+    }
+
+    #[test]
+    fn case_only_renames_are_detected() {
+        let dir = Path::new("notes");
+        assert!(is_case_only_rename(
+            &dir.join("Readme.md"),
+            &dir.join("README.md")
+        ));
+        assert!(!is_case_only_rename(
+            &dir.join("README.md"),
+            &dir.join("README.md")
+        ));
+        assert!(!is_case_only_rename(
+            &dir.join("Readme.md"),
+            &Path::new("docs").join("README.md")
+        ));
+        assert!(!is_case_only_rename(
+            &dir.join("Readme.md"),
+            &dir.join("Readme.txt")
+        ));
+    }
+
+    fn dir_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn case_only_rename_round_trip() {
+        let dir = TempDir::new("case-rename");
+        let original = dir.0.join("Readme.md");
+        let upper = dir.0.join("README.md");
+        fs::write(&original, "hola").unwrap();
+
+        rename_path(&original, &upper).unwrap();
+        assert_eq!(dir_names(&dir.0), vec!["README.md"]);
+        assert_eq!(fs::read_to_string(&upper).unwrap(), "hola");
+
+        rename_path(&upper, &original).unwrap();
+        assert_eq!(dir_names(&dir.0), vec!["Readme.md"]);
+        assert_eq!(fs::read_to_string(&original).unwrap(), "hola");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn written_files_get_the_requested_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("write-mode");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let secret = dir.0.join("secret.env");
+        write_file_contents(&secret, b"TOKEN=1", true, Some(0o600)).unwrap();
+        assert_eq!(mode(&secret), 0o600);
+
+        let shared = dir.0.join("shared.txt");
+        fs::write(&shared, "old").unwrap();
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o644)).unwrap();
+        write_file_contents(&shared, b"new", false, Some(0o600)).unwrap();
+        assert_eq!(mode(&shared), 0o600);
+        assert_eq!(fs::read_to_string(&shared).unwrap(), "new");
+
+        assert!(write_file_contents(&secret, b"again", true, None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parents_behind_an_escaping_link_are_rejected() {
+        let dir = TempDir::new("parent-link");
+        let orbit = dir.0.join("orbit");
+        let outside = dir.0.join("outside");
+        fs::create_dir_all(orbit.join("inner")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, orbit.join("ext")).unwrap();
+        std::os::unix::fs::symlink("inner", orbit.join("alias")).unwrap();
+
+        assert!(ensure_parent_inside(&orbit, &orbit.join("inner/file.txt")).is_ok());
+        assert!(ensure_parent_inside(&orbit, &orbit.join("alias/file.txt")).is_ok());
+        assert!(ensure_parent_inside(&orbit, &orbit.join("ext")).is_ok());
+        assert!(ensure_parent_inside(&orbit, &orbit.join("ext/file.txt")).is_err());
+    }
+
+    #[test]
+    fn unexpected_exit_serializes_as_an_object() {
+        let response = ExecResponse {
+            command: "cargo".to_string(),
+            args: vec!["test".to_string()],
+            cwd: None,
+            exit_code: 101,
+            stdout: "running 3 tests".to_string(),
+            stderr: "test failed".to_string(),
+            duration_ms: 10,
+        };
+        let error = serde_json::to_value(unexpected_exit_error(response, 0)).unwrap();
+        assert_eq!(error["kind"], "unexpectedExit");
+        assert_eq!(error["expected"], 0);
+        assert_eq!(error["actual"], 101);
+        assert_eq!(error["stdout"], "running 3 tests");
+        assert_eq!(error["stderr"], "test failed");
+
+        let message = serde_json::to_value(ShellExecError::from("Comando no permitido.")).unwrap();
+        assert_eq!(message, "Comando no permitido.");
+    }
 }